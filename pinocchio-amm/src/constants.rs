@@ -1 +1,18 @@
 pub const CONFIG_SEED: &'static str = "config";
+pub const MAX_FEE: u16 = 1_000;
+pub const LP_SEED: &str = "lp";
+pub const VAULT_SEED: &str = "vault";
+pub const LP_DECIMALS: u8 = 6;
+pub const OBSERVATION_COUNT: usize = 8;
+pub const OBSERVATION_INTERVAL: i64 = 300;
+pub const MAX_AMP: u64 = 10_000;
+pub const MINIMUM_LIQUIDITY: u64 = 1_000;
+pub const POOL_SEED: &str = "pool";
+pub const REGISTRY_SEED: &str = "registry";
+pub const CONFIG_DISCRIMINATOR: [u8; 8] = *b"ammconfg";
+pub const CONFIG_VERSION: u8 = 5;
+pub const PRICE_SAMPLE_COUNT: usize = 8;
+pub const PRICE_SAMPLE_INTERVAL: i64 = 60;
+pub const VOLATILITY_WINDOW: i64 = PRICE_SAMPLE_COUNT as i64 * PRICE_SAMPLE_INTERVAL;
+pub const STATS_SEED: &str = "stats";
+pub const MIN_WEIGHT: u16 = 100;
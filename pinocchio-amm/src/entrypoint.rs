@@ -26,5 +26,33 @@ fn process_instruction(
         AMMInstruction::Deposit => instruction::process_deposit(accounts, &instruction_data),
         AMMInstruction::Swap => instruction::process_swap(accounts, &instruction_data),
         AMMInstruction::Withdraw => instruction::process_withdraw(accounts, &instruction_data),
+        AMMInstruction::ClaimFees => instruction::process_claim_fees(accounts),
+        AMMInstruction::UpdateConfig => {
+            instruction::process_update_config(accounts, &instruction_data)
+        }
+        AMMInstruction::SwapExactOut => {
+            instruction::process_swap_exact_out(accounts, &instruction_data)
+        }
+        AMMInstruction::GetTwap => instruction::process_get_twap(accounts, &instruction_data),
+        AMMInstruction::FlashLoan => instruction::process_flash_loan(accounts, &instruction_data),
+        AMMInstruction::FlashRepay => instruction::process_flash_repay(accounts),
+        AMMInstruction::RouteSwap => instruction::process_route_swap(accounts, &instruction_data),
+        AMMInstruction::Quote => instruction::process_quote(accounts, &instruction_data),
+        AMMInstruction::DepositSingle => {
+            instruction::process_deposit_single(accounts, &instruction_data)
+        }
+        AMMInstruction::ProposeAuthority => {
+            instruction::process_propose_authority(accounts, &instruction_data)
+        }
+        AMMInstruction::AcceptAuthority => instruction::process_accept_authority(accounts),
+        AMMInstruction::RenounceAuthority => instruction::process_renounce_authority(accounts),
+        AMMInstruction::ClosePool => instruction::process_close_pool(accounts, &instruction_data),
+        AMMInstruction::MigrateConfig => instruction::process_migrate_config(accounts),
+        AMMInstruction::UpdateDynamicFee => {
+            instruction::process_update_dynamic_fee(accounts, &instruction_data)
+        }
+        AMMInstruction::InitializeStats => {
+            instruction::process_initialize_stats(accounts, &instruction_data)
+        }
     }
 }
@@ -5,6 +5,25 @@ pub enum CustomError {
     InvalidDeposit,
     InvalidSwap,
     InvalidWithdrawal,
+    PoolLocked,
+    InvalidFee,
+    DepositSlippageExceeded,
+    WithdrawSlippageExceeded,
+    MintMismatch,
+    VaultMintMismatch,
+    DuplicateAccount,
+    SwapSlippageExceeded,
+    OracleWindowUnavailable,
+    InvalidCurve,
+    FlashLoanNotRepaid,
+    RouteMintMismatch,
+    InvalidMintOrder,
+    AuthorityRenounced,
+    PoolNotEmpty,
+    UnsupportedConfigVersion,
+    DeadlineExceeded,
+    UnmigratableConfig,
+    UnsupportedMintExtension,
 }
 
 impl From<CustomError> for ProgramError {
@@ -0,0 +1,91 @@
+//! Events logged by the pool instructions, for indexers.
+//!
+//! Each event is logged with `sol_log_data` as two fields: its `EventKind` byte, then the event
+//! struct bytes. Off-chain code decodes the second field with `bytemuck::from_bytes`.
+
+use bytemuck::{Pod, Zeroable};
+use pinocchio::{log::sol_log_data, program_error::ProgramError};
+
+#[repr(u8)]
+pub enum EventKind {
+    Swap,
+    Deposit,
+    Withdraw,
+}
+
+impl TryFrom<&u8> for EventKind {
+    type Error = ProgramError;
+
+    fn try_from(value: &u8) -> Result<Self, Self::Error> {
+        match *value {
+            0 => Ok(EventKind::Swap),
+            1 => Ok(EventKind::Deposit),
+            2 => Ok(EventKind::Withdraw),
+            _ => Err(ProgramError::InvalidArgument),
+        }
+    }
+}
+
+pub trait Event: Pod {
+    const KIND: EventKind;
+
+    fn emit(&self) {
+        sol_log_data(&[&[Self::KIND as u8], bytemuck::bytes_of(self)]);
+    }
+}
+
+/// A swap of `amount_in` of the input token, `mint_x` when `is_x` is 1, for `amount_out` of the
+/// other one. `fee` is the part of `amount_in` kept by the pool and the protocol. The reserves
+/// exclude unclaimed protocol fees and are taken after the swap.
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct SwapEvent {
+    pub pool: [u8; 32],
+    pub user: [u8; 32],
+    pub is_x: u8,
+    pub amount_in: [u8; 8],
+    pub amount_out: [u8; 8],
+    pub fee: [u8; 8],
+    pub reserve_x: [u8; 8],
+    pub reserve_y: [u8; 8],
+}
+
+impl Event for SwapEvent {
+    const KIND: EventKind = EventKind::Swap;
+}
+
+/// A deposit of `amount_x` and `amount_y` for `amount_lp` LP tokens. Deposits are not charged a
+/// fee.
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct DepositEvent {
+    pub pool: [u8; 32],
+    pub user: [u8; 32],
+    pub amount_x: [u8; 8],
+    pub amount_y: [u8; 8],
+    pub amount_lp: [u8; 8],
+    pub reserve_x: [u8; 8],
+    pub reserve_y: [u8; 8],
+}
+
+impl Event for DepositEvent {
+    const KIND: EventKind = EventKind::Deposit;
+}
+
+/// A withdrawal of `amount_x` and `amount_y` for `amount_lp` burned LP tokens. Withdrawals are
+/// not charged a fee.
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct WithdrawEvent {
+    pub pool: [u8; 32],
+    pub user: [u8; 32],
+    pub amount_x: [u8; 8],
+    pub amount_y: [u8; 8],
+    pub amount_lp: [u8; 8],
+    pub reserve_x: [u8; 8],
+    pub reserve_y: [u8; 8],
+}
+
+impl Event for WithdrawEvent {
+    const KIND: EventKind = EventKind::Withdraw;
+}
@@ -0,0 +1,32 @@
+use pinocchio::{
+    account_info::AccountInfo, program_error::ProgramError, pubkey::Pubkey, ProgramResult,
+};
+
+use crate::{state::Config, validation::validate_config};
+
+pub fn process_accept_authority(accounts: &[AccountInfo]) -> ProgramResult {
+    let [new_authority, config] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if !new_authority.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let config_account = Config::load(config)?;
+
+    // Validate pool accounts.
+    validate_config(config, config_account)?;
+
+    // Only the proposed authority can take over, which proves it can sign for the pool.
+    if config_account.pending_authority == Pubkey::default()
+        || config_account.pending_authority.ne(new_authority.key())
+    {
+        return Err(ProgramError::IncorrectAuthority);
+    }
+
+    config_account.authority = config_account.pending_authority;
+    config_account.pending_authority = Pubkey::default();
+
+    Ok(())
+}
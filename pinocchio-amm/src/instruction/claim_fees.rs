@@ -0,0 +1,81 @@
+use pinocchio::{
+    account_info::AccountInfo,
+    instruction::{Seed, Signer},
+    program_error::ProgramError,
+    ProgramResult,
+};
+
+use crate::{
+    state::Config,
+    token::{self, Mint},
+    validation::{
+        validate_authority, validate_config, validate_mint_token_program, validate_mints,
+        validate_vaults,
+    },
+};
+
+pub fn process_claim_fees(accounts: &[AccountInfo]) -> ProgramResult {
+    let [authority, mint_x, mint_y, vault_x, vault_y, treasury_x, treasury_y, config, token_program_x, token_program_y] =
+        accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if !authority.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let config_account = Config::load(config)?;
+
+    // Validate pool accounts.
+    validate_config(config, config_account)?;
+    validate_authority(config_account, authority)?;
+    validate_mints(config_account, mint_x, mint_y)?;
+    validate_vaults(config, config_account, mint_x, mint_y, vault_x, vault_y)?;
+    validate_mint_token_program(mint_x, token_program_x)?;
+    validate_mint_token_program(mint_y, token_program_y)?;
+
+    let mint_x_account = Mint::from_account_info(mint_x)?;
+    let mint_y_account = Mint::from_account_info(mint_y)?;
+
+    let fees_x = u64::from_le_bytes(config_account.protocol_fees_x);
+    let fees_y = u64::from_le_bytes(config_account.protocol_fees_y);
+
+    // Reset the accrued fees before moving them out of the vaults.
+    config_account.protocol_fees_x = 0u64.to_le_bytes();
+    config_account.protocol_fees_y = 0u64.to_le_bytes();
+
+    // Setup signer seeds.
+    let seed = config_account.signer_seeds().map(Seed::from);
+    let seeds = Signer::from(&seed);
+
+    // Claim mint_x fees.
+    if fees_x > 0 {
+        token::instructions::TransferChecked {
+            from: vault_x,
+            mint: mint_x,
+            to: treasury_x,
+            authority: config,
+            amount: fees_x,
+            decimals: mint_x_account.decimals(),
+            token_program: token_program_x.key(),
+        }
+        .invoke_signed(&[seeds.clone()])?;
+    }
+
+    // Claim mint_y fees.
+    if fees_y > 0 {
+        token::instructions::TransferChecked {
+            from: vault_y,
+            mint: mint_y,
+            to: treasury_y,
+            authority: config,
+            amount: fees_y,
+            decimals: mint_y_account.decimals(),
+            token_program: token_program_y.key(),
+        }
+        .invoke_signed(&[seeds.clone()])?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,182 @@
+use bytemuck::{Pod, Zeroable};
+use pinocchio::{
+    account_info::AccountInfo,
+    instruction::{Seed, Signer},
+    program_error::ProgramError,
+    pubkey,
+    sysvars::{rent::Rent, Sysvar},
+    ProgramResult,
+};
+
+use crate::{
+    constants::REGISTRY_SEED,
+    error::CustomError,
+    state::{Config, Registry, Stats},
+    token::{self, Mint, TokenAccount},
+    validation::{
+        validate_authority, validate_config, validate_mint_lp, validate_mint_token_program,
+        validate_mints, validate_stats, validate_vault_lp, validate_vaults,
+    },
+};
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct ClosePoolInstructionData {
+    pub registry_bump: u8,
+}
+
+impl ClosePoolInstructionData {
+    pub const LEN: usize = core::mem::size_of::<ClosePoolInstructionData>();
+}
+
+/// Closes a pool once every LP token but the locked minimum liquidity has been withdrawn. The
+/// locked liquidity belongs to no one, so the reserves behind it, along with any unclaimed protocol
+/// fees, are swept to the authority.
+pub fn process_close_pool(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let [authority, mint_x, mint_y, vault_x, vault_y, treasury_x, treasury_y, mint_lp, vault_lp, config, registry, token_program_x, token_program_y, stats] =
+        accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if !authority.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let instruction_data = bytemuck::try_from_bytes::<ClosePoolInstructionData>(&data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+
+    let config_account = Config::load(config)?;
+    if config_account.flash_loan != 0 {
+        return Err(CustomError::PoolLocked.into());
+    }
+
+    // Validate pool accounts.
+    validate_config(config, config_account)?;
+    validate_authority(config_account, authority)?;
+    validate_mints(config_account, mint_x, mint_y)?;
+    let (vault_x_amount, vault_y_amount) =
+        validate_vaults(config, config_account, mint_x, mint_y, vault_x, vault_y)?;
+    let mint_lp_supply = validate_mint_lp(config, config_account, mint_lp)?;
+    validate_vault_lp(config, config_account, mint_lp, vault_lp)?;
+    validate_mint_token_program(mint_x, token_program_x)?;
+    validate_mint_token_program(mint_y, token_program_y)?;
+    validate_stats(config, Stats::load(stats)?)?;
+
+    // Validate registry account.
+    let registry_pda = pubkey::create_program_address(
+        &[REGISTRY_SEED.as_bytes(), &[instruction_data.registry_bump]],
+        &crate::ID,
+    )?;
+    if registry.key() != &registry_pda {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    // Every LP token but the locked minimum liquidity must have been withdrawn.
+    let locked = TokenAccount::from_account_info(vault_lp)?.amount();
+    if mint_lp_supply != locked {
+        return Err(CustomError::PoolNotEmpty.into());
+    }
+
+    // Setup signer seeds.
+    let seed = config_account.signer_seeds().map(Seed::from);
+    let seeds = Signer::from(&seed);
+
+    // Burn the locked minimum liquidity. The LP mint itself cannot be closed, and is taken over
+    // again if the pool is reopened.
+    if locked > 0 {
+        token::instructions::Burn {
+            account: vault_lp,
+            mint: mint_lp,
+            authority: config,
+            amount: locked,
+            token_program: token_program_x.key(),
+        }
+        .invoke_signed(&[seeds.clone()])?;
+    }
+
+    // Sweep what is left in the vaults, the reserves behind the locked liquidity and any
+    // unclaimed protocol fees, to the authority.
+    for (vault, mint, treasury, amount, token_program) in [
+        (vault_x, mint_x, treasury_x, vault_x_amount, token_program_x),
+        (vault_y, mint_y, treasury_y, vault_y_amount, token_program_y),
+    ] {
+        if amount > 0 {
+            let decimals = Mint::from_account_info(mint)?.decimals();
+            token::instructions::TransferChecked {
+                from: vault,
+                mint,
+                to: treasury,
+                authority: config,
+                amount,
+                decimals,
+                token_program: token_program.key(),
+            }
+            .invoke_signed(&[seeds.clone()])?;
+        }
+    }
+
+    // Close the vaults, returning their rent to the authority.
+    for (vault, token_program) in [
+        (vault_x, token_program_x),
+        (vault_y, token_program_y),
+        (vault_lp, token_program_x),
+    ] {
+        token::instructions::CloseAccount {
+            account: vault,
+            destination: authority,
+            authority: config,
+            token_program: token_program.key(),
+        }
+        .invoke_signed(&[seeds.clone()])?;
+    }
+
+    unregister_pool(authority, config, registry)?;
+
+    // Close the stats and config accounts, returning their rent to the authority.
+    for account in [stats, config] {
+        *authority.try_borrow_mut_lamports()? += account.lamports();
+        *account.try_borrow_mut_lamports()? = 0;
+        account.close()?;
+    }
+
+    Ok(())
+}
+
+/// Removes the pool at `config` from the registry, keeping the other pools in order, and refunds
+/// the rent the registry no longer needs to `authority`. Pools opened before the registry are not
+/// listed in it, and leave it as it is.
+fn unregister_pool(
+    authority: &AccountInfo,
+    config: &AccountInfo,
+    registry: &AccountInfo,
+) -> ProgramResult {
+    if registry.data_len() == 0 {
+        return Ok(());
+    }
+    if !registry.is_owned_by(&crate::ID) {
+        return Err(ProgramError::InvalidAccountOwner);
+    }
+
+    let (registry_state, pools) = Registry::load(registry)?;
+    let pool_count = u64::from_le_bytes(registry_state.pool_count);
+    let pools = pools
+        .get_mut(..pool_count as usize)
+        .ok_or(ProgramError::InvalidAccountData)?;
+    let Some(index) = pools.iter().position(|pool| pool == config.key()) else {
+        return Ok(());
+    };
+    pools.copy_within(index + 1.., index);
+    registry_state.pool_count = (pool_count - 1).to_le_bytes();
+
+    // Shrink the registry and refund the rent it no longer needs.
+    let space = Registry::space(pool_count - 1);
+    registry.realloc(space, true)?;
+    let refund = registry
+        .lamports()
+        .saturating_sub(Rent::get()?.minimum_balance(space));
+    *registry.try_borrow_mut_lamports()? -= refund;
+    *authority.try_borrow_mut_lamports()? += refund;
+
+    Ok(())
+}
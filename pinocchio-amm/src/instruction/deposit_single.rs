@@ -0,0 +1,240 @@
+use bytemuck::{Pod, Zeroable};
+use pinocchio::{
+    account_info::AccountInfo,
+    instruction::{Seed, Signer},
+    program_error::ProgramError,
+    sysvars::{clock::Clock, Sysvar},
+    ProgramResult,
+};
+
+use crate::{
+    error::CustomError,
+    events::{DepositEvent, Event, SwapEvent},
+    instruction::swap::swap_amount_out,
+    math::{amount_after_fee, deposit_amounts, deposit_liquidity},
+    state::{Config, Stats},
+    token::{self, Mint},
+    validation::{
+        validate_config, validate_mint_lp, validate_mint_token_program, validate_mints,
+        validate_stats, validate_token_account, validate_vaults,
+    },
+};
+
+/// Deposit `amount` of `mint_x` when `is_x` is 1, or of `mint_y` otherwise, for at least `min`
+/// LP tokens.
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct DepositSingleInstructionData {
+    pub is_x: u8,
+    pub amount: [u8; 8],
+    pub min: [u8; 8],
+}
+
+impl DepositSingleInstructionData {
+    pub const LEN: usize = core::mem::size_of::<DepositSingleInstructionData>();
+}
+
+pub fn process_deposit_single(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let [user, mint_x, mint_y, vault_x, vault_y, user_in, mint_lp, user_lp, config, _system_program, token_program_x, token_program_y, stats] =
+        accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if !user.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let instruction_data = bytemuck::try_from_bytes::<DepositSingleInstructionData>(&data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+
+    let is_x = match instruction_data.is_x {
+        0 => false,
+        1 => true,
+        _ => return Err(ProgramError::InvalidInstructionData),
+    };
+
+    let config_account = Config::load(config)?;
+    if config_account.locked != 0 || config_account.flash_loan != 0 {
+        return Err(CustomError::PoolLocked.into());
+    }
+
+    // Validate pool accounts.
+    validate_config(config, config_account)?;
+    validate_mints(config_account, mint_x, mint_y)?;
+    let (vault_x_amount, vault_y_amount) =
+        validate_vaults(config, config_account, mint_x, mint_y, vault_x, vault_y)?;
+    let (vault_x_amount, vault_y_amount) =
+        config_account.reserves(vault_x_amount, vault_y_amount)?;
+    let mint_lp_supply = validate_mint_lp(config, config_account, mint_lp)?;
+    validate_mint_token_program(mint_x, token_program_x)?;
+    validate_mint_token_program(mint_y, token_program_y)?;
+    let stats_account = Stats::load(stats)?;
+    validate_stats(config, stats_account)?;
+
+    // The first deposit sets the price, so it needs both tokens.
+    if mint_lp_supply == 0 {
+        return Err(CustomError::InvalidDeposit.into());
+    }
+
+    // Accrue the price oracle with the reserves from before this instruction.
+    let clock = Clock::get()?;
+    config_account.update_oracle(vault_x_amount, vault_y_amount, clock.unix_timestamp);
+
+    // Validate user accounts.
+    validate_token_account(user_in, user)?;
+    validate_token_account(user_lp, user)?;
+
+    let (reserve_in, reserve_out) = match is_x {
+        true => (vault_x_amount, vault_y_amount),
+        false => (vault_y_amount, vault_x_amount),
+    };
+    let (vault_in, mint_in, token_program_in) = match is_x {
+        true => (vault_x, mint_x, token_program_x),
+        false => (vault_y, mint_y, token_program_y),
+    };
+
+    let decimals = Mint::from_account_info(mint_in)?.decimals();
+
+    // Deposit the input tokens first, so the split is worked out on what reaches the vault, net
+    // of any transfer fee.
+    let amount = token::instructions::TransferChecked {
+        from: user_in,
+        mint: mint_in,
+        to: vault_in,
+        authority: user,
+        amount: u64::from_le_bytes(instruction_data.amount),
+        decimals,
+        token_program: token_program_in.key(),
+    }
+    .invoke_signed_received(&[])?;
+
+    let (swap_in, lp) = split_deposit(
+        config_account,
+        is_x,
+        reserve_in,
+        reserve_out,
+        mint_lp_supply,
+        amount,
+    )?;
+
+    // Slippage protection.
+    if lp < u64::from_le_bytes(instruction_data.min) {
+        return Err(CustomError::DepositSlippageExceeded.into());
+    }
+
+    // Swap `swap_in` against the pool, then deposit the rest alongside the swap output. Neither
+    // leaves the vaults, and what the deposit does not need of either stays in the pool.
+    let swap_out = swap_amount_out(config_account, is_x, reserve_in, reserve_out, swap_in)?;
+    let protocol_fee = config_account.accrue_protocol_fee(is_x, swap_in)?;
+    let swap_reserve_in = reserve_in + swap_in - protocol_fee;
+    let swap_reserve_out = reserve_out - swap_out;
+    let (deposit_in, deposit_out) =
+        deposit_amounts(swap_reserve_in, swap_reserve_out, mint_lp_supply, lp)
+            .ok_or(CustomError::InvalidDeposit)?;
+
+    // Setup signer seeds.
+    let seed = config_account.signer_seeds().map(Seed::from);
+    let seeds = Signer::from(&seed);
+
+    // Mint mint_lp tokens.
+    token::instructions::MintTo {
+        mint: mint_lp,
+        account: user_lp,
+        mint_authority: config,
+        amount: lp,
+        token_program: token_program_x.key(),
+    }
+    .invoke_signed(&[seeds])?;
+
+    // Log the swap and the deposit for indexers, and count them in the pool statistics.
+    let order = |amount_in: u64, amount_out: u64| match is_x {
+        true => (amount_in, amount_out),
+        false => (amount_out, amount_in),
+    };
+    let fee = swap_in
+        - amount_after_fee(swap_in, config_account.swap_fee()).ok_or(CustomError::InvalidSwap)?;
+    let (reserve_x, reserve_y) = order(swap_reserve_in, swap_reserve_out);
+    SwapEvent {
+        pool: *config.key(),
+        user: *user.key(),
+        is_x: is_x as u8,
+        amount_in: swap_in.to_le_bytes(),
+        amount_out: swap_out.to_le_bytes(),
+        fee: fee.to_le_bytes(),
+        reserve_x: reserve_x.to_le_bytes(),
+        reserve_y: reserve_y.to_le_bytes(),
+    }
+    .emit();
+    let (amount_x, amount_y) = order(deposit_in, deposit_out);
+    let (reserve_x, reserve_y) = order(reserve_in + amount - protocol_fee, reserve_out);
+    DepositEvent {
+        pool: *config.key(),
+        user: *user.key(),
+        amount_x: amount_x.to_le_bytes(),
+        amount_y: amount_y.to_le_bytes(),
+        amount_lp: lp.to_le_bytes(),
+        reserve_x: reserve_x.to_le_bytes(),
+        reserve_y: reserve_y.to_le_bytes(),
+    }
+    .emit();
+    stats_account.record_swap(is_x, swap_in, swap_out, fee, clock.slot);
+    stats_account.record_deposit();
+
+    Ok(())
+}
+
+/// Splits `amount` of the input token into a swap and a deposit of what is left with the swap
+/// output. Returns the swap input and the LP minted for the deposit, which the split maximises.
+fn split_deposit(
+    config_account: &Config,
+    is_x: bool,
+    reserve_in: u64,
+    reserve_out: u64,
+    supply: u64,
+    amount: u64,
+) -> Result<(u64, u64), ProgramError> {
+    // Returns the swap output and the reserves after swapping `swap_in`.
+    let after_swap = |swap_in: u64| -> Option<(u64, u64, u64)> {
+        let swap_out = match swap_in {
+            0 => 0,
+            _ => {
+                swap_amount_out(config_account, is_x, reserve_in, reserve_out, swap_in).unwrap_or(0)
+            }
+        };
+        let reserve_in =
+            reserve_in.checked_add(swap_in - config_account.protocol_fee_amount(swap_in))?;
+        Some((swap_out, reserve_in, reserve_out.checked_sub(swap_out)?))
+    };
+
+    // The LP minted falls on the input side and rises on the output side as more is swapped.
+    // Find the first split where the input left over no longer exceeds the pool ratio.
+    let (mut low, mut high) = (0, amount);
+    while low < high {
+        let middle = low + (high - low) / 2;
+        let (swap_out, reserve_in, reserve_out) =
+            after_swap(middle).ok_or(CustomError::InvalidDeposit)?;
+        if (amount - middle) as u128 * reserve_out as u128 > swap_out as u128 * reserve_in as u128 {
+            low = middle + 1;
+        } else {
+            high = middle;
+        }
+    }
+
+    // The best split is either side of the crossing.
+    let mut best = (0, 0);
+    for swap_in in [low.saturating_sub(1), low] {
+        let (swap_out, reserve_in, reserve_out) =
+            after_swap(swap_in).ok_or(CustomError::InvalidDeposit)?;
+        let lp = deposit_liquidity(reserve_in, reserve_out, supply, amount - swap_in, swap_out)
+            .ok_or(CustomError::InvalidDeposit)?;
+        if lp > best.1 {
+            best = (swap_in, lp);
+        }
+    }
+
+    match best {
+        (_, 0) => Err(CustomError::InvalidDeposit.into()),
+        best => Ok(best),
+    }
+}
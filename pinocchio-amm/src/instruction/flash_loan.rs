@@ -0,0 +1,155 @@
+use bytemuck::{Pod, Zeroable};
+use pinocchio::{
+    account_info::AccountInfo,
+    instruction::{Seed, Signer},
+    program_error::ProgramError,
+    sysvars::{clock::Clock, instructions::Instructions, Sysvar},
+    ProgramResult,
+};
+
+use crate::{
+    error::CustomError,
+    instruction::AMMInstruction,
+    math::amount_before_fee,
+    state::{Config, Stats},
+    token::{self, Mint},
+    validation::{
+        validate_config, validate_mint_token_program, validate_mints, validate_stats,
+        validate_token_account, validate_vaults,
+    },
+};
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct FlashLoanInstructionData {
+    pub amount_x: [u8; 8],
+    pub amount_y: [u8; 8],
+}
+
+impl FlashLoanInstructionData {
+    pub const LEN: usize = core::mem::size_of::<FlashLoanInstructionData>();
+}
+
+/// Position of the config account in the `FlashRepay` accounts.
+const REPAY_CONFIG_INDEX: usize = 4;
+
+pub fn process_flash_loan(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let [user, mint_x, mint_y, vault_x, vault_y, user_x, user_y, config, instructions_sysvar, token_program_x, token_program_y, stats] =
+        accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if !user.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let instruction_data = bytemuck::try_from_bytes::<FlashLoanInstructionData>(&data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+
+    // The pool stays locked until the loan is repaid, which also rules out nested loans.
+    let config_account = Config::load(config)?;
+    if config_account.locked != 0 || config_account.flash_loan != 0 {
+        return Err(CustomError::PoolLocked.into());
+    }
+
+    // Validate pool accounts.
+    validate_config(config, config_account)?;
+    validate_mints(config_account, mint_x, mint_y)?;
+    let (vault_x_amount, vault_y_amount) =
+        validate_vaults(config, config_account, mint_x, mint_y, vault_x, vault_y)?;
+    validate_mint_token_program(mint_x, token_program_x)?;
+    validate_mint_token_program(mint_y, token_program_y)?;
+    let stats_account = Stats::load(stats)?;
+    validate_stats(config, stats_account)?;
+
+    // Validate user accounts.
+    validate_token_account(user_x, user)?;
+    validate_token_account(user_y, user)?;
+
+    // A repay instruction for this pool must follow in the same transaction.
+    let instructions = Instructions::try_from(instructions_sysvar)?;
+    let mut index = instructions.load_current_index() as usize + 1;
+    let mut repaid = false;
+    // Loading an instruction fails past the last one of the transaction.
+    while let Ok(instruction) = instructions.load_instruction_at(index) {
+        if instruction.get_program_id() == &crate::ID
+            && instruction.get_instruction_data().first()
+                == Some(&(AMMInstruction::FlashRepay as u8))
+            && instruction.get_account_meta_at(REPAY_CONFIG_INDEX)?.key == *config.key()
+        {
+            repaid = true;
+            break;
+        }
+        index += 1;
+    }
+    if !repaid {
+        return Err(CustomError::FlashLoanNotRepaid.into());
+    }
+
+    let amount_x = u64::from_le_bytes(instruction_data.amount_x);
+    let amount_y = u64::from_le_bytes(instruction_data.amount_y);
+    if amount_x > vault_x_amount || amount_y > vault_y_amount {
+        return Err(ProgramError::InsufficientFunds);
+    }
+
+    // The vaults must get back the loan plus the swap fee on it, of which the protocol takes the
+    // same share as on swaps. In dynamic-fee mode, the fee follows the volatility up to now, as it
+    // would for a swap.
+    config_account.update_volatility(vault_x_amount, vault_y_amount, Clock::get()?.unix_timestamp);
+    let fee = config_account.swap_fee();
+    let repay_x = amount_before_fee(amount_x, fee).ok_or(ProgramError::ArithmeticOverflow)?;
+    let repay_y = amount_before_fee(amount_y, fee).ok_or(ProgramError::ArithmeticOverflow)?;
+    config_account.flash_loan = 1;
+    config_account.flash_loan_x = (vault_x_amount - amount_x)
+        .checked_add(repay_x)
+        .ok_or(ProgramError::ArithmeticOverflow)?
+        .to_le_bytes();
+    config_account.flash_loan_y = (vault_y_amount - amount_y)
+        .checked_add(repay_y)
+        .ok_or(ProgramError::ArithmeticOverflow)?
+        .to_le_bytes();
+
+    // Keep the protocol's cut of the fee out of the pool reserves, and count the fee in the pool
+    // statistics.
+    config_account.accrue_protocol_fee(true, repay_x)?;
+    config_account.accrue_protocol_fee(false, repay_y)?;
+    stats_account.record_flash_loan(repay_x - amount_x, repay_y - amount_y);
+
+    let mint_x_account = Mint::from_account_info(mint_x)?;
+    let mint_y_account = Mint::from_account_info(mint_y)?;
+
+    // Setup signer seeds.
+    let seed = config_account.signer_seeds().map(Seed::from);
+    let seeds = Signer::from(&seed);
+
+    // Lend mint_x tokens.
+    if amount_x > 0 {
+        token::instructions::TransferChecked {
+            from: vault_x,
+            mint: mint_x,
+            to: user_x,
+            authority: config,
+            amount: amount_x,
+            decimals: mint_x_account.decimals(),
+            token_program: token_program_x.key(),
+        }
+        .invoke_signed(&[seeds.clone()])?;
+    }
+
+    // Lend mint_y tokens.
+    if amount_y > 0 {
+        token::instructions::TransferChecked {
+            from: vault_y,
+            mint: mint_y,
+            to: user_y,
+            authority: config,
+            amount: amount_y,
+            decimals: mint_y_account.decimals(),
+            token_program: token_program_y.key(),
+        }
+        .invoke_signed(&[seeds.clone()])?;
+    }
+
+    Ok(())
+}
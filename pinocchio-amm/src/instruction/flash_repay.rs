@@ -0,0 +1,37 @@
+use pinocchio::{account_info::AccountInfo, program_error::ProgramError, ProgramResult};
+
+use crate::{
+    error::CustomError,
+    state::Config,
+    validation::{validate_config, validate_mints, validate_vaults},
+};
+
+pub fn process_flash_repay(accounts: &[AccountInfo]) -> ProgramResult {
+    let [mint_x, mint_y, vault_x, vault_y, config] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    let config_account = Config::load(config)?;
+    if config_account.flash_loan == 0 {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    // Validate pool accounts.
+    validate_config(config, config_account)?;
+    validate_mints(config_account, mint_x, mint_y)?;
+    let (vault_x_amount, vault_y_amount) =
+        validate_vaults(config, config_account, mint_x, mint_y, vault_x, vault_y)?;
+
+    // The loan and its fee must be back in the vaults.
+    if vault_x_amount < u64::from_le_bytes(config_account.flash_loan_x)
+        || vault_y_amount < u64::from_le_bytes(config_account.flash_loan_y)
+    {
+        return Err(CustomError::FlashLoanNotRepaid.into());
+    }
+
+    config_account.flash_loan = 0;
+    config_account.flash_loan_x = [0; 8];
+    config_account.flash_loan_y = [0; 8];
+
+    Ok(())
+}
@@ -0,0 +1,91 @@
+use bytemuck::{Pod, Zeroable};
+use pinocchio::{
+    account_info::AccountInfo,
+    cpi::set_return_data,
+    program_error::ProgramError,
+    sysvars::{clock::Clock, Sysvar},
+    ProgramResult,
+};
+
+use crate::{
+    error::CustomError,
+    state::Config,
+    validation::{validate_config, validate_mints, validate_vaults},
+};
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct GetTwapInstructionData {
+    pub window: [u8; 8],
+}
+
+impl GetTwapInstructionData {
+    pub const LEN: usize = core::mem::size_of::<GetTwapInstructionData>();
+}
+
+/// Return data of `GetTwap`: the Q64.64 time-weighted average prices of x (in y) and y (in x),
+/// and the number of seconds they were averaged over.
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct TwapResult {
+    pub price_x: [u8; 16],
+    pub price_y: [u8; 16],
+    pub elapsed: [u8; 8],
+}
+
+impl TwapResult {
+    pub const LEN: usize = core::mem::size_of::<TwapResult>();
+}
+
+pub fn process_get_twap(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let [mint_x, mint_y, vault_x, vault_y, config] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    let instruction_data = bytemuck::try_from_bytes::<GetTwapInstructionData>(&data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+
+    // The vault balances are not reserves while a flash loan is outstanding.
+    let config_account = Config::load(config)?;
+    if config_account.flash_loan != 0 {
+        return Err(CustomError::PoolLocked.into());
+    }
+
+    // Validate pool accounts.
+    validate_config(config, config_account)?;
+    validate_mints(config_account, mint_x, mint_y)?;
+    let (vault_x_amount, vault_y_amount) =
+        validate_vaults(config, config_account, mint_x, mint_y, vault_x, vault_y)?;
+    let (vault_x_amount, vault_y_amount) =
+        config_account.reserves(vault_x_amount, vault_y_amount)?;
+
+    let now = Clock::get()?.unix_timestamp;
+    let window = i64::from_le_bytes(instruction_data.window);
+    if window <= 0 {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    // Average from the latest observation that covers the whole window up to now.
+    let observation = config_account
+        .observation_before(now.saturating_sub(window))
+        .ok_or(CustomError::OracleWindowUnavailable)?;
+    let elapsed = now - i64::from_le_bytes(observation.timestamp);
+
+    let (price_x_cumulative, price_y_cumulative) =
+        config_account.cumulative_prices(vault_x_amount, vault_y_amount, now);
+    let price_x = price_x_cumulative
+        .wrapping_sub(u128::from_le_bytes(observation.price_x_cumulative))
+        / elapsed as u128;
+    let price_y = price_y_cumulative
+        .wrapping_sub(u128::from_le_bytes(observation.price_y_cumulative))
+        / elapsed as u128;
+
+    let result = TwapResult {
+        price_x: price_x.to_le_bytes(),
+        price_y: price_y.to_le_bytes(),
+        elapsed: elapsed.to_le_bytes(),
+    };
+    set_return_data(bytemuck::bytes_of(&result));
+
+    Ok(())
+}
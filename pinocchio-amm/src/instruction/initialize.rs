@@ -1,14 +1,22 @@
 use bytemuck::{Pod, Zeroable};
 use pinocchio::{
     account_info::AccountInfo,
+    instruction::{Seed, Signer},
     program_error::ProgramError,
-    pubkey,
-    sysvars::{rent::Rent, Sysvar},
+    pubkey::{self, Pubkey},
+    sysvars::{clock::Clock, rent::Rent, Sysvar},
     ProgramResult,
 };
-use pinocchio_token::state::{Mint, TokenAccount};
 
-use crate::{constants::CONFIG_SEED, state::Config};
+use crate::{
+    constants::{LP_DECIMALS, LP_SEED, MAX_FEE, REGISTRY_SEED, VAULT_SEED},
+    error::CustomError,
+    instruction::initialize_stats::create_stats,
+    math::Curve,
+    state::{Config, Registry},
+    token::{self, Mint, TokenAccount, TOKEN_2022_ID},
+    validation::{validate_config, validate_mint_extensions, validate_mint_token_program},
+};
 
 #[repr(C)]
 #[derive(Clone, Copy, Pod, Zeroable)]
@@ -16,6 +24,18 @@ pub struct InitializeInstructionData {
     pub seed: [u8; 8],
     pub fee: [u8; 2],
     pub config_bump: u8,
+    pub protocol_fee: [u8; 2],
+    pub lp_bump: u8,
+    pub vault_x_bump: u8,
+    pub vault_y_bump: u8,
+    pub curve_type: u8,
+    pub amp: [u8; 8],
+    pub vault_lp_bump: u8,
+    pub factory: u8,
+    pub registry_bump: u8,
+    pub stats_bump: u8,
+    pub weight_x: [u8; 2],
+    pub weight_y: [u8; 2],
 }
 
 impl InitializeInstructionData {
@@ -23,7 +43,7 @@ impl InitializeInstructionData {
 }
 
 pub fn process_initialize(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
-    let [authority, mint_x, mint_y, vault_x, vault_y, mint_lp, config, _system_program, _token_program] =
+    let [authority, mint_x, mint_y, vault_x, vault_y, mint_lp, vault_lp, config, registry, _system_program, token_program_x, token_program_y, stats] =
         accounts
     else {
         return Err(ProgramError::NotEnoughAccountKeys);
@@ -36,57 +56,328 @@ pub fn process_initialize(accounts: &[AccountInfo], data: &[u8]) -> ProgramResul
     let instruction_data = bytemuck::try_from_bytes::<InitializeInstructionData>(&data)
         .map_err(|_| ProgramError::InvalidInstructionData)?;
 
-    // Validate config account.
-    let config_pda = pubkey::create_program_address(
+    if u16::from_le_bytes(instruction_data.fee) > MAX_FEE {
+        return Err(CustomError::InvalidFee.into());
+    }
+    if u16::from_le_bytes(instruction_data.protocol_fee) > 10_000 {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    Curve::new(
+        instruction_data.curve_type,
+        u64::from_le_bytes(instruction_data.amp),
+        u16::from_le_bytes(instruction_data.weight_x),
+        u16::from_le_bytes(instruction_data.weight_y),
+    )
+    .ok_or(CustomError::InvalidCurve)?;
+
+    if mint_x.key() == mint_y.key() {
+        return Err(CustomError::DuplicateAccount.into());
+    }
+
+    // Each vault is created under the token program of its mint, and the LP mint and its vault
+    // under the one of mint_x.
+    validate_mint_token_program(mint_x, token_program_x)?;
+    validate_mint_token_program(mint_y, token_program_y)?;
+    validate_mint_extensions(mint_x)?;
+    validate_mint_extensions(mint_y)?;
+
+    let mut config_state = Config::default();
+    config_state.seed = instruction_data.seed;
+    config_state.authority = *authority.key();
+    config_state.mint_x = *mint_x.key();
+    config_state.mint_y = *mint_y.key();
+    config_state.fee = instruction_data.fee;
+    config_state.config_bump = instruction_data.config_bump;
+    config_state.protocol_fee = instruction_data.protocol_fee;
+    config_state.curve_type = instruction_data.curve_type;
+    config_state.amp = instruction_data.amp;
+    config_state.weight_x = instruction_data.weight_x;
+    config_state.weight_y = instruction_data.weight_y;
+    config_state.vault_x_bump = instruction_data.vault_x_bump;
+    config_state.vault_y_bump = instruction_data.vault_y_bump;
+    config_state.lp_bump = instruction_data.lp_bump;
+    config_state.vault_lp_bump = instruction_data.vault_lp_bump;
+
+    // Factory pools derive from their sorted mints and fee tier, so there is one per pair and tier.
+    match instruction_data.factory {
+        0 => {}
+        1 => {
+            if mint_x.key() > mint_y.key() {
+                return Err(CustomError::InvalidMintOrder.into());
+            }
+            config_state.factory = 1;
+            config_state.fee_tier = instruction_data.fee;
+        }
+        _ => return Err(ProgramError::InvalidInstructionData),
+    }
+
+    // Validate pool accounts.
+    validate_config(config, &config_state)?;
+
+    // Any off-curve bump gives a valid address, so a factory pool must sit at the canonical one
+    // for its pair and tier to have a single pool.
+    if config_state.factory == 1 {
+        let [seeds @ .., _] = config_state.signer_seeds();
+        if pubkey::find_program_address(&seeds, &crate::ID).1 != config_state.config_bump {
+            return Err(ProgramError::InvalidSeeds);
+        }
+    }
+
+    // Validate mint_lp account.
+    let mint_lp_pda = pubkey::create_program_address(
         &[
-            CONFIG_SEED.as_bytes(),
-            instruction_data.seed.as_ref(),
-            &[instruction_data.config_bump as u8],
+            LP_SEED.as_bytes(),
+            config.key().as_ref(),
+            &[instruction_data.lp_bump],
         ],
         &crate::ID,
     )?;
-    if config.key() != &config_pda {
+    if mint_lp.key() != &mint_lp_pda {
         return Err(ProgramError::InvalidAccountData);
     }
 
     // Validate vault accounts.
+    let vault_x_pda = pubkey::create_program_address(
+        &[
+            VAULT_SEED.as_bytes(),
+            config.key().as_ref(),
+            mint_x.key().as_ref(),
+            &[instruction_data.vault_x_bump],
+        ],
+        &crate::ID,
+    )?;
+    let vault_y_pda = pubkey::create_program_address(
+        &[
+            VAULT_SEED.as_bytes(),
+            config.key().as_ref(),
+            mint_y.key().as_ref(),
+            &[instruction_data.vault_y_bump],
+        ],
+        &crate::ID,
+    )?;
+    let vault_lp_pda = pubkey::create_program_address(
+        &[
+            VAULT_SEED.as_bytes(),
+            config.key().as_ref(),
+            mint_lp.key().as_ref(),
+            &[instruction_data.vault_lp_bump],
+        ],
+        &crate::ID,
+    )?;
+    if vault_x.key() != &vault_x_pda
+        || vault_y.key() != &vault_y_pda
+        || vault_lp.key() != &vault_lp_pda
     {
-        let vault_x_account = TokenAccount::from_account_info(vault_x)?;
-        let vault_y_account = TokenAccount::from_account_info(vault_y)?;
-        if vault_x_account.owner() != config.key() {
-            return Err(ProgramError::InvalidAccountData);
-        }
-        if vault_y_account.owner() != config.key() {
-            return Err(ProgramError::InvalidAccountData);
-        }
+        return Err(ProgramError::InvalidAccountData);
     }
 
-    // Validate mint_lp account.
-    {
-        let mint_lp_account = Mint::from_account_info(mint_lp)?;
-        if mint_lp_account.mint_authority() != Some(config.key()) {
-            return Err(ProgramError::InvalidAccountData);
-        }
+    // Validate registry account.
+    let registry_pda = pubkey::create_program_address(
+        &[REGISTRY_SEED.as_bytes(), &[instruction_data.registry_bump]],
+        &crate::ID,
+    )?;
+    if registry.key() != &registry_pda {
+        return Err(ProgramError::InvalidAccountData);
     }
 
+    let rent = Rent::get()?;
+
     // Create config account.
-    pinocchio_system::instructions::CreateAccount {
-        from: authority,
-        to: config,
-        space: Config::LEN as u64,
-        lamports: Rent::get()?.minimum_balance(Config::LEN),
-        owner: &crate::ID,
+    let config_seed = config_state.signer_seeds().map(Seed::from);
+    create_pda_account(
+        authority,
+        config,
+        Config::LEN,
+        rent.minimum_balance(Config::LEN),
+        &crate::ID,
+        &config_seed,
+    )?;
+
+    // Create mint_lp account, with the config as mint authority. Mints cannot be closed, so the LP
+    // mint of a closed pool is still there when the pool is reopened, and is taken over as it is
+    // once all of its tokens have been burned.
+    match mint_lp.data_len() {
+        0 => {
+            let lp_bump = [instruction_data.lp_bump];
+            let lp_seed = [
+                Seed::from(LP_SEED.as_bytes()),
+                Seed::from(config.key().as_ref()),
+                Seed::from(&lp_bump),
+            ];
+            create_pda_account(
+                authority,
+                mint_lp,
+                Mint::LEN,
+                rent.minimum_balance(Mint::LEN),
+                token_program_x.key(),
+                &lp_seed,
+            )?;
+
+            token::instructions::InitializeMint2 {
+                mint: mint_lp,
+                decimals: LP_DECIMALS,
+                mint_authority: config.key(),
+                token_program: token_program_x.key(),
+            }
+            .invoke()?;
+        }
+        _ => {
+            if !mint_lp.is_owned_by(token_program_x.key()) {
+                return Err(ProgramError::InvalidAccountOwner);
+            }
+            let mint_lp_account = Mint::from_account_info(mint_lp)?;
+            if mint_lp_account.mint_authority() != Some(config.key())
+                || mint_lp_account.supply() != 0
+            {
+                return Err(ProgramError::InvalidAccountData);
+            }
+        }
+    }
+
+    // Create vault accounts, owned by the config. vault_lp holds the locked minimum liquidity.
+    for (vault, mint, vault_bump, token_program) in [
+        (
+            vault_x,
+            mint_x,
+            instruction_data.vault_x_bump,
+            token_program_x,
+        ),
+        (
+            vault_y,
+            mint_y,
+            instruction_data.vault_y_bump,
+            token_program_y,
+        ),
+        (
+            vault_lp,
+            mint_lp,
+            instruction_data.vault_lp_bump,
+            token_program_x,
+        ),
+    ] {
+        let vault_bump = [vault_bump];
+        let vault_seed = [
+            Seed::from(VAULT_SEED.as_bytes()),
+            Seed::from(config.key().as_ref()),
+            Seed::from(mint.key().as_ref()),
+            Seed::from(&vault_bump),
+        ];
+        // Token-2022 accounts grow with the extensions of their mint.
+        let space = match token_program.key() == &TOKEN_2022_ID {
+            true => token::instructions::GetAccountDataSize {
+                mint,
+                token_program: token_program.key(),
+            }
+            .invoke()?,
+            false => TokenAccount::LEN,
+        };
+        create_pda_account(
+            authority,
+            vault,
+            space,
+            rent.minimum_balance(space),
+            token_program.key(),
+            &vault_seed,
+        )?;
+
+        token::instructions::InitializeAccount3 {
+            account: vault,
+            mint,
+            owner: config.key(),
+            token_program: token_program.key(),
+        }
+        .invoke()?;
     }
-    .invoke()?;
 
     // Initialize config account.
-    let config_state = Config::load(config)?;
-    config_state.seed = instruction_data.seed;
-    config_state.authority = *authority.key();
-    config_state.mint_x = *mint_x.key();
-    config_state.mint_y = *mint_y.key();
-    config_state.fee = instruction_data.fee;
-    config_state.config_bump = instruction_data.config_bump;
+    let config_account = Config::load(config)?;
+    *config_account = config_state;
+    config_account.update_oracle(0, 0, Clock::get()?.unix_timestamp);
+
+    // Create stats account.
+    create_stats(authority, config, stats, instruction_data.stats_bump)?;
+
+    // Record the pool in the registry, which is created along with the first pool.
+    let pool_count = match registry.data_len() {
+        0 => {
+            let registry_bump = [instruction_data.registry_bump];
+            let registry_seed = [
+                Seed::from(REGISTRY_SEED.as_bytes()),
+                Seed::from(&registry_bump),
+            ];
+            create_pda_account(
+                authority,
+                registry,
+                Registry::space(1),
+                rent.minimum_balance(Registry::space(1)),
+                &crate::ID,
+                &registry_seed,
+            )?;
+            0
+        }
+        _ => {
+            if !registry.is_owned_by(&crate::ID) {
+                return Err(ProgramError::InvalidAccountOwner);
+            }
+            let pool_count = u64::from_le_bytes(Registry::load(registry)?.0.pool_count);
+            let space = Registry::space(pool_count + 1);
+            let lamports = rent
+                .minimum_balance(space)
+                .saturating_sub(registry.lamports());
+            if lamports > 0 {
+                pinocchio_system::instructions::Transfer {
+                    from: authority,
+                    to: registry,
+                    lamports,
+                }
+                .invoke()?;
+            }
+            registry.realloc(space, true)?;
+            pool_count
+        }
+    };
+    let (registry_state, pools) = Registry::load(registry)?;
+    pools[pool_count as usize] = *config.key();
+    registry_state.pool_count = (pool_count + 1).to_le_bytes();
 
     Ok(())
 }
+
+/// Creates `account` at a PDA signed for by `seeds`, funded by `payer`. Anyone can send lamports to
+/// an address before an account is created there, which makes `CreateAccount` fail, so a funded
+/// address is topped up to `lamports` and then allocated and assigned instead.
+pub fn create_pda_account(
+    payer: &AccountInfo,
+    account: &AccountInfo,
+    space: usize,
+    lamports: u64,
+    owner: &Pubkey,
+    seeds: &[Seed],
+) -> ProgramResult {
+    if account.lamports() == 0 {
+        return pinocchio_system::instructions::CreateAccount {
+            from: payer,
+            to: account,
+            space: space as u64,
+            lamports,
+            owner,
+        }
+        .invoke_signed(&[Signer::from(seeds)]);
+    }
+
+    let top_up = lamports.saturating_sub(account.lamports());
+    if top_up > 0 {
+        pinocchio_system::instructions::Transfer {
+            from: payer,
+            to: account,
+            lamports: top_up,
+        }
+        .invoke()?;
+    }
+    pinocchio_system::instructions::Allocate {
+        account,
+        space: space as u64,
+    }
+    .invoke_signed(&[Signer::from(seeds)])?;
+    pinocchio_system::instructions::Assign { account, owner }.invoke_signed(&[Signer::from(seeds)])
+}
@@ -0,0 +1,85 @@
+use bytemuck::{Pod, Zeroable};
+use pinocchio::{
+    account_info::AccountInfo,
+    instruction::Seed,
+    program_error::ProgramError,
+    pubkey,
+    sysvars::{rent::Rent, Sysvar},
+    ProgramResult,
+};
+
+use crate::{
+    constants::STATS_SEED,
+    instruction::initialize::create_pda_account,
+    state::{Config, Stats},
+    validation::validate_config,
+};
+
+/// Opens the statistics account of a pool created before pools came with one.
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct InitializeStatsInstructionData {
+    pub stats_bump: u8,
+}
+
+impl InitializeStatsInstructionData {
+    pub const LEN: usize = core::mem::size_of::<InitializeStatsInstructionData>();
+}
+
+pub fn process_initialize_stats(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let [payer, config, stats, _system_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if !payer.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let instruction_data = bytemuck::try_from_bytes::<InitializeStatsInstructionData>(&data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+
+    let config_account = Config::load(config)?;
+
+    // Validate pool accounts.
+    validate_config(config, config_account)?;
+
+    create_stats(payer, config, stats, instruction_data.stats_bump)
+}
+
+/// Creates the statistics account of the pool at `config`, funded by `payer`.
+pub(crate) fn create_stats(
+    payer: &AccountInfo,
+    config: &AccountInfo,
+    stats: &AccountInfo,
+    stats_bump: u8,
+) -> ProgramResult {
+    // Validate stats account.
+    let stats_pda = pubkey::create_program_address(
+        &[STATS_SEED.as_bytes(), config.key().as_ref(), &[stats_bump]],
+        &crate::ID,
+    )?;
+    if stats.key() != &stats_pda {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let bump = [stats_bump];
+    let stats_seed = [
+        Seed::from(STATS_SEED.as_bytes()),
+        Seed::from(config.key().as_ref()),
+        Seed::from(&bump),
+    ];
+    create_pda_account(
+        payer,
+        stats,
+        Stats::LEN,
+        Rent::get()?.minimum_balance(Stats::LEN),
+        &crate::ID,
+        &stats_seed,
+    )?;
+
+    let stats_account = Stats::load(stats)?;
+    stats_account.pool = *config.key();
+    stats_account.bump = stats_bump;
+
+    Ok(())
+}
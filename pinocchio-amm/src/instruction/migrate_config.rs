@@ -0,0 +1,94 @@
+use pinocchio::{
+    account_info::AccountInfo,
+    program_error::ProgramError,
+    pubkey::{self, Pubkey},
+    sysvars::{rent::Rent, Sysvar},
+    ProgramResult,
+};
+
+use crate::{
+    constants::{CONFIG_VERSION, LP_SEED, VAULT_SEED},
+    error::CustomError,
+    state::Config,
+    validation::validate_config,
+};
+
+pub fn process_migrate_config(accounts: &[AccountInfo]) -> ProgramResult {
+    let [payer, config, _system_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if !payer.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if !config.is_owned_by(&crate::ID) {
+        return Err(ProgramError::InvalidAccountOwner);
+    }
+
+    // Fields are only ever appended, so an older config is a prefix of the current layout, after
+    // the header for versioned configs and on its own for configs from before the header.
+    let data = unsafe { config.borrow_data_unchecked() };
+    let fields = match Config::version(data) {
+        Some(CONFIG_VERSION) => return Ok(()),
+        Some(version) if version < CONFIG_VERSION => &data[Config::HEADER_LEN..],
+        Some(_) => return Err(CustomError::UnsupportedConfigVersion.into()),
+        None => data,
+    };
+    if fields.len() > Config::LEN - Config::HEADER_LEN {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let mut config_state = Config::default();
+    bytemuck::bytes_of_mut(&mut config_state)[Config::HEADER_LEN..][..fields.len()]
+        .copy_from_slice(fields);
+
+    // Validate pool accounts. This also rules out other accounts of the program, such as the
+    // registry.
+    validate_config(config, &config_state)?;
+
+    // Pools from before the LP vault, whose configs end at the curve fields at most, may hold their
+    // reserves and LP mint at arbitrary addresses, which can't be recorded as PDA bumps.
+    if fields.len() <= core::mem::offset_of!(Config, flash_loan) - Config::HEADER_LEN {
+        return Err(CustomError::UnmigratableConfig.into());
+    }
+
+    // Configs from before the vault and LP mint bumps were stored get the canonical ones, which
+    // are the ones clients derive the pool accounts with.
+    if fields.len() <= core::mem::offset_of!(Config, vault_x_bump) - Config::HEADER_LEN {
+        let (mint_lp, lp_bump) =
+            pubkey::find_program_address(&[LP_SEED.as_bytes(), config.key().as_ref()], &crate::ID);
+        config_state.lp_bump = lp_bump;
+        config_state.vault_x_bump = vault_bump(config.key(), &config_state.mint_x);
+        config_state.vault_y_bump = vault_bump(config.key(), &config_state.mint_y);
+        config_state.vault_lp_bump = vault_bump(config.key(), &mint_lp);
+    }
+
+    // Grow the config to the current layout, with the payer covering the extra rent.
+    let lamports = Rent::get()?
+        .minimum_balance(Config::LEN)
+        .saturating_sub(config.lamports());
+    if lamports > 0 {
+        pinocchio_system::instructions::Transfer {
+            from: payer,
+            to: config,
+            lamports,
+        }
+        .invoke()?;
+    }
+    config.realloc(Config::LEN, true)?;
+
+    let data = unsafe { config.borrow_mut_data_unchecked() };
+    data.copy_from_slice(bytemuck::bytes_of(&config_state));
+
+    Ok(())
+}
+
+/// Returns the canonical bump of the vault PDA of the pool at `config` holding `mint`.
+fn vault_bump(config: &Pubkey, mint: &Pubkey) -> u8 {
+    pubkey::find_program_address(
+        &[VAULT_SEED.as_bytes(), config.as_ref(), mint.as_ref()],
+        &crate::ID,
+    )
+    .1
+}
@@ -1,13 +1,45 @@
 use pinocchio::program_error::ProgramError;
 
+pub mod accept_authority;
+pub mod claim_fees;
+pub mod close_pool;
 pub mod deposit;
+pub mod deposit_single;
+pub mod flash_loan;
+pub mod flash_repay;
+pub mod get_twap;
 pub mod initialize;
+pub mod initialize_stats;
+pub mod migrate_config;
+pub mod propose_authority;
+pub mod quote;
+pub mod renounce_authority;
+pub mod route_swap;
 pub mod swap;
+pub mod swap_exact_out;
+pub mod update_config;
+pub mod update_dynamic_fee;
 pub mod withdraw;
 
+pub use accept_authority::*;
+pub use claim_fees::*;
+pub use close_pool::*;
 pub use deposit::*;
+pub use deposit_single::*;
+pub use flash_loan::*;
+pub use flash_repay::*;
+pub use get_twap::*;
 pub use initialize::*;
+pub use initialize_stats::*;
+pub use migrate_config::*;
+pub use propose_authority::*;
+pub use quote::*;
+pub use renounce_authority::*;
+pub use route_swap::*;
 pub use swap::*;
+pub use swap_exact_out::*;
+pub use update_config::*;
+pub use update_dynamic_fee::*;
 pub use withdraw::*;
 
 #[repr(u8)]
@@ -16,6 +48,22 @@ pub enum AMMInstruction {
     Deposit,
     Swap,
     Withdraw,
+    ClaimFees,
+    UpdateConfig,
+    SwapExactOut,
+    GetTwap,
+    FlashLoan,
+    FlashRepay,
+    RouteSwap,
+    Quote,
+    DepositSingle,
+    ProposeAuthority,
+    AcceptAuthority,
+    RenounceAuthority,
+    ClosePool,
+    MigrateConfig,
+    UpdateDynamicFee,
+    InitializeStats,
 }
 
 impl TryFrom<&u8> for AMMInstruction {
@@ -27,6 +75,22 @@ impl TryFrom<&u8> for AMMInstruction {
             1 => Ok(AMMInstruction::Deposit),
             2 => Ok(AMMInstruction::Swap),
             3 => Ok(AMMInstruction::Withdraw),
+            4 => Ok(AMMInstruction::ClaimFees),
+            5 => Ok(AMMInstruction::UpdateConfig),
+            6 => Ok(AMMInstruction::SwapExactOut),
+            7 => Ok(AMMInstruction::GetTwap),
+            8 => Ok(AMMInstruction::FlashLoan),
+            9 => Ok(AMMInstruction::FlashRepay),
+            10 => Ok(AMMInstruction::RouteSwap),
+            11 => Ok(AMMInstruction::Quote),
+            12 => Ok(AMMInstruction::DepositSingle),
+            13 => Ok(AMMInstruction::ProposeAuthority),
+            14 => Ok(AMMInstruction::AcceptAuthority),
+            15 => Ok(AMMInstruction::RenounceAuthority),
+            16 => Ok(AMMInstruction::ClosePool),
+            17 => Ok(AMMInstruction::MigrateConfig),
+            18 => Ok(AMMInstruction::UpdateDynamicFee),
+            19 => Ok(AMMInstruction::InitializeStats),
             _ => Err(ProgramError::InvalidInstructionData),
         }
     }
@@ -0,0 +1,44 @@
+use bytemuck::{Pod, Zeroable};
+use pinocchio::{
+    account_info::AccountInfo, program_error::ProgramError, pubkey::Pubkey, ProgramResult,
+};
+
+use crate::{
+    state::Config,
+    validation::{validate_authority, validate_config},
+};
+
+/// Proposing the null key withdraws a pending proposal.
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct ProposeAuthorityInstructionData {
+    pub new_authority: Pubkey,
+}
+
+impl ProposeAuthorityInstructionData {
+    pub const LEN: usize = core::mem::size_of::<ProposeAuthorityInstructionData>();
+}
+
+pub fn process_propose_authority(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let [authority, config] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if !authority.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let instruction_data = bytemuck::try_from_bytes::<ProposeAuthorityInstructionData>(&data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+
+    let config_account = Config::load(config)?;
+
+    // Validate pool accounts.
+    validate_config(config, config_account)?;
+    validate_authority(config_account, authority)?;
+
+    // The current authority stays in charge until the new one accepts.
+    config_account.pending_authority = instruction_data.new_authority;
+
+    Ok(())
+}
@@ -0,0 +1,149 @@
+use bytemuck::{Pod, Zeroable};
+use pinocchio::{
+    account_info::AccountInfo,
+    cpi::set_return_data,
+    program_error::ProgramError,
+    sysvars::{clock::Clock, Sysvar},
+    ProgramResult,
+};
+
+use crate::{
+    error::CustomError,
+    instruction::swap::{swap_amount_in, swap_amount_out},
+    math::{amount_after_fee, deposit_amounts, withdraw_amounts},
+    state::Config,
+    validation::{validate_config, validate_mint_lp, validate_mints, validate_vaults},
+};
+
+/// `kind` is 0 for an exact input swap, 1 for an exact output swap, 2 for a deposit and 3 for a
+/// withdrawal. `amount` is the swap amount, or the LP amount to mint or burn.
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct QuoteInstructionData {
+    pub kind: u8,
+    pub is_x: u8,
+    pub amount: [u8; 8],
+}
+
+impl QuoteInstructionData {
+    pub const LEN: usize = core::mem::size_of::<QuoteInstructionData>();
+}
+
+/// Return data of `Quote`: the token amounts paid or received on each side, the LP amount
+/// minted or burned, the swap fee in the input token and the price impact in basis points.
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct QuoteResult {
+    pub amount_x: [u8; 8],
+    pub amount_y: [u8; 8],
+    pub amount_lp: [u8; 8],
+    pub fee: [u8; 8],
+    pub price_impact: [u8; 2],
+}
+
+impl QuoteResult {
+    pub const LEN: usize = core::mem::size_of::<QuoteResult>();
+}
+
+pub fn process_quote(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let [mint_x, mint_y, vault_x, vault_y, mint_lp, config] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    let instruction_data = bytemuck::try_from_bytes::<QuoteInstructionData>(&data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+
+    // Quote the pool as the other instructions would see it, without changing it.
+    let config_account = Config::load(config)?;
+    if config_account.locked != 0 || config_account.flash_loan != 0 {
+        return Err(CustomError::PoolLocked.into());
+    }
+
+    // Validate pool accounts.
+    validate_config(config, config_account)?;
+    validate_mints(config_account, mint_x, mint_y)?;
+    let (vault_x_amount, vault_y_amount) =
+        validate_vaults(config, config_account, mint_x, mint_y, vault_x, vault_y)?;
+    let (vault_x_amount, vault_y_amount) =
+        config_account.reserves(vault_x_amount, vault_y_amount)?;
+    let mint_lp_supply = validate_mint_lp(config, config_account, mint_lp)?;
+
+    // Swaps refresh the volatility before they are priced, so the dynamic fee is quoted from a
+    // copy of the config brought up to date the same way.
+    let mut config_state = *config_account;
+    config_state.update_oracle(vault_x_amount, vault_y_amount, Clock::get()?.unix_timestamp);
+    let config_account = &config_state;
+
+    let amount = u64::from_le_bytes(instruction_data.amount);
+
+    let result = match instruction_data.kind {
+        0 | 1 => {
+            let is_x = match instruction_data.is_x {
+                0 => false,
+                1 => true,
+                _ => return Err(ProgramError::InvalidInstructionData),
+            };
+            let (reserve_in, reserve_out) = match is_x {
+                true => (vault_x_amount, vault_y_amount),
+                false => (vault_y_amount, vault_x_amount),
+            };
+
+            let (amount_in, amount_out) = match instruction_data.kind {
+                0 => (
+                    amount,
+                    swap_amount_out(config_account, is_x, reserve_in, reserve_out, amount)?,
+                ),
+                _ => (
+                    swap_amount_in(config_account, is_x, reserve_in, reserve_out, amount)?,
+                    amount,
+                ),
+            };
+            let amount_in_after_fee = amount_after_fee(amount_in, config_account.swap_fee())
+                .ok_or(CustomError::InvalidSwap)?;
+
+            // Compare with the output at the current price, fee excluded.
+            let spot_amount_out = config_account
+                .curve()?
+                .oriented(is_x)
+                .spot_amount_out(reserve_in, reserve_out, amount_in_after_fee)
+                .ok_or(CustomError::InvalidSwap)?;
+            let price_impact = match spot_amount_out {
+                0 => 0,
+                _ => {
+                    spot_amount_out.saturating_sub(amount_out) as u128 * 10_000
+                        / spot_amount_out as u128
+                }
+            };
+
+            let (amount_x, amount_y) = match is_x {
+                true => (amount_in, amount_out),
+                false => (amount_out, amount_in),
+            };
+            QuoteResult {
+                amount_x: amount_x.to_le_bytes(),
+                amount_y: amount_y.to_le_bytes(),
+                amount_lp: 0u64.to_le_bytes(),
+                fee: (amount_in - amount_in_after_fee).to_le_bytes(),
+                price_impact: (price_impact as u16).to_le_bytes(),
+            }
+        }
+        2 | 3 => {
+            let (amount_x, amount_y) = match instruction_data.kind {
+                2 => deposit_amounts(vault_x_amount, vault_y_amount, mint_lp_supply, amount)
+                    .ok_or(CustomError::InvalidDeposit)?,
+                _ => withdraw_amounts(vault_x_amount, vault_y_amount, mint_lp_supply, amount)
+                    .ok_or(CustomError::InvalidWithdrawal)?,
+            };
+            QuoteResult {
+                amount_x: amount_x.to_le_bytes(),
+                amount_y: amount_y.to_le_bytes(),
+                amount_lp: amount.to_le_bytes(),
+                ..Zeroable::zeroed()
+            }
+        }
+        _ => return Err(ProgramError::InvalidInstructionData),
+    };
+    set_return_data(bytemuck::bytes_of(&result));
+
+    Ok(())
+}
@@ -0,0 +1,41 @@
+use pinocchio::{
+    account_info::AccountInfo, program_error::ProgramError, pubkey::Pubkey, ProgramResult,
+};
+
+use crate::{
+    error::CustomError,
+    state::Config,
+    validation::{validate_authority, validate_config},
+};
+
+pub fn process_renounce_authority(accounts: &[AccountInfo]) -> ProgramResult {
+    let [authority, config] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if !authority.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let config_account = Config::load(config)?;
+
+    // Validate pool accounts.
+    validate_config(config, config_account)?;
+    validate_authority(config_account, authority)?;
+
+    // Nobody can sign for the null key, so the fee and the lock are frozen for good. A pool
+    // renounced while locked could never be withdrawn from.
+    if config_account.locked != 0 || config_account.flash_loan != 0 {
+        return Err(CustomError::PoolLocked.into());
+    }
+    config_account.authority = Pubkey::default();
+    config_account.pending_authority = Pubkey::default();
+
+    // Nor can protocol fees be claimed anymore, so the protocol stops taking a share of swap fees,
+    // and what it has accrued is released to the pool reserves.
+    config_account.protocol_fee = 0u16.to_le_bytes();
+    config_account.protocol_fees_x = 0u64.to_le_bytes();
+    config_account.protocol_fees_y = 0u64.to_le_bytes();
+
+    Ok(())
+}
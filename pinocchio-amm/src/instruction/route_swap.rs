@@ -0,0 +1,235 @@
+use bytemuck::{Pod, Zeroable};
+use pinocchio::{
+    account_info::AccountInfo,
+    instruction::{Seed, Signer},
+    program_error::ProgramError,
+    sysvars::{clock::Clock, Sysvar},
+    ProgramResult,
+};
+
+use crate::{
+    error::CustomError,
+    events::{Event, SwapEvent},
+    instruction::swap::swap_amount_out,
+    math::amount_after_fee,
+    state::{Config, Stats},
+    token::{self, Mint, TokenAccount},
+    validation::{
+        validate_config, validate_deadline, validate_mints, validate_stats, validate_token_account,
+        validate_token_program, validate_vaults,
+    },
+};
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct RouteSwapInstructionData {
+    pub amount: [u8; 8],
+    pub min: [u8; 8],
+    // Unix timestamp after which the swap is rejected, or 0 for no deadline.
+    pub deadline: [u8; 8],
+}
+
+impl RouteSwapInstructionData {
+    pub const LEN: usize = core::mem::size_of::<RouteSwapInstructionData>();
+}
+
+/// Number of accounts of each hop: `[config, mint_x, mint_y, vault_x, vault_y, stats]`.
+pub const ROUTE_HOP_ACCOUNTS: usize = 6;
+
+pub fn process_route_swap(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let [user, user_in, user_out, token_program_a, token_program_b, hop_accounts @ ..] = accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if !user.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let instruction_data = bytemuck::try_from_bytes::<RouteSwapInstructionData>(&data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+
+    validate_deadline(instruction_data.deadline)?;
+
+    let hops = hop_accounts.chunks_exact(ROUTE_HOP_ACCOUNTS);
+    if hop_accounts.is_empty() || !hops.remainder().is_empty() {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    // Validate user accounts.
+    validate_token_account(user_in, user)?;
+    validate_token_account(user_out, user)?;
+    // The mints of the route may belong to either token program. Both are passed, the same one
+    // twice when every mint belongs to it.
+    validate_token_program(token_program_a)?;
+    validate_token_program(token_program_b)?;
+    let token_programs = [token_program_a, token_program_b];
+
+    let clock = Clock::get()?;
+    let mut mint = *TokenAccount::from_account_info(user_in)?.mint();
+    let mut amount = u64::from_le_bytes(instruction_data.amount);
+    // The vault, mint and config of the previous hop, which owes `amount` to the next one.
+    let mut source: Option<(&AccountInfo, &AccountInfo, &AccountInfo)> = None;
+
+    for hop in hops {
+        let [config, mint_x, mint_y, vault_x, vault_y, stats] = hop else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+
+        let config_account = Config::load(config)?;
+        if config_account.locked != 0 || config_account.flash_loan != 0 {
+            return Err(CustomError::PoolLocked.into());
+        }
+        // The previous hop has not paid out yet, so its vaults do not hold the pool reserves.
+        if source.is_some_and(|(_, _, previous_config)| previous_config.key() == config.key()) {
+            return Err(CustomError::DuplicateAccount.into());
+        }
+
+        // Validate pool accounts.
+        validate_config(config, config_account)?;
+        validate_mints(config_account, mint_x, mint_y)?;
+        let (vault_x_amount, vault_y_amount) =
+            validate_vaults(config, config_account, mint_x, mint_y, vault_x, vault_y)?;
+        let (vault_x_amount, vault_y_amount) =
+            config_account.reserves(vault_x_amount, vault_y_amount)?;
+        let stats_account = Stats::load(stats)?;
+        validate_stats(config, stats_account)?;
+
+        // Accrue the price oracle with the reserves from before this instruction.
+        config_account.update_oracle(vault_x_amount, vault_y_amount, clock.unix_timestamp);
+
+        // The hop must take the token the previous one gave out.
+        let is_x = if &mint == mint_x.key() {
+            true
+        } else if &mint == mint_y.key() {
+            false
+        } else {
+            return Err(CustomError::RouteMintMismatch.into());
+        };
+        let (reserve_in, reserve_out) = match is_x {
+            true => (vault_x_amount, vault_y_amount),
+            false => (vault_y_amount, vault_x_amount),
+        };
+        let (vault_in, vault_out, mint_in, mint_out) = match is_x {
+            true => (vault_x, vault_y, mint_x, mint_y),
+            false => (vault_y, vault_x, mint_y, mint_x),
+        };
+
+        // Pay this hop, from the user for the first one and from the previous hop after that. The
+        // hop is priced on what reaches its vault, net of any transfer fee.
+        let amount_in = match source {
+            None => {
+                let decimals = Mint::from_account_info(mint_in)?.decimals();
+                token::instructions::TransferChecked {
+                    from: user_in,
+                    mint: mint_in,
+                    to: vault_in,
+                    authority: user,
+                    amount,
+                    decimals,
+                    token_program: mint_token_program(mint_in, token_programs)?.key(),
+                }
+                .invoke_signed_received(&[])?
+            }
+            Some((previous_vault, previous_mint, previous_config)) => transfer_from_vault(
+                previous_vault,
+                previous_mint,
+                vault_in,
+                previous_config,
+                mint_token_program(previous_mint, token_programs)?,
+                amount,
+            )?,
+        };
+
+        let amount_out = swap_amount_out(config_account, is_x, reserve_in, reserve_out, amount_in)?;
+
+        // Keep the protocol's cut of the fee out of the pool reserves.
+        let protocol_fee = config_account.accrue_protocol_fee(is_x, amount_in)?;
+
+        // Log each hop as its own swap, and count it in the statistics of its pool. Its output is
+        // paid out by the next hop, or at the end.
+        let fee = amount_in
+            - amount_after_fee(amount_in, config_account.swap_fee())
+                .ok_or(CustomError::InvalidSwap)?;
+        stats_account.record_swap(is_x, amount_in, amount_out, fee, clock.slot);
+        let (reserve_in, reserve_out) = (
+            reserve_in + amount_in - protocol_fee,
+            reserve_out - amount_out,
+        );
+        let (reserve_x, reserve_y) = match is_x {
+            true => (reserve_in, reserve_out),
+            false => (reserve_out, reserve_in),
+        };
+        SwapEvent {
+            pool: *config.key(),
+            user: *user.key(),
+            is_x: is_x as u8,
+            amount_in: amount_in.to_le_bytes(),
+            amount_out: amount_out.to_le_bytes(),
+            fee: fee.to_le_bytes(),
+            reserve_x: reserve_x.to_le_bytes(),
+            reserve_y: reserve_y.to_le_bytes(),
+        }
+        .emit();
+
+        source = Some((vault_out, mint_out, config));
+        mint = *mint_out.key();
+        amount = amount_out;
+    }
+
+    if TokenAccount::from_account_info(user_out)?.mint() != &mint {
+        return Err(CustomError::RouteMintMismatch.into());
+    }
+
+    // Pay out the last hop to the user.
+    let (vault, mint, config) = source.ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let token_program = mint_token_program(mint, token_programs)?;
+    let received = transfer_from_vault(vault, mint, user_out, config, token_program, amount)?;
+
+    // Slippage protection, on what reaches the user net of any transfer fee on the output.
+    if received < u64::from_le_bytes(instruction_data.min) {
+        return Err(CustomError::SwapSlippageExceeded.into());
+    }
+
+    Ok(())
+}
+
+/// Transfers `amount` out of a pool vault, signed by its config. Returns the amount credited to
+/// `to`.
+fn transfer_from_vault(
+    vault: &AccountInfo,
+    mint: &AccountInfo,
+    to: &AccountInfo,
+    config: &AccountInfo,
+    token_program: &AccountInfo,
+    amount: u64,
+) -> Result<u64, ProgramError> {
+    let config_account = Config::load(config)?;
+    let decimals = Mint::from_account_info(mint)?.decimals();
+
+    // Setup signer seeds.
+    let seed = config_account.signer_seeds().map(Seed::from);
+    let seeds = Signer::from(&seed);
+
+    token::instructions::TransferChecked {
+        from: vault,
+        mint,
+        to,
+        authority: config,
+        amount,
+        decimals,
+        token_program: token_program.key(),
+    }
+    .invoke_signed_received(&[seeds])
+}
+
+/// Returns the token program of the route that `mint` belongs to.
+fn mint_token_program<'a>(
+    mint: &AccountInfo,
+    token_programs: [&'a AccountInfo; 2],
+) -> Result<&'a AccountInfo, ProgramError> {
+    token_programs
+        .into_iter()
+        .find(|token_program| mint.is_owned_by(token_program.key()))
+        .ok_or(ProgramError::InvalidAccountOwner)
+}
@@ -1,14 +1,23 @@
 use bytemuck::{Pod, Zeroable};
-use constant_product_curve::{ConstantProduct, LiquidityPair};
 use pinocchio::{
     account_info::AccountInfo,
     instruction::{Seed, Signer},
     program_error::ProgramError,
-    pubkey, ProgramResult,
+    sysvars::{clock::Clock, Sysvar},
+    ProgramResult,
 };
-use pinocchio_token::state::{Mint, TokenAccount};
 
-use crate::{constants::CONFIG_SEED, error::CustomError, state::Config};
+use crate::{
+    error::CustomError,
+    events::{Event, SwapEvent},
+    math::{amount_after_fee, amount_before_fee},
+    state::{Config, Stats},
+    token::{self, Mint, TokenAccount},
+    validation::{
+        validate_config, validate_deadline, validate_mint_lp, validate_mint_token_program,
+        validate_mints, validate_stats, validate_token_account, validate_vaults,
+    },
+};
 
 #[repr(C)]
 #[derive(Clone, Copy, Pod, Zeroable)]
@@ -16,14 +25,47 @@ pub struct SwapInstructionData {
     pub is_x: u8,
     pub amount: [u8; 8],
     pub min: [u8; 8],
+    // Unix timestamp after which the swap is rejected, or 0 for no deadline.
+    pub deadline: [u8; 8],
 }
 
 impl SwapInstructionData {
     pub const LEN: usize = core::mem::size_of::<SwapInstructionData>();
 }
 
+pub enum SwapMode {
+    /// Swap exactly `amount` in for at least `min` out.
+    ExactIn { amount: u64, min: u64 },
+    /// Swap at most `max` in for exactly `amount` out.
+    ExactOut { amount: u64, max: u64 },
+}
+
 pub fn process_swap(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
-    let [user, mint_x, mint_y, vault_x, vault_y, user_x, user_y, mint_lp, user_lp, config, _system_program, _token_program] =
+    let instruction_data = bytemuck::try_from_bytes::<SwapInstructionData>(&data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+
+    let is_x = match instruction_data.is_x {
+        0 => false,
+        1 => true,
+        _ => return Err(ProgramError::InvalidAccountData),
+    };
+
+    validate_deadline(instruction_data.deadline)?;
+
+    execute_swap(
+        accounts,
+        is_x,
+        SwapMode::ExactIn {
+            amount: u64::from_le_bytes(instruction_data.amount),
+            min: u64::from_le_bytes(instruction_data.min),
+        },
+    )
+}
+
+/// Runs a swap. An optional referrer token account for the input mint may follow the pool
+/// accounts, to be paid its share of the swap fee.
+pub(crate) fn execute_swap(accounts: &[AccountInfo], is_x: bool, mode: SwapMode) -> ProgramResult {
+    let [user, mint_x, mint_y, vault_x, vault_y, user_x, user_y, mint_lp, user_lp, config, _system_program, token_program_x, token_program_y, stats, referrer @ ..] =
         accounts
     else {
         return Err(ProgramError::NotEnoughAccountKeys);
@@ -33,148 +75,217 @@ pub fn process_swap(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
         return Err(ProgramError::MissingRequiredSignature);
     }
 
-    let instruction_data = bytemuck::try_from_bytes::<SwapInstructionData>(&data)
-        .map_err(|_| ProgramError::InvalidInstructionData)?;
-
     let config_account = Config::load(config)?;
-
-    // Validate config account.
-    let config_pda = pubkey::create_program_address(
-        &[
-            CONFIG_SEED.as_bytes(),
-            config_account.seed.as_ref(),
-            &[config_account.config_bump],
-        ],
-        &crate::ID,
-    )?;
-    if config.key() != &config_pda {
-        return Err(ProgramError::InvalidAccountData);
+    if config_account.locked != 0 || config_account.flash_loan != 0 {
+        return Err(CustomError::PoolLocked.into());
     }
 
-    // Validate vault accounts.
-    let vault_x_amount;
-    let vault_y_amount;
-    {
-        let vault_x_account = TokenAccount::from_account_info(vault_x)?;
-        let vault_y_account = TokenAccount::from_account_info(vault_y)?;
-        if vault_x_account.owner() != config.key() {
-            return Err(ProgramError::InvalidAccountData);
-        }
-        if vault_y_account.owner() != config.key() {
-            return Err(ProgramError::InvalidAccountData);
-        }
-        vault_x_amount = vault_x_account.amount();
-        vault_y_amount = vault_y_account.amount();
-    }
+    // Validate pool accounts.
+    validate_config(config, config_account)?;
+    validate_mints(config_account, mint_x, mint_y)?;
+    let (vault_x_amount, vault_y_amount) =
+        validate_vaults(config, config_account, mint_x, mint_y, vault_x, vault_y)?;
+    let (vault_x_amount, vault_y_amount) =
+        config_account.reserves(vault_x_amount, vault_y_amount)?;
+    validate_mint_lp(config, config_account, mint_lp)?;
+    validate_mint_token_program(mint_x, token_program_x)?;
+    validate_mint_token_program(mint_y, token_program_y)?;
+    let stats_account = Stats::load(stats)?;
+    validate_stats(config, stats_account)?;
 
-    // Validate mint_lp account.
-    let mint_lp_supply;
-    {
-        let mint_lp_account = Mint::from_account_info(mint_lp)?;
-        if mint_lp_account.mint_authority() != Some(config.key()) {
-            return Err(ProgramError::InvalidAccountData);
-        }
-        mint_lp_supply = mint_lp_account.supply();
-    }
+    // Accrue the price oracle with the reserves from before this instruction.
+    let clock = Clock::get()?;
+    config_account.update_oracle(vault_x_amount, vault_y_amount, clock.unix_timestamp);
 
     // Validate user accounts.
-    {
-        let user_x_account = TokenAccount::from_account_info(user_x)?;
-        let user_y_account = TokenAccount::from_account_info(user_y)?;
-        let user_lp_account = TokenAccount::from_account_info(user_lp)?;
-        if user_x_account.owner() != user.key() {
-            return Err(ProgramError::InvalidAccountData);
-        }
-        if user_y_account.owner() != user.key() {
-            return Err(ProgramError::InvalidAccountData);
-        }
-        if user_lp_account.owner() != user.key() {
-            return Err(ProgramError::InvalidAccountData);
-        }
-    }
+    validate_token_account(user_x, user)?;
+    validate_token_account(user_y, user)?;
+    validate_token_account(user_lp, user)?;
 
     let mint_x_account = Mint::from_account_info(mint_x)?;
     let mint_y_account = Mint::from_account_info(mint_y)?;
 
-    let amount = u64::from_le_bytes(instruction_data.amount);
-    let is_x = match instruction_data.is_x {
-        0 => false,
-        1 => true,
-        _ => return Err(ProgramError::InvalidAccountData),
+    let (reserve_in, reserve_out) = match is_x {
+        true => (vault_x_amount, vault_y_amount),
+        false => (vault_y_amount, vault_x_amount),
     };
 
-    let mut curve = ConstantProduct::init(
-        vault_x_amount,
-        vault_y_amount,
-        mint_lp_supply,
-        u16::from_le_bytes(config_account.fee),
-        None,
-    )
-    .map_err(|_| CustomError::InvalidSwap)?;
-
-    let p = match is_x {
-        true => LiquidityPair::X,
-        false => LiquidityPair::Y,
+    // The swap is priced on what reaches the vault, net of any transfer fee, so the input goes
+    // in first.
+    let amount_in = match mode {
+        SwapMode::ExactIn { amount, .. } => amount,
+        SwapMode::ExactOut { amount, max } => {
+            let deposit = swap_amount_in(config_account, is_x, reserve_in, reserve_out, amount)?;
+            if deposit > max {
+                return Err(CustomError::SwapSlippageExceeded.into());
+            }
+            deposit
+        }
     };
 
-    let res = curve
-        .swap(p, amount, u64::from_le_bytes(instruction_data.min))
-        .map_err(|_| CustomError::InvalidSwap)?;
-
-    // Setup signer seeds.
-    let bump = [config_account.config_bump];
-    let seed = [
-        Seed::from(CONFIG_SEED.as_bytes()),
-        Seed::from(config_account.seed.as_ref()),
-        Seed::from(&bump),
-    ];
-    let seeds = Signer::from(&seed);
-
     // Deposit to pool.
-    if is_x {
-        pinocchio_token::instructions::TransferChecked {
+    let deposit = if is_x {
+        token::instructions::TransferChecked {
             from: user_x,
             mint: mint_x,
             to: vault_x,
             authority: user,
-            amount: res.deposit,
+            amount: amount_in,
             decimals: mint_x_account.decimals(),
+            token_program: token_program_x.key(),
         }
-        .invoke()?;
+        .invoke_signed_received(&[])?
     } else {
-        pinocchio_token::instructions::TransferChecked {
+        token::instructions::TransferChecked {
             from: user_y,
             mint: mint_y,
             to: vault_y,
             authority: user,
-            amount: res.deposit,
+            amount: amount_in,
             decimals: mint_y_account.decimals(),
+            token_program: token_program_y.key(),
         }
-        .invoke()?;
-    }
+        .invoke_signed_received(&[])?
+    };
+
+    let withdraw = match mode {
+        SwapMode::ExactIn { .. } => {
+            swap_amount_out(config_account, is_x, reserve_in, reserve_out, deposit)?
+        }
+        // The price of an exact output leaves no room for a transfer fee on the input.
+        SwapMode::ExactOut { amount, .. } => {
+            if deposit < amount_in {
+                return Err(CustomError::InvalidSwap.into());
+            }
+            amount
+        }
+    };
+
+    // Keep the protocol's cut of the fee out of the pool reserves.
+    let protocol_fee = config_account.accrue_protocol_fee(is_x, deposit)?;
+
+    // Setup signer seeds.
+    let seed = config_account.signer_seeds().map(Seed::from);
+    let seeds = Signer::from(&seed);
 
     // Withdraw from pool.
-    if is_x {
-        pinocchio_token::instructions::TransferChecked {
+    let received = if is_x {
+        token::instructions::TransferChecked {
             from: vault_y,
             mint: mint_y,
             to: user_y,
             authority: config,
-            amount: res.withdraw,
+            amount: withdraw,
             decimals: mint_y_account.decimals(),
+            token_program: token_program_y.key(),
         }
-        .invoke_signed(&[seeds.clone()])?;
+        .invoke_signed_received(&[seeds.clone()])?
     } else {
-        pinocchio_token::instructions::TransferChecked {
+        token::instructions::TransferChecked {
             from: vault_x,
             mint: mint_x,
             to: user_x,
             authority: config,
-            amount: res.withdraw,
+            amount: withdraw,
             decimals: mint_x_account.decimals(),
+            token_program: token_program_x.key(),
+        }
+        .invoke_signed_received(&[seeds.clone()])?
+    };
+
+    // Slippage protection, on what reaches the user net of any transfer fee on the output.
+    match mode {
+        SwapMode::ExactIn { min, .. } if received < min => {
+            return Err(CustomError::SwapSlippageExceeded.into());
+        }
+        SwapMode::ExactOut { amount, .. } if received < amount => {
+            return Err(CustomError::InvalidSwap.into());
+        }
+        _ => {}
+    }
+
+    // Pay the referrer's share of the fee out of the input vault.
+    let referral_fee = match referrer.first() {
+        Some(referrer) => {
+            let (vault_in, mint_in, decimals, token_program) = match is_x {
+                true => (vault_x, mint_x, mint_x_account.decimals(), token_program_x),
+                false => (vault_y, mint_y, mint_y_account.decimals(), token_program_y),
+            };
+            if TokenAccount::from_account_info(referrer)?.mint() != mint_in.key() {
+                return Err(CustomError::MintMismatch.into());
+            }
+            let referral_fee = config_account.referral_fee_amount(deposit);
+            if referral_fee > 0 {
+                token::instructions::TransferChecked {
+                    from: vault_in,
+                    mint: mint_in,
+                    to: referrer,
+                    authority: config,
+                    amount: referral_fee,
+                    decimals,
+                    token_program: token_program.key(),
+                }
+                .invoke_signed(&[seeds.clone()])?;
+            }
+            referral_fee
         }
-        .invoke_signed(&[seeds.clone()])?;
+        None => 0,
+    };
+
+    // Log the swap for indexers, and count it in the pool statistics.
+    let fee = deposit
+        - amount_after_fee(deposit, config_account.swap_fee()).ok_or(CustomError::InvalidSwap)?;
+    stats_account.record_swap(is_x, deposit, withdraw, fee, clock.slot);
+    // Reserves after the swap, without the protocol and referral fees.
+    let (reserve_in, reserve_out) = (
+        reserve_in + deposit - protocol_fee - referral_fee,
+        reserve_out - withdraw,
+    );
+    let (reserve_x, reserve_y) = match is_x {
+        true => (reserve_in, reserve_out),
+        false => (reserve_out, reserve_in),
+    };
+    SwapEvent {
+        pool: *config.key(),
+        user: *user.key(),
+        is_x: is_x as u8,
+        amount_in: deposit.to_le_bytes(),
+        amount_out: withdraw.to_le_bytes(),
+        fee: fee.to_le_bytes(),
+        reserve_x: reserve_x.to_le_bytes(),
+        reserve_y: reserve_y.to_le_bytes(),
     }
+    .emit();
 
     Ok(())
 }
+
+/// Returns the output for exactly `amount_in` in, after the pool fee.
+pub(crate) fn swap_amount_out(
+    config_account: &Config,
+    is_x: bool,
+    reserve_in: u64,
+    reserve_out: u64,
+    amount_in: u64,
+) -> Result<u64, ProgramError> {
+    let curve = config_account.curve()?.oriented(is_x);
+    amount_after_fee(amount_in, config_account.swap_fee())
+        .and_then(|amount_in| curve.swap_exact_in(reserve_in, reserve_out, amount_in))
+        .filter(|amount_out| *amount_out > 0)
+        .ok_or(CustomError::InvalidSwap.into())
+}
+
+/// Returns the input, fee included, needed for exactly `amount_out` out.
+pub(crate) fn swap_amount_in(
+    config_account: &Config,
+    is_x: bool,
+    reserve_in: u64,
+    reserve_out: u64,
+    amount_out: u64,
+) -> Result<u64, ProgramError> {
+    let curve = config_account.curve()?.oriented(is_x);
+    curve
+        .swap_exact_out(reserve_in, reserve_out, amount_out)
+        .and_then(|amount_in| amount_before_fee(amount_in, config_account.swap_fee()))
+        .ok_or(CustomError::InvalidSwap.into())
+}
@@ -0,0 +1,41 @@
+use bytemuck::{Pod, Zeroable};
+use pinocchio::{account_info::AccountInfo, program_error::ProgramError, ProgramResult};
+
+use super::swap::{execute_swap, SwapMode};
+use crate::validation::validate_deadline;
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct SwapExactOutInstructionData {
+    pub is_x: u8,
+    pub amount: [u8; 8],
+    pub max: [u8; 8],
+    // Unix timestamp after which the swap is rejected, or 0 for no deadline.
+    pub deadline: [u8; 8],
+}
+
+impl SwapExactOutInstructionData {
+    pub const LEN: usize = core::mem::size_of::<SwapExactOutInstructionData>();
+}
+
+pub fn process_swap_exact_out(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let instruction_data = bytemuck::try_from_bytes::<SwapExactOutInstructionData>(&data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+
+    let is_x = match instruction_data.is_x {
+        0 => false,
+        1 => true,
+        _ => return Err(ProgramError::InvalidAccountData),
+    };
+
+    validate_deadline(instruction_data.deadline)?;
+
+    execute_swap(
+        accounts,
+        is_x,
+        SwapMode::ExactOut {
+            amount: u64::from_le_bytes(instruction_data.amount),
+            max: u64::from_le_bytes(instruction_data.max),
+        },
+    )
+}
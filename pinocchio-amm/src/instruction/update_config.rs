@@ -0,0 +1,64 @@
+use bytemuck::{Pod, Zeroable};
+use pinocchio::{account_info::AccountInfo, program_error::ProgramError, ProgramResult};
+
+use crate::{
+    constants::MAX_FEE,
+    error::CustomError,
+    state::Config,
+    validation::{validate_authority, validate_config},
+};
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct UpdateConfigInstructionData {
+    pub fee: [u8; 2],
+    pub locked: u8,
+    pub referral_fee: [u8; 2],
+}
+
+impl UpdateConfigInstructionData {
+    pub const LEN: usize = core::mem::size_of::<UpdateConfigInstructionData>();
+}
+
+pub fn process_update_config(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let [authority, config] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if !authority.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let instruction_data = bytemuck::try_from_bytes::<UpdateConfigInstructionData>(&data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+
+    let config_account = Config::load(config)?;
+
+    // Validate pool accounts.
+    validate_config(config, config_account)?;
+    validate_authority(config_account, authority)?;
+
+    if u16::from_le_bytes(instruction_data.fee) > MAX_FEE {
+        return Err(CustomError::InvalidFee.into());
+    }
+    // Factory pools are found by their fee tier, so their fee cannot move away from it.
+    if config_account.factory != 0 && instruction_data.fee != config_account.fee_tier {
+        return Err(CustomError::InvalidFee.into());
+    }
+    // The protocol and the referrer are both paid out of the swap fee.
+    if u16::from_le_bytes(instruction_data.referral_fee) as u32
+        + u16::from_le_bytes(config_account.protocol_fee) as u32
+        > 10_000
+    {
+        return Err(CustomError::InvalidFee.into());
+    }
+    if instruction_data.locked > 1 {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    config_account.fee = instruction_data.fee;
+    config_account.locked = instruction_data.locked;
+    config_account.referral_fee = instruction_data.referral_fee;
+
+    Ok(())
+}
@@ -0,0 +1,64 @@
+use bytemuck::{Pod, Zeroable};
+use pinocchio::{account_info::AccountInfo, program_error::ProgramError, ProgramResult};
+
+use crate::{
+    constants::MAX_FEE,
+    error::CustomError,
+    state::Config,
+    validation::{validate_authority, validate_config},
+};
+
+/// Turns dynamic-fee mode on when `dynamic_fee` is 1, or off when it is 0, with the fee capped at
+/// `max_fee` and raised by `volatility_factor` basis points of the volatility.
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct UpdateDynamicFeeInstructionData {
+    pub dynamic_fee: u8,
+    pub max_fee: [u8; 2],
+    pub volatility_factor: [u8; 2],
+}
+
+impl UpdateDynamicFeeInstructionData {
+    pub const LEN: usize = core::mem::size_of::<UpdateDynamicFeeInstructionData>();
+}
+
+pub fn process_update_dynamic_fee(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let [authority, config] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if !authority.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let instruction_data = bytemuck::try_from_bytes::<UpdateDynamicFeeInstructionData>(&data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+
+    let config_account = Config::load(config)?;
+
+    // Validate pool accounts.
+    validate_config(config, config_account)?;
+    validate_authority(config_account, authority)?;
+
+    if instruction_data.dynamic_fee > 1 {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    let max_fee = u16::from_le_bytes(instruction_data.max_fee);
+    if max_fee > MAX_FEE || max_fee < u16::from_le_bytes(config_account.fee) {
+        return Err(CustomError::InvalidFee.into());
+    }
+
+    // Switching modes starts the volatility over, so that it is never measured against stale
+    // samples.
+    if instruction_data.dynamic_fee != config_account.dynamic_fee {
+        config_account.volatility = [0; 4];
+        config_account.price_sample_index = 0;
+        config_account.price_samples = Zeroable::zeroed();
+    }
+
+    config_account.dynamic_fee = instruction_data.dynamic_fee;
+    config_account.max_dynamic_fee = instruction_data.max_fee;
+    config_account.volatility_factor = instruction_data.volatility_factor;
+
+    Ok(())
+}
@@ -1,21 +1,30 @@
 use bytemuck::{Pod, Zeroable};
-use constant_product_curve::ConstantProduct;
 use pinocchio::{
     account_info::AccountInfo,
     instruction::{Seed, Signer},
     program_error::ProgramError,
-    pubkey, ProgramResult,
+    sysvars::{clock::Clock, Sysvar},
+    ProgramResult,
 };
-use pinocchio_token::state::{Mint, TokenAccount};
 
-use crate::{constants::CONFIG_SEED, error::CustomError, state::Config};
+use crate::{
+    error::CustomError,
+    events::{Event, WithdrawEvent},
+    math::withdraw_amounts,
+    state::{Config, Stats},
+    token::{self, Mint},
+    validation::{
+        validate_config, validate_mint_lp, validate_mint_token_program, validate_mints,
+        validate_stats, validate_token_account, validate_vaults,
+    },
+};
 
 #[repr(C)]
 #[derive(Clone, Copy, Pod, Zeroable)]
 pub struct WithdrawInstructionData {
     pub amount: [u8; 8],
-    pub max_x: [u8; 8],
-    pub max_y: [u8; 8],
+    pub min_x: [u8; 8],
+    pub min_y: [u8; 8],
 }
 
 impl WithdrawInstructionData {
@@ -23,7 +32,7 @@ impl WithdrawInstructionData {
 }
 
 pub fn process_withdraw(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
-    let [user, mint_x, mint_y, vault_x, vault_y, user_x, user_y, mint_lp, user_lp, config, _system_program, _token_program] =
+    let [user, mint_x, mint_y, vault_x, vault_y, user_x, user_y, mint_lp, user_lp, config, _system_program, token_program_x, token_program_y, stats] =
         accounts
     else {
         return Err(ProgramError::NotEnoughAccountKeys);
@@ -37,123 +46,100 @@ pub fn process_withdraw(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult
         .map_err(|_| ProgramError::InvalidInstructionData)?;
 
     let config_account = Config::load(config)?;
-
-    // Validate config account.
-    let config_pda = pubkey::create_program_address(
-        &[
-            CONFIG_SEED.as_bytes(),
-            config_account.seed.as_ref(),
-            &[config_account.config_bump],
-        ],
-        &crate::ID,
-    )?;
-    if config.key() != &config_pda {
-        return Err(ProgramError::InvalidAccountData);
+    if config_account.locked != 0 || config_account.flash_loan != 0 {
+        return Err(CustomError::PoolLocked.into());
     }
 
-    // Validate vault accounts.
-    let vault_x_amount;
-    let vault_y_amount;
-    {
-        let vault_x_account = TokenAccount::from_account_info(vault_x)?;
-        let vault_y_account = TokenAccount::from_account_info(vault_y)?;
-        if vault_x_account.owner() != config.key() {
-            return Err(ProgramError::InvalidAccountData);
-        }
-        if vault_y_account.owner() != config.key() {
-            return Err(ProgramError::InvalidAccountData);
-        }
-        vault_x_amount = vault_x_account.amount();
-        vault_y_amount = vault_y_account.amount();
-    }
-
-    // Validate mint_lp account.
-    let mint_lp_supply;
-    {
-        let mint_lp_account = Mint::from_account_info(mint_lp)?;
-        if mint_lp_account.mint_authority() != Some(config.key()) {
-            return Err(ProgramError::InvalidAccountData);
-        }
-        mint_lp_supply = mint_lp_account.supply();
-    }
+    // Validate pool accounts.
+    validate_config(config, config_account)?;
+    validate_mints(config_account, mint_x, mint_y)?;
+    let (vault_x_amount, vault_y_amount) =
+        validate_vaults(config, config_account, mint_x, mint_y, vault_x, vault_y)?;
+    let (vault_x_amount, vault_y_amount) =
+        config_account.reserves(vault_x_amount, vault_y_amount)?;
+    let mint_lp_supply = validate_mint_lp(config, config_account, mint_lp)?;
+    validate_mint_token_program(mint_x, token_program_x)?;
+    validate_mint_token_program(mint_y, token_program_y)?;
+    let stats_account = Stats::load(stats)?;
+    validate_stats(config, stats_account)?;
+
+    // Accrue the price oracle with the reserves from before this instruction.
+    config_account.update_oracle(vault_x_amount, vault_y_amount, Clock::get()?.unix_timestamp);
 
     // Validate user accounts.
-    {
-        let user_x_account = TokenAccount::from_account_info(user_x)?;
-        let user_y_account = TokenAccount::from_account_info(user_y)?;
-        let user_lp_account = TokenAccount::from_account_info(user_lp)?;
-        if user_x_account.owner() != user.key() {
-            return Err(ProgramError::InvalidAccountData);
-        }
-        if user_y_account.owner() != user.key() {
-            return Err(ProgramError::InvalidAccountData);
-        }
-        if user_lp_account.owner() != user.key() {
-            return Err(ProgramError::InvalidAccountData);
-        }
-    }
+    validate_token_account(user_x, user)?;
+    validate_token_account(user_y, user)?;
+    validate_token_account(user_lp, user)?;
 
     let mint_x_account = Mint::from_account_info(mint_x)?;
     let mint_y_account = Mint::from_account_info(mint_y)?;
 
     let amount = u64::from_le_bytes(instruction_data.amount);
-    let max_x = u64::from_le_bytes(instruction_data.max_x);
-    let max_y = u64::from_le_bytes(instruction_data.max_y);
-
-    let (x, y) = match mint_lp_supply == 0 && vault_x_amount == 0 && vault_y_amount == 0 {
-        true => (max_x, max_y),
-        false => {
-            let amounts = ConstantProduct::xy_withdraw_amounts_from_l(
-                vault_x_amount,
-                vault_y_amount,
-                mint_lp_supply,
-                amount,
-                6,
-            )
-            .map_err(|_| CustomError::InvalidWithdrawal)?;
-            (amounts.x, amounts.y)
-        }
-    };
+    let min_x = u64::from_le_bytes(instruction_data.min_x);
+    let min_y = u64::from_le_bytes(instruction_data.min_y);
+
+    if mint_lp_supply == 0 {
+        return Err(CustomError::InvalidWithdrawal.into());
+    }
+
+    let (x, y) = withdraw_amounts(vault_x_amount, vault_y_amount, mint_lp_supply, amount)
+        .ok_or(CustomError::InvalidWithdrawal)?;
+
+    // Slippage protection.
+    if x < min_x || y < min_y {
+        return Err(CustomError::WithdrawSlippageExceeded.into());
+    }
 
     // Setup signer seeds.
-    let bump = [config_account.config_bump];
-    let seed = [
-        Seed::from(CONFIG_SEED.as_bytes()),
-        Seed::from(config_account.seed.as_ref()),
-        Seed::from(&bump),
-    ];
+    let seed = config_account.signer_seeds().map(Seed::from);
     let seeds = Signer::from(&seed);
 
     // Withdraw mint_x tokens.
-    pinocchio_token::instructions::TransferChecked {
+    token::instructions::TransferChecked {
         from: vault_x,
         mint: mint_x,
         to: user_x,
         authority: config,
         amount: x,
         decimals: mint_x_account.decimals(),
+        token_program: token_program_x.key(),
     }
     .invoke_signed(&[seeds.clone()])?;
 
     // Withdraw mint_y tokens.
-    pinocchio_token::instructions::TransferChecked {
+    token::instructions::TransferChecked {
         from: vault_y,
         mint: mint_y,
         to: user_y,
         authority: config,
         amount: y,
         decimals: mint_y_account.decimals(),
+        token_program: token_program_y.key(),
     }
     .invoke_signed(&[seeds.clone()])?;
 
     // Burn mint_lp tokens.
-    pinocchio_token::instructions::Burn {
+    token::instructions::Burn {
         account: user_lp,
         mint: mint_lp,
         authority: user,
         amount: amount,
+        token_program: token_program_x.key(),
     }
     .invoke()?;
 
+    // Log the withdrawal for indexers, and count it in the pool statistics.
+    stats_account.record_withdraw();
+    WithdrawEvent {
+        pool: *config.key(),
+        user: *user.key(),
+        amount_x: x.to_le_bytes(),
+        amount_y: y.to_le_bytes(),
+        amount_lp: amount.to_le_bytes(),
+        reserve_x: (vault_x_amount - x).to_le_bytes(),
+        reserve_y: (vault_y_amount - y).to_le_bytes(),
+    }
+    .emit();
+
     Ok(())
 }
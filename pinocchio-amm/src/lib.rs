@@ -1,7 +1,11 @@
 pub mod constants;
 pub mod error;
+pub mod events;
 pub mod instruction;
+pub mod math;
 pub mod state;
+pub mod token;
+pub mod validation;
 
 #[cfg(not(feature = "no-bpf-entrypoint"))]
 mod entrypoint;
@@ -0,0 +1,51 @@
+//! Constant product invariant: `x * y = k`.
+//!
+//! All functions use checked `u128` arithmetic and return `None` on overflow or when the trade
+//! cannot be filled. Amounts are rounded in the pool's favour, so `k` never decreases.
+
+/// Returns the LP minted for the first deposit of `x` and `y`, `sqrt(x * y)` rounded down.
+pub fn initial_liquidity(x: u64, y: u64) -> u64 {
+    let product = x as u128 * y as u128;
+    if product == 0 {
+        return 0;
+    }
+
+    // Newton's method, starting above the root so the iterates decrease monotonically.
+    let mut root = product;
+    let mut next = (product + 1) >> 1;
+    while next < root {
+        root = next;
+        next = (root + product / root) >> 1;
+    }
+
+    root as u64
+}
+
+/// Returns the output for exactly `amount_in` (net of fees) in, rounded down.
+pub fn swap_exact_in(reserve_in: u64, reserve_out: u64, amount_in: u64) -> Option<u64> {
+    if amount_in == 0 || reserve_in == 0 || reserve_out == 0 {
+        return None;
+    }
+
+    // reserve_out * amount_in / (reserve_in + amount_in)
+    let amount_out = (reserve_out as u128)
+        .checked_mul(amount_in as u128)?
+        .checked_div((reserve_in as u128).checked_add(amount_in as u128)?)?;
+
+    u64::try_from(amount_out).ok()
+}
+
+/// Returns the input (net of fees) needed to take exactly `amount_out` out of the pool, rounded
+/// up.
+pub fn swap_exact_out(reserve_in: u64, reserve_out: u64, amount_out: u64) -> Option<u64> {
+    if amount_out == 0 || reserve_in == 0 || amount_out >= reserve_out {
+        return None;
+    }
+
+    // reserve_in * amount_out / (reserve_out - amount_out)
+    let amount_in = (reserve_in as u128)
+        .checked_mul(amount_out as u128)?
+        .div_ceil((reserve_out - amount_out) as u128);
+
+    u64::try_from(amount_in).ok()
+}
@@ -0,0 +1,183 @@
+//! Pricing and liquidity math. Only depends on `core`, so it can be used off-chain as is.
+
+pub mod constant_product;
+pub mod stable_swap;
+pub mod weighted;
+
+use crate::constants::{MAX_AMP, MIN_WEIGHT};
+
+/// Pricing invariant of a pool, as seen by a trade of x into y unless `oriented` otherwise.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Curve {
+    /// `x * y = k`.
+    ConstantProduct,
+    /// Curve's StableSwap invariant with amplification coefficient `amp`, for pegged pairs.
+    StableSwap { amp: u64 },
+    /// `x^wx * y^wy = V`, for pools other than 50/50, with the weights of the input and output
+    /// tokens in basis points.
+    Weighted { weight_in: u16, weight_out: u16 },
+}
+
+impl Curve {
+    pub fn new(curve_type: u8, amp: u64, weight_x: u16, weight_y: u16) -> Option<Self> {
+        match curve_type {
+            0 => Some(Curve::ConstantProduct),
+            1 if (1..=MAX_AMP).contains(&amp) => Some(Curve::StableSwap { amp }),
+            2 if weight_x >= MIN_WEIGHT
+                && weight_y >= MIN_WEIGHT
+                && weight_x as u32 + weight_y as u32 == 10_000 =>
+            {
+                Some(Curve::Weighted {
+                    weight_in: weight_x,
+                    weight_out: weight_y,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the curve as seen by a trade of x into y when `is_x`, of y into x otherwise. Only
+    /// weighted pools tell the two apart.
+    pub fn oriented(self, is_x: bool) -> Self {
+        match self {
+            Curve::Weighted {
+                weight_in,
+                weight_out,
+            } if !is_x => Curve::Weighted {
+                weight_in: weight_out,
+                weight_out: weight_in,
+            },
+            curve => curve,
+        }
+    }
+
+    /// Returns the output for exactly `amount_in` (net of fees) in, rounded down.
+    pub fn swap_exact_in(&self, reserve_in: u64, reserve_out: u64, amount_in: u64) -> Option<u64> {
+        match *self {
+            Curve::ConstantProduct => {
+                constant_product::swap_exact_in(reserve_in, reserve_out, amount_in)
+            }
+            Curve::StableSwap { amp } => {
+                stable_swap::swap_exact_in(reserve_in, reserve_out, amount_in, amp)
+            }
+            Curve::Weighted {
+                weight_in,
+                weight_out,
+            } => weighted::swap_exact_in(reserve_in, reserve_out, amount_in, weight_in, weight_out),
+        }
+    }
+
+    /// Returns the output for `amount_in` at the current marginal price, i.e. without price impact.
+    /// StableSwap pools read the marginal price off a swap of 0.01% of the input reserve, weighted
+    /// pools price each token at its reserve over its weight.
+    pub fn spot_amount_out(
+        &self,
+        reserve_in: u64,
+        reserve_out: u64,
+        amount_in: u64,
+    ) -> Option<u64> {
+        let (probe_in, probe_out) = match *self {
+            Curve::ConstantProduct => (reserve_in as u128, reserve_out as u128),
+            Curve::StableSwap { amp } => {
+                let probe_in = reserve_in / 10_000 + 1;
+                let probe_out = stable_swap::swap_exact_in(reserve_in, reserve_out, probe_in, amp)?;
+                (probe_in as u128, probe_out as u128)
+            }
+            Curve::Weighted {
+                weight_in,
+                weight_out,
+            } => (
+                reserve_in as u128 * weight_out as u128,
+                reserve_out as u128 * weight_in as u128,
+            ),
+        };
+        let amount_out = (amount_in as u128)
+            .checked_mul(probe_out)?
+            .checked_div(probe_in)?;
+        u64::try_from(amount_out).ok()
+    }
+
+    /// Returns the input (net of fees) needed for exactly `amount_out` out, rounded up.
+    pub fn swap_exact_out(
+        &self,
+        reserve_in: u64,
+        reserve_out: u64,
+        amount_out: u64,
+    ) -> Option<u64> {
+        match *self {
+            Curve::ConstantProduct => {
+                constant_product::swap_exact_out(reserve_in, reserve_out, amount_out)
+            }
+            Curve::StableSwap { amp } => {
+                stable_swap::swap_exact_out(reserve_in, reserve_out, amount_out, amp)
+            }
+            Curve::Weighted {
+                weight_in,
+                weight_out,
+            } => {
+                weighted::swap_exact_out(reserve_in, reserve_out, amount_out, weight_in, weight_out)
+            }
+        }
+    }
+}
+
+/// Deducts a fee of `fee` basis points from `amount`, rounding the remainder down.
+pub fn amount_after_fee(amount: u64, fee: u16) -> Option<u64> {
+    let amount = (amount as u128).checked_mul(10_000u128.checked_sub(fee as u128)?)? / 10_000;
+    u64::try_from(amount).ok()
+}
+
+/// Returns the amount which is at least `amount` once a fee of `fee` basis points is deducted.
+pub fn amount_before_fee(amount: u64, fee: u16) -> Option<u64> {
+    let amount = (amount as u128)
+        .checked_mul(10_000)?
+        .div_ceil(10_000u128.checked_sub(fee as u128)?);
+    u64::try_from(amount).ok()
+}
+
+/// Returns the token amounts to deposit in exchange for `lp` new LP tokens, rounded up.
+pub fn deposit_amounts(reserve_x: u64, reserve_y: u64, supply: u64, lp: u64) -> Option<(u64, u64)> {
+    if supply == 0 {
+        return None;
+    }
+    let x = (reserve_x as u128)
+        .checked_mul(lp as u128)?
+        .div_ceil(supply as u128);
+    let y = (reserve_y as u128)
+        .checked_mul(lp as u128)?
+        .div_ceil(supply as u128);
+    Some((u64::try_from(x).ok()?, u64::try_from(y).ok()?))
+}
+
+/// Returns the LP tokens minted for depositing `x` and `y`, in proportion to the scarcer of the
+/// two, rounded down.
+pub fn deposit_liquidity(
+    reserve_x: u64,
+    reserve_y: u64,
+    supply: u64,
+    x: u64,
+    y: u64,
+) -> Option<u64> {
+    let lp_x = (x as u128)
+        .checked_mul(supply as u128)?
+        .checked_div(reserve_x as u128)?;
+    let lp_y = (y as u128)
+        .checked_mul(supply as u128)?
+        .checked_div(reserve_y as u128)?;
+    u64::try_from(lp_x.min(lp_y)).ok()
+}
+
+/// Returns the token amounts redeemed by burning `lp` LP tokens, rounded down.
+pub fn withdraw_amounts(
+    reserve_x: u64,
+    reserve_y: u64,
+    supply: u64,
+    lp: u64,
+) -> Option<(u64, u64)> {
+    if supply == 0 || lp > supply {
+        return None;
+    }
+    let x = (reserve_x as u128).checked_mul(lp as u128)? / supply as u128;
+    let y = (reserve_y as u128).checked_mul(lp as u128)? / supply as u128;
+    Some((x as u64, y as u64))
+}
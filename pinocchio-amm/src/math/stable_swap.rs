@@ -0,0 +1,110 @@
+//! Two-token StableSwap invariant: `4A(x + y) + D = 4AD + D^3 / (4xy)`.
+//!
+//! All functions use checked `u128` arithmetic and return `None` on overflow or when the
+//! iterations do not converge. Amounts are rounded in the pool's favour.
+
+const N_COINS: u128 = 2;
+const MAX_ITERATIONS: usize = 255;
+
+/// Computes the invariant `D` of a pool holding `x` and `y`.
+pub fn compute_d(x: u64, y: u64, amp: u64) -> Option<u128> {
+    let (x, y) = (x as u128, y as u128);
+    let sum = x.checked_add(y)?;
+    if sum == 0 {
+        return Some(0);
+    }
+    if x == 0 || y == 0 {
+        return None;
+    }
+
+    let ann = (amp as u128).checked_mul(N_COINS * N_COINS)?;
+    let mut d = sum;
+    for _ in 0..MAX_ITERATIONS {
+        // d_p = D^3 / (4xy)
+        let d_p = d
+            .checked_mul(d)?
+            .checked_div(x.checked_mul(N_COINS)?)?
+            .checked_mul(d)?
+            .checked_div(y.checked_mul(N_COINS)?)?;
+
+        let d_prev = d;
+        let numerator = ann
+            .checked_mul(sum)?
+            .checked_add(d_p.checked_mul(N_COINS)?)?
+            .checked_mul(d)?;
+        let denominator = ann
+            .checked_sub(1)?
+            .checked_mul(d)?
+            .checked_add(d_p.checked_mul(N_COINS + 1)?)?;
+        d = numerator.checked_div(denominator)?;
+
+        if d.abs_diff(d_prev) <= 1 {
+            return Some(d);
+        }
+    }
+
+    None
+}
+
+/// Computes the balance of the other token that keeps the invariant at `d` once one side of
+/// the pool holds `x`.
+pub fn compute_y(x: u64, d: u128, amp: u64) -> Option<u128> {
+    let x = x as u128;
+    if x == 0 {
+        return None;
+    }
+
+    let ann = (amp as u128).checked_mul(N_COINS * N_COINS)?;
+    // c = D^3 / (4x * Ann * 2), b = x + D / Ann
+    let c = d
+        .checked_mul(d)?
+        .checked_div(x.checked_mul(N_COINS)?)?
+        .checked_mul(d)?
+        .checked_div(ann.checked_mul(N_COINS)?)?;
+    let b = x.checked_add(d.checked_div(ann)?)?;
+
+    let mut y = d;
+    for _ in 0..MAX_ITERATIONS {
+        let y_prev = y;
+        // y = (y^2 + c) / (2y + b - D)
+        let numerator = y.checked_mul(y)?.checked_add(c)?;
+        let denominator = y.checked_mul(2)?.checked_add(b)?.checked_sub(d)?;
+        y = numerator.checked_div(denominator)?;
+
+        if y.abs_diff(y_prev) <= 1 {
+            return Some(y);
+        }
+    }
+
+    None
+}
+
+/// Returns the output for swapping `amount_in` (after fees) into the pool, rounded down.
+pub fn swap_exact_in(reserve_in: u64, reserve_out: u64, amount_in: u64, amp: u64) -> Option<u64> {
+    // Round the invariant up, as a lower D would value the pool's balances less.
+    let d = compute_d(reserve_in, reserve_out, amp)?.checked_add(1)?;
+    let new_reserve_out = compute_y(reserve_in.checked_add(amount_in)?, d, amp)?;
+
+    // Subtract one to absorb the rounding error of the iterations.
+    let amount_out = (reserve_out as u128)
+        .checked_sub(new_reserve_out)?
+        .checked_sub(1)?;
+    u64::try_from(amount_out).ok()
+}
+
+/// Returns the input (before fees) needed to take `amount_out` out of the pool, rounded up.
+pub fn swap_exact_out(reserve_in: u64, reserve_out: u64, amount_out: u64, amp: u64) -> Option<u64> {
+    if amount_out >= reserve_out {
+        return None;
+    }
+
+    // Round the invariant up, as a lower D would value the pool's balances less.
+    let d = compute_d(reserve_in, reserve_out, amp)?.checked_add(1)?;
+    let new_reserve_in = compute_y(reserve_out - amount_out, d, amp)?;
+
+    // Add one to absorb the rounding error of the iterations.
+    let amount_in = new_reserve_in
+        .checked_sub(reserve_in as u128)?
+        .checked_add(1)?;
+    u64::try_from(amount_in).ok()
+}
@@ -0,0 +1,147 @@
+//! Two-token weighted product invariant: `x^wx * y^wy = V`, with the weights in basis points
+//! adding up to 10_000.
+//!
+//! Powers are approximated in Q64.64 fixed point as `2^(e * log2(b))`. All functions use checked
+//! arithmetic and return `None` on overflow or when the trade cannot be filled. Amounts are
+//! rounded in the pool's favour, by a margin wider than the error of the approximation.
+
+const ONE: u128 = 1 << 64;
+
+/// `ln(2)` in Q64.64.
+const LN_2: u128 = 0xB172_17F7_D1CF_79AB;
+
+/// Relative margin applied to every power, as a shift: `2^-48`, about `4e-15`.
+const POW_MARGIN_SHIFT: u32 = 48;
+
+/// Returns `log2(x)` for `x` in Q64.64, as a signed Q64.64 number, or `None` for zero.
+pub fn log2(x: u128) -> Option<i128> {
+    if x == 0 {
+        return None;
+    }
+    let msb = 127 - x.leading_zeros() as i128;
+
+    // Normalize x into [1, 2), as a Q1.63 number, so that its square fits in 128 bits.
+    let mut m = match msb >= 63 {
+        true => x >> (msb - 63),
+        false => x << (63 - msb),
+    };
+
+    // Each squaring doubles the logarithm, its integer part being the next bit of the fraction.
+    let mut fraction: u128 = 0;
+    for bit in (0..64).rev() {
+        m = (m * m) >> 63;
+        if m >= ONE {
+            m >>= 1;
+            fraction |= 1 << bit;
+        }
+    }
+
+    Some(((msb - 64) << 64) + fraction as i128)
+}
+
+/// Returns `2^x` for a signed Q64.64 `x`, as a Q64.64 number, or `None` on overflow.
+pub fn exp2(x: i128) -> Option<u128> {
+    let integer = x >> 64;
+    let fraction = (x - (integer << 64)) as u128;
+
+    // 2^f = e^(f * ln(2)), summed as a Taylor series, which converges quickly for f < 1.
+    let z = (fraction * LN_2) >> 64;
+    let mut term = ONE;
+    let mut sum = ONE;
+    let mut k = 1;
+    while term > 0 {
+        term = ((term * z) >> 64) / k;
+        sum += term;
+        k += 1;
+    }
+
+    match integer >= 0 {
+        // sum < 2^65, so shifting by up to 62 bits cannot overflow.
+        true => (integer < sum.leading_zeros() as i128).then(|| sum << integer),
+        false => Some(
+            sum.checked_shr(u32::try_from(-integer).unwrap_or(u32::MAX))
+                .unwrap_or(0),
+        ),
+    }
+}
+
+/// Returns `base^(numerator / denominator)` for `base` in Q64.64, as a Q64.64 number.
+pub fn pow(base: u128, numerator: u64, denominator: u64) -> Option<u128> {
+    if denominator == 0 {
+        return None;
+    }
+    let exponent = log2(base)?
+        .checked_mul(numerator as i128)?
+        .checked_div(denominator as i128)?;
+    exp2(exponent)
+}
+
+/// Returns the margin by which `value`, a result of `exp2`, is rounded.
+fn margin(value: u128) -> u128 {
+    (value >> POW_MARGIN_SHIFT) + 1
+}
+
+/// `pow` rounded up by the margin.
+fn pow_up(base: u128, numerator: u64, denominator: u64) -> Option<u128> {
+    let power = pow(base, numerator, denominator)?;
+    power.checked_add(margin(power))
+}
+
+/// Returns the invariant `x^wx * y^wy` of a pool holding `x` and `y`, rounded down. With weights
+/// adding up to 10_000 it is in token units, and grows in proportion to the reserves.
+pub fn compute_invariant(x: u64, y: u64, weight_x: u16, weight_y: u16) -> Option<u64> {
+    if x == 0 || y == 0 {
+        return Some(0);
+    }
+    let log2_v = log2((x as u128) << 64)?
+        .checked_mul(weight_x as i128)?
+        .checked_add(log2((y as u128) << 64)?.checked_mul(weight_y as i128)?)?
+        .checked_div(weight_x as i128 + weight_y as i128)?;
+    let v = exp2(log2_v)?;
+    u64::try_from(v.saturating_sub(margin(v)) >> 64).ok()
+}
+
+/// Returns the output for exactly `amount_in` (net of fees) in, rounded down:
+/// `reserve_out * (1 - (reserve_in / (reserve_in + amount_in))^(weight_in / weight_out))`.
+pub fn swap_exact_in(
+    reserve_in: u64,
+    reserve_out: u64,
+    amount_in: u64,
+    weight_in: u16,
+    weight_out: u16,
+) -> Option<u64> {
+    if amount_in == 0 || reserve_in == 0 || reserve_out == 0 {
+        return None;
+    }
+
+    // Round the base and the power up, which leaves less for the output.
+    let base =
+        ((reserve_in as u128) << 64).div_ceil((reserve_in as u128).checked_add(amount_in as u128)?);
+    let power = pow_up(base, weight_in as u64, weight_out as u64)?.min(ONE);
+
+    let amount_out = ((reserve_out as u128) * (ONE - power)) >> 64;
+    u64::try_from(amount_out).ok()
+}
+
+/// Returns the input (net of fees) needed to take exactly `amount_out` out of the pool, rounded
+/// up: `reserve_in * ((reserve_out / (reserve_out - amount_out))^(weight_out / weight_in) - 1)`.
+pub fn swap_exact_out(
+    reserve_in: u64,
+    reserve_out: u64,
+    amount_out: u64,
+    weight_in: u16,
+    weight_out: u16,
+) -> Option<u64> {
+    if amount_out == 0 || reserve_in == 0 || amount_out >= reserve_out {
+        return None;
+    }
+
+    // Round the base and the power up, which asks for more input.
+    let base = ((reserve_out as u128) << 64).div_ceil((reserve_out - amount_out) as u128);
+    let power = pow_up(base, weight_out as u64, weight_in as u64)?;
+
+    let amount_in = (reserve_in as u128)
+        .checked_mul(power.checked_sub(ONE)?)?
+        .div_ceil(ONE);
+    u64::try_from(amount_in).ok()
+}
@@ -1,24 +1,317 @@
 use bytemuck::{Pod, Zeroable};
 use pinocchio::{account_info::AccountInfo, program_error::ProgramError, pubkey::Pubkey};
 
+use super::{spot_price, Observation, PriceSample};
+use crate::{
+    constants::{
+        CONFIG_DISCRIMINATOR, CONFIG_SEED, CONFIG_VERSION, OBSERVATION_COUNT, OBSERVATION_INTERVAL,
+        POOL_SEED, PRICE_SAMPLE_COUNT, PRICE_SAMPLE_INTERVAL, VOLATILITY_WINDOW,
+    },
+    error::CustomError,
+    math::Curve,
+};
+
+/// Pool state, behind a header identifying the account and its layout version. Fields are only
+/// ever appended, and every change to the layout bumps `CONFIG_VERSION`, so `MigrateConfig` can
+/// bring older configs up to date by growing them and zero-filling the new fields.
 #[repr(C)]
 #[derive(Clone, Copy, Pod, Zeroable)]
 pub struct Config {
+    pub discriminator: [u8; 8],
+    pub version: u8,
     pub seed: [u8; 8],
     pub authority: Pubkey,
     pub mint_x: Pubkey,
     pub mint_y: Pubkey,
     pub fee: [u8; 2],
     pub config_bump: u8,
+    // Share of every swap fee (in basis points of the fee) owed to the protocol.
+    pub protocol_fee: [u8; 2],
+    // Protocol fees accrued in the vaults and not yet claimed by the authority.
+    pub protocol_fees_x: [u8; 8],
+    pub protocol_fees_y: [u8; 8],
+    pub locked: u8,
+    // Sums of the Q64.64 spot prices weighted by the seconds they were in effect.
+    pub price_x_cumulative: [u8; 16],
+    pub price_y_cumulative: [u8; 16],
+    pub last_update_timestamp: [u8; 8],
+    // Ring buffer of accumulator snapshots, at least `OBSERVATION_INTERVAL` seconds apart.
+    pub observation_index: u8,
+    pub observations: [Observation; OBSERVATION_COUNT],
+    // Pricing invariant, see `Curve`, and its amplification coefficient for StableSwap pools.
+    pub curve_type: u8,
+    pub amp: [u8; 8],
+    // Set while a flash loan is outstanding, with the vault balances its repayment must restore.
+    pub flash_loan: u8,
+    pub flash_loan_x: [u8; 8],
+    pub flash_loan_y: [u8; 8],
+    // Set for pools opened in factory mode, whose address derives from the mints and fee tier.
+    pub factory: u8,
+    pub fee_tier: [u8; 2],
+    // Authority proposed by the current one, which takes over once it accepts.
+    pub pending_authority: Pubkey,
+    // Share of every swap fee (in basis points of the fee) paid to the referrer of the swap, if any.
+    pub referral_fee: [u8; 2],
+    // Set for pools whose swap fee rises above `fee` with recent volatility, up to
+    // `max_dynamic_fee`. `volatility_factor` is the share of the volatility (in basis points)
+    // added to the fee.
+    pub dynamic_fee: u8,
+    pub max_dynamic_fee: [u8; 2],
+    pub volatility_factor: [u8; 2],
+    // Largest recent price move in basis points, as of the last update, and the ring buffer of
+    // spot prices it is measured against, at least `PRICE_SAMPLE_INTERVAL` seconds apart.
+    pub volatility: [u8; 4],
+    pub price_sample_index: u8,
+    pub price_samples: [PriceSample; PRICE_SAMPLE_COUNT],
+    // Weights of the tokens in basis points for weighted pools, adding up to 10_000.
+    pub weight_x: [u8; 2],
+    pub weight_y: [u8; 2],
+    // Bumps of the vault and LP mint PDAs, which the accounts passed to the pool are checked
+    // against.
+    pub vault_x_bump: u8,
+    pub vault_y_bump: u8,
+    pub lp_bump: u8,
+    pub vault_lp_bump: u8,
 }
 
 impl Config {
     pub const LEN: usize = core::mem::size_of::<Config>();
+    pub const HEADER_LEN: usize = core::mem::offset_of!(Config, seed);
 
     pub fn load(config_account: &AccountInfo) -> Result<&mut Self, ProgramError> {
         let data = unsafe { config_account.borrow_mut_data_unchecked() };
+        match Self::version(data) {
+            Some(CONFIG_VERSION) => {}
+            Some(_) => return Err(CustomError::UnsupportedConfigVersion.into()),
+            None => return Err(ProgramError::InvalidAccountData),
+        }
         let config_state = bytemuck::try_from_bytes_mut::<Config>(data)
             .map_err(|_| ProgramError::InvalidAccountData)?;
         Ok(config_state)
     }
+
+    /// Returns the layout version of the config in `data`, or `None` when `data` does not start
+    /// with the config header, as is the case for configs written before it was introduced.
+    pub fn version(data: &[u8]) -> Option<u8> {
+        match data.get(..CONFIG_DISCRIMINATOR.len())? == CONFIG_DISCRIMINATOR {
+            true => data.get(CONFIG_DISCRIMINATOR.len()).copied(),
+            false => None,
+        }
+    }
+
+    /// Returns the seeds of the config PDA, bump included. Factory pools derive from their sorted
+    /// mints and fee tier, other pools from `seed`. Empty seeds do not change the derived address,
+    /// so both fit in the same array.
+    pub fn signer_seeds(&self) -> [&[u8]; 5] {
+        let bump = core::slice::from_ref(&self.config_bump);
+        match self.factory {
+            0 => [CONFIG_SEED.as_bytes(), &self.seed, &[], &[], bump],
+            _ => [
+                POOL_SEED.as_bytes(),
+                &self.mint_x,
+                &self.mint_y,
+                &self.fee_tier,
+                bump,
+            ],
+        }
+    }
+
+    pub fn curve(&self) -> Result<Curve, ProgramError> {
+        Curve::new(
+            self.curve_type,
+            u64::from_le_bytes(self.amp),
+            u16::from_le_bytes(self.weight_x),
+            u16::from_le_bytes(self.weight_y),
+        )
+        .ok_or(CustomError::InvalidCurve.into())
+    }
+
+    /// Returns the Q64.64 spot prices of x in units of y, and of y in units of x. Weighted pools
+    /// price each token at its reserve over its weight.
+    pub fn spot_prices(&self, reserve_x: u64, reserve_y: u64) -> (u128, u128) {
+        let price_x = spot_price(reserve_x, reserve_y);
+        let price_y = spot_price(reserve_y, reserve_x);
+        match self.curve() {
+            Ok(Curve::Weighted {
+                weight_in,
+                weight_out,
+            }) => (
+                price_x.saturating_mul(weight_in as u128) / weight_out as u128,
+                price_y.saturating_mul(weight_out as u128) / weight_in as u128,
+            ),
+            _ => (price_x, price_y),
+        }
+    }
+
+    /// Returns the vault balances that belong to liquidity providers, i.e.
+    /// excluding the protocol fees that are still waiting to be claimed.
+    pub fn reserves(
+        &self,
+        vault_x_amount: u64,
+        vault_y_amount: u64,
+    ) -> Result<(u64, u64), ProgramError> {
+        let x = vault_x_amount
+            .checked_sub(u64::from_le_bytes(self.protocol_fees_x))
+            .ok_or(ProgramError::InvalidAccountData)?;
+        let y = vault_y_amount
+            .checked_sub(u64::from_le_bytes(self.protocol_fees_y))
+            .ok_or(ProgramError::InvalidAccountData)?;
+        Ok((x, y))
+    }
+
+    /// Returns the fee charged on swaps, in basis points. In dynamic-fee mode, the base fee rises
+    /// with the volatility as of the last update, up to the cap set by the authority.
+    pub fn swap_fee(&self) -> u16 {
+        let fee = u16::from_le_bytes(self.fee);
+        if self.dynamic_fee == 0 {
+            return fee;
+        }
+
+        let increase = u32::from_le_bytes(self.volatility) as u64
+            * u16::from_le_bytes(self.volatility_factor) as u64
+            / 10_000;
+        let max_fee = u16::from_le_bytes(self.max_dynamic_fee).max(fee);
+        (fee as u64 + increase).min(max_fee as u64) as u16
+    }
+
+    /// Returns the protocol's share of a swap fee charged on `amount` of the input token.
+    pub fn protocol_fee_amount(&self, amount: u64) -> u64 {
+        let fee = amount as u128 * self.swap_fee() as u128 / 10_000;
+        (fee * u16::from_le_bytes(self.protocol_fee) as u128 / 10_000) as u64
+    }
+
+    /// Returns the referrer's share of a swap fee charged on `amount` of the input token.
+    pub fn referral_fee_amount(&self, amount: u64) -> u64 {
+        let fee = amount as u128 * self.swap_fee() as u128 / 10_000;
+        (fee * u16::from_le_bytes(self.referral_fee) as u128 / 10_000) as u64
+    }
+
+    /// Books the protocol's share of a swap fee charged on `amount` of the input token, and
+    /// returns it.
+    pub fn accrue_protocol_fee(&mut self, is_x: bool, amount: u64) -> Result<u64, ProgramError> {
+        let protocol_fee = self.protocol_fee_amount(amount);
+
+        let accrued = match is_x {
+            true => &mut self.protocol_fees_x,
+            false => &mut self.protocol_fees_y,
+        };
+        *accrued = u64::from_le_bytes(*accrued)
+            .checked_add(protocol_fee)
+            .ok_or(ProgramError::ArithmeticOverflow)?
+            .to_le_bytes();
+        Ok(protocol_fee)
+    }
+
+    /// Returns the price accumulators as they would be at `now`, given the current reserves.
+    pub fn cumulative_prices(&self, reserve_x: u64, reserve_y: u64, now: i64) -> (u128, u128) {
+        let mut price_x_cumulative = u128::from_le_bytes(self.price_x_cumulative);
+        let mut price_y_cumulative = u128::from_le_bytes(self.price_y_cumulative);
+
+        let last_update_timestamp = i64::from_le_bytes(self.last_update_timestamp);
+        let elapsed = now.saturating_sub(last_update_timestamp);
+        if last_update_timestamp != 0 && elapsed > 0 && reserve_x != 0 && reserve_y != 0 {
+            // The accumulators are expected to wrap around, only their differences are meaningful.
+            let (price_x, price_y) = self.spot_prices(reserve_x, reserve_y);
+            price_x_cumulative =
+                price_x_cumulative.wrapping_add(price_x.wrapping_mul(elapsed as u128));
+            price_y_cumulative =
+                price_y_cumulative.wrapping_add(price_y.wrapping_mul(elapsed as u128));
+        }
+
+        (price_x_cumulative, price_y_cumulative)
+    }
+
+    /// Accrues the price accumulators up to `now`, and the volatility in dynamic-fee mode. Must be
+    /// called with the reserves from before the pool balances change, so that each price is
+    /// weighted by how long it was in effect.
+    pub fn update_oracle(&mut self, reserve_x: u64, reserve_y: u64, now: i64) {
+        self.update_volatility(reserve_x, reserve_y, now);
+
+        if now <= i64::from_le_bytes(self.last_update_timestamp) {
+            return;
+        }
+
+        let (price_x_cumulative, price_y_cumulative) =
+            self.cumulative_prices(reserve_x, reserve_y, now);
+        self.price_x_cumulative = price_x_cumulative.to_le_bytes();
+        self.price_y_cumulative = price_y_cumulative.to_le_bytes();
+        self.last_update_timestamp = now.to_le_bytes();
+
+        // Record a new observation once the latest one is old enough.
+        let latest = &self.observations[self.observation_index as usize % OBSERVATION_COUNT];
+        let latest_timestamp = i64::from_le_bytes(latest.timestamp);
+        if latest_timestamp == 0 || now - latest_timestamp >= OBSERVATION_INTERVAL {
+            let index = match latest_timestamp {
+                0 => self.observation_index as usize % OBSERVATION_COUNT,
+                _ => (self.observation_index as usize + 1) % OBSERVATION_COUNT,
+            };
+            self.observations[index] = Observation {
+                timestamp: now.to_le_bytes(),
+                price_x_cumulative: self.price_x_cumulative,
+                price_y_cumulative: self.price_y_cumulative,
+            };
+            self.observation_index = index as u8;
+        }
+    }
+
+    /// Measures the volatility as the largest move of the spot price from the recent price
+    /// samples, each fading out linearly over `VOLATILITY_WINDOW` seconds, so that the dynamic
+    /// fee decays back to the base fee once the price settles. Then samples the spot price.
+    pub fn update_volatility(&mut self, reserve_x: u64, reserve_y: u64, now: i64) {
+        if self.dynamic_fee == 0 || reserve_x == 0 || reserve_y == 0 {
+            return;
+        }
+        let (price, _) = self.spot_prices(reserve_x, reserve_y);
+
+        let mut volatility: u128 = 0;
+        for sample in &self.price_samples {
+            let age = now - i64::from_le_bytes(sample.timestamp);
+            let sample_price = u128::from_le_bytes(sample.price);
+            if sample_price == 0 || !(0..VOLATILITY_WINDOW).contains(&age) {
+                continue;
+            }
+            let deviation = price.abs_diff(sample_price).saturating_mul(10_000) / sample_price;
+            let faded = deviation.saturating_mul((VOLATILITY_WINDOW - age) as u128)
+                / VOLATILITY_WINDOW as u128;
+            volatility = volatility.max(faded);
+        }
+        self.volatility = (volatility.min(u32::MAX as u128) as u32).to_le_bytes();
+
+        // Record a new sample once the latest one is old enough.
+        let latest = &self.price_samples[self.price_sample_index as usize % PRICE_SAMPLE_COUNT];
+        let latest_timestamp = i64::from_le_bytes(latest.timestamp);
+        if latest_timestamp == 0 || now - latest_timestamp >= PRICE_SAMPLE_INTERVAL {
+            let index = match latest_timestamp {
+                0 => self.price_sample_index as usize % PRICE_SAMPLE_COUNT,
+                _ => (self.price_sample_index as usize + 1) % PRICE_SAMPLE_COUNT,
+            };
+            self.price_samples[index] = PriceSample {
+                timestamp: now.to_le_bytes(),
+                price: price.to_le_bytes(),
+            };
+            self.price_sample_index = index as u8;
+        }
+    }
+
+    /// Returns the most recent observation taken at or before `timestamp`.
+    pub fn observation_before(&self, timestamp: i64) -> Option<&Observation> {
+        self.observations
+            .iter()
+            .filter(|observation| {
+                let observed_at = i64::from_le_bytes(observation.timestamp);
+                observed_at != 0 && observed_at <= timestamp
+            })
+            .max_by_key(|observation| i64::from_le_bytes(observation.timestamp))
+    }
+}
+
+impl Default for Config {
+    /// Returns an empty config carrying the current header.
+    fn default() -> Self {
+        Self {
+            discriminator: CONFIG_DISCRIMINATOR,
+            version: CONFIG_VERSION,
+            ..Zeroable::zeroed()
+        }
+    }
 }
@@ -1,2 +1,8 @@
 pub mod config;
+pub mod oracle;
+pub mod registry;
+pub mod stats;
 pub use config::*;
+pub use oracle::*;
+pub use registry::*;
+pub use stats::*;
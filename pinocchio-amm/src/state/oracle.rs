@@ -0,0 +1,24 @@
+use bytemuck::{Pod, Zeroable};
+
+/// Snapshot of the price accumulators, used as the start of a TWAP window.
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct Observation {
+    pub timestamp: [u8; 8],
+    pub price_x_cumulative: [u8; 16],
+    pub price_y_cumulative: [u8; 16],
+}
+
+/// Spot price of the pool at `timestamp`, used to measure volatility for the dynamic fee.
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct PriceSample {
+    pub timestamp: [u8; 8],
+    pub price: [u8; 16],
+}
+
+/// Price of one unit of the `reserve_in` token in units of the `reserve_out` token, as a Q64.64
+/// fixed-point number.
+pub fn spot_price(reserve_in: u64, reserve_out: u64) -> u128 {
+    ((reserve_out as u128) << 64) / reserve_in as u128
+}
@@ -0,0 +1,34 @@
+use bytemuck::{Pod, Zeroable};
+use pinocchio::{account_info::AccountInfo, program_error::ProgramError, pubkey::Pubkey};
+
+/// Header of the registry account, followed by the config address of every pool, in the order
+/// they were opened.
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct Registry {
+    pub pool_count: [u8; 8],
+}
+
+impl Registry {
+    pub const LEN: usize = core::mem::size_of::<Registry>();
+
+    /// Returns the size of a registry holding `pool_count` pools.
+    pub fn space(pool_count: u64) -> usize {
+        Self::LEN + pool_count as usize * core::mem::size_of::<Pubkey>()
+    }
+
+    pub fn load(
+        registry_account: &AccountInfo,
+    ) -> Result<(&mut Self, &mut [Pubkey]), ProgramError> {
+        let data = unsafe { registry_account.borrow_mut_data_unchecked() };
+        if data.len() < Self::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let (header, pools) = data.split_at_mut(Self::LEN);
+        let registry_state = bytemuck::try_from_bytes_mut::<Registry>(header)
+            .map_err(|_| ProgramError::InvalidAccountData)?;
+        let pools = bytemuck::try_cast_slice_mut::<u8, Pubkey>(pools)
+            .map_err(|_| ProgramError::InvalidAccountData)?;
+        Ok((registry_state, pools))
+    }
+}
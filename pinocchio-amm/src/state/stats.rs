@@ -0,0 +1,79 @@
+use bytemuck::{Pod, Zeroable};
+use pinocchio::{account_info::AccountInfo, program_error::ProgramError, pubkey::Pubkey};
+
+/// Cumulative counters of a pool, at the PDA of `STATS_SEED` and the config address. Other
+/// programs can read them straight from the account.
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct Stats {
+    pub pool: Pubkey,
+    pub bump: u8,
+    // Amounts swapped into and out of the pool, per token.
+    pub volume_x: [u8; 16],
+    pub volume_y: [u8; 16],
+    // Swap and flash loan fees charged in each token, the protocol and referral shares included.
+    pub fees_x: [u8; 16],
+    pub fees_y: [u8; 16],
+    pub swap_count: [u8; 8],
+    pub deposit_count: [u8; 8],
+    pub withdraw_count: [u8; 8],
+    pub last_trade_slot: [u8; 8],
+}
+
+impl Stats {
+    pub const LEN: usize = core::mem::size_of::<Stats>();
+
+    pub fn load(stats_account: &AccountInfo) -> Result<&mut Self, ProgramError> {
+        if !stats_account.is_owned_by(&crate::ID) {
+            return Err(ProgramError::InvalidAccountOwner);
+        }
+        let data = unsafe { stats_account.borrow_mut_data_unchecked() };
+        bytemuck::try_from_bytes_mut::<Stats>(data).map_err(|_| ProgramError::InvalidAccountData)
+    }
+
+    /// Records a swap of `amount_in` for `amount_out`, charged `fee` in the input token.
+    pub fn record_swap(
+        &mut self,
+        is_x: bool,
+        amount_in: u64,
+        amount_out: u64,
+        fee: u64,
+        slot: u64,
+    ) {
+        let (volume_in, volume_out, fees) = match is_x {
+            true => (&mut self.volume_x, &mut self.volume_y, &mut self.fees_x),
+            false => (&mut self.volume_y, &mut self.volume_x, &mut self.fees_y),
+        };
+        add(volume_in, amount_in);
+        add(volume_out, amount_out);
+        add(fees, fee);
+        self.swap_count = u64::from_le_bytes(self.swap_count)
+            .saturating_add(1)
+            .to_le_bytes();
+        self.last_trade_slot = slot.to_le_bytes();
+    }
+
+    /// Records the fees charged on a flash loan, in each token.
+    pub fn record_flash_loan(&mut self, fee_x: u64, fee_y: u64) {
+        add(&mut self.fees_x, fee_x);
+        add(&mut self.fees_y, fee_y);
+    }
+
+    pub fn record_deposit(&mut self) {
+        self.deposit_count = u64::from_le_bytes(self.deposit_count)
+            .saturating_add(1)
+            .to_le_bytes();
+    }
+
+    pub fn record_withdraw(&mut self) {
+        self.withdraw_count = u64::from_le_bytes(self.withdraw_count)
+            .saturating_add(1)
+            .to_le_bytes();
+    }
+}
+
+fn add(counter: &mut [u8; 16], amount: u64) {
+    *counter = u128::from_le_bytes(*counter)
+        .saturating_add(amount as u128)
+        .to_le_bytes();
+}
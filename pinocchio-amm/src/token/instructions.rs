@@ -0,0 +1,249 @@
+use pinocchio::{
+    account_info::AccountInfo,
+    cpi::{get_return_data, invoke_signed},
+    instruction::{AccountMeta, Instruction, Signer},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    ProgramResult,
+};
+
+use super::TokenAccount;
+
+/// Transfers `amount` from `from` to `to`.
+pub struct TransferChecked<'a> {
+    pub from: &'a AccountInfo,
+    pub mint: &'a AccountInfo,
+    pub to: &'a AccountInfo,
+    pub authority: &'a AccountInfo,
+    pub amount: u64,
+    pub decimals: u8,
+    pub token_program: &'a Pubkey,
+}
+
+impl TransferChecked<'_> {
+    pub fn invoke(&self) -> ProgramResult {
+        self.invoke_signed(&[])
+    }
+
+    pub fn invoke_signed(&self, signers: &[Signer]) -> ProgramResult {
+        let account_metas = [
+            AccountMeta::writable(self.from.key()),
+            AccountMeta::readonly(self.mint.key()),
+            AccountMeta::writable(self.to.key()),
+            AccountMeta::readonly_signer(self.authority.key()),
+        ];
+
+        let mut instruction_data = [0; 10];
+        instruction_data[0] = 12;
+        instruction_data[1..9].copy_from_slice(&self.amount.to_le_bytes());
+        instruction_data[9] = self.decimals;
+
+        let instruction = Instruction {
+            program_id: self.token_program,
+            accounts: &account_metas,
+            data: &instruction_data,
+        };
+        invoke_signed(
+            &instruction,
+            &[self.from, self.mint, self.to, self.authority],
+            signers,
+        )
+    }
+
+    /// Runs the transfer and returns the amount credited to `to`, which is less than `amount`
+    /// when the mint charges a transfer fee.
+    pub fn invoke_signed_received(&self, signers: &[Signer]) -> Result<u64, ProgramError> {
+        let before = TokenAccount::from_account_info(self.to)?.amount();
+        self.invoke_signed(signers)?;
+        TokenAccount::from_account_info(self.to)?
+            .amount()
+            .checked_sub(before)
+            .ok_or(ProgramError::InvalidAccountData)
+    }
+}
+
+/// Mints `amount` of `mint` to `account`.
+pub struct MintTo<'a> {
+    pub mint: &'a AccountInfo,
+    pub account: &'a AccountInfo,
+    pub mint_authority: &'a AccountInfo,
+    pub amount: u64,
+    pub token_program: &'a Pubkey,
+}
+
+impl MintTo<'_> {
+    pub fn invoke_signed(&self, signers: &[Signer]) -> ProgramResult {
+        let account_metas = [
+            AccountMeta::writable(self.mint.key()),
+            AccountMeta::writable(self.account.key()),
+            AccountMeta::readonly_signer(self.mint_authority.key()),
+        ];
+
+        let mut instruction_data = [0; 9];
+        instruction_data[0] = 7;
+        instruction_data[1..9].copy_from_slice(&self.amount.to_le_bytes());
+
+        let instruction = Instruction {
+            program_id: self.token_program,
+            accounts: &account_metas,
+            data: &instruction_data,
+        };
+        invoke_signed(
+            &instruction,
+            &[self.mint, self.account, self.mint_authority],
+            signers,
+        )
+    }
+}
+
+/// Burns `amount` of `mint` from `account`.
+pub struct Burn<'a> {
+    pub account: &'a AccountInfo,
+    pub mint: &'a AccountInfo,
+    pub authority: &'a AccountInfo,
+    pub amount: u64,
+    pub token_program: &'a Pubkey,
+}
+
+impl Burn<'_> {
+    pub fn invoke(&self) -> ProgramResult {
+        self.invoke_signed(&[])
+    }
+
+    pub fn invoke_signed(&self, signers: &[Signer]) -> ProgramResult {
+        let account_metas = [
+            AccountMeta::writable(self.account.key()),
+            AccountMeta::writable(self.mint.key()),
+            AccountMeta::readonly_signer(self.authority.key()),
+        ];
+
+        let mut instruction_data = [0; 9];
+        instruction_data[0] = 8;
+        instruction_data[1..9].copy_from_slice(&self.amount.to_le_bytes());
+
+        let instruction = Instruction {
+            program_id: self.token_program,
+            accounts: &account_metas,
+            data: &instruction_data,
+        };
+        invoke_signed(
+            &instruction,
+            &[self.account, self.mint, self.authority],
+            signers,
+        )
+    }
+}
+
+/// Closes the empty `account`, sending its lamports to `destination`.
+pub struct CloseAccount<'a> {
+    pub account: &'a AccountInfo,
+    pub destination: &'a AccountInfo,
+    pub authority: &'a AccountInfo,
+    pub token_program: &'a Pubkey,
+}
+
+impl CloseAccount<'_> {
+    pub fn invoke_signed(&self, signers: &[Signer]) -> ProgramResult {
+        let account_metas = [
+            AccountMeta::writable(self.account.key()),
+            AccountMeta::writable(self.destination.key()),
+            AccountMeta::readonly_signer(self.authority.key()),
+        ];
+
+        let instruction = Instruction {
+            program_id: self.token_program,
+            accounts: &account_metas,
+            data: &[9],
+        };
+        invoke_signed(
+            &instruction,
+            &[self.account, self.destination, self.authority],
+            signers,
+        )
+    }
+}
+
+/// Initializes `mint`, without a freeze authority.
+pub struct InitializeMint2<'a> {
+    pub mint: &'a AccountInfo,
+    pub decimals: u8,
+    pub mint_authority: &'a Pubkey,
+    pub token_program: &'a Pubkey,
+}
+
+impl InitializeMint2<'_> {
+    pub fn invoke(&self) -> ProgramResult {
+        let account_metas = [AccountMeta::writable(self.mint.key())];
+
+        // The last byte is the empty freeze authority option.
+        let mut instruction_data = [0; 35];
+        instruction_data[0] = 20;
+        instruction_data[1] = self.decimals;
+        instruction_data[2..34].copy_from_slice(self.mint_authority);
+
+        let instruction = Instruction {
+            program_id: self.token_program,
+            accounts: &account_metas,
+            data: &instruction_data,
+        };
+        invoke_signed(&instruction, &[self.mint], &[])
+    }
+}
+
+/// Initializes `account` to hold `mint` on behalf of `owner`.
+pub struct InitializeAccount3<'a> {
+    pub account: &'a AccountInfo,
+    pub mint: &'a AccountInfo,
+    pub owner: &'a Pubkey,
+    pub token_program: &'a Pubkey,
+}
+
+impl InitializeAccount3<'_> {
+    pub fn invoke(&self) -> ProgramResult {
+        let account_metas = [
+            AccountMeta::writable(self.account.key()),
+            AccountMeta::readonly(self.mint.key()),
+        ];
+
+        let mut instruction_data = [0; 33];
+        instruction_data[0] = 18;
+        instruction_data[1..33].copy_from_slice(self.owner);
+
+        let instruction = Instruction {
+            program_id: self.token_program,
+            accounts: &account_metas,
+            data: &instruction_data,
+        };
+        invoke_signed(&instruction, &[self.account, self.mint], &[])
+    }
+}
+
+/// Returns the size of a token account for `mint`, which depends on the mint extensions under
+/// Token-2022.
+pub struct GetAccountDataSize<'a> {
+    pub mint: &'a AccountInfo,
+    pub token_program: &'a Pubkey,
+}
+
+impl GetAccountDataSize<'_> {
+    pub fn invoke(&self) -> Result<usize, ProgramError> {
+        let account_metas = [AccountMeta::readonly(self.mint.key())];
+
+        let instruction = Instruction {
+            program_id: self.token_program,
+            accounts: &account_metas,
+            data: &[21],
+        };
+        invoke_signed(&instruction, &[self.mint], &[])?;
+
+        let return_data = get_return_data().ok_or(ProgramError::InvalidAccountData)?;
+        if return_data.program_id() != self.token_program {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let size = return_data
+            .as_slice()
+            .try_into()
+            .map_err(|_| ProgramError::InvalidAccountData)?;
+        Ok(u64::from_le_bytes(size) as usize)
+    }
+}
@@ -0,0 +1,20 @@
+//! Token accounts and instructions for both the token program and Token-2022.
+//!
+//! `pinocchio_token` only targets the token program, so pools go through these instead and call
+//! whichever token program owns their mints. Only the base layout of mints and token accounts is
+//! read, extensions are left to the token program, save for the mint extensions pools reject.
+
+use pinocchio::pubkey::Pubkey;
+
+pub mod instructions;
+pub mod state;
+
+pub use state::*;
+
+pub const TOKEN_2022_ID: Pubkey =
+    pinocchio_pubkey::pubkey!("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb");
+
+/// Returns whether `program_id` is the token program or Token-2022.
+pub fn is_token_program(program_id: &Pubkey) -> bool {
+    program_id == &pinocchio_token::ID || program_id == &TOKEN_2022_ID
+}
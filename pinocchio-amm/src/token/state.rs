@@ -0,0 +1,135 @@
+use bytemuck::{Pod, Zeroable};
+use pinocchio::{
+    account_info::{AccountInfo, Ref},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    ProgramResult,
+};
+
+use super::TOKEN_2022_ID;
+
+/// Token-2022 pads mints with extensions to the size of a token account, then writes the account
+/// type before the extensions.
+const ACCOUNT_TYPE_OFFSET: usize = TokenAccount::LEN;
+const ACCOUNT_TYPE_MINT: u8 = 1;
+const ACCOUNT_TYPE_ACCOUNT: u8 = 2;
+
+/// Token-2022 mint extensions that let a third party block or take transfers of the mint.
+pub const EXTENSION_NON_TRANSFERABLE: u16 = 9;
+pub const EXTENSION_PERMANENT_DELEGATE: u16 = 12;
+pub const EXTENSION_TRANSFER_HOOK: u16 = 14;
+
+/// Base layout of a mint, shared by both token programs.
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct Mint {
+    pub mint_authority_flag: [u8; 4],
+    pub mint_authority: Pubkey,
+    pub supply: [u8; 8],
+    pub decimals: u8,
+    pub is_initialized: u8,
+    pub freeze_authority_flag: [u8; 4],
+    pub freeze_authority: Pubkey,
+}
+
+impl Mint {
+    pub const LEN: usize = core::mem::size_of::<Mint>();
+
+    pub fn from_account_info(account_info: &AccountInfo) -> Result<Ref<Mint>, ProgramError> {
+        check_account(account_info, Self::LEN, ACCOUNT_TYPE_MINT)?;
+        Ok(Ref::map(account_info.try_borrow_data()?, |data| {
+            bytemuck::from_bytes(&data[..Self::LEN])
+        }))
+    }
+
+    pub fn mint_authority(&self) -> Option<&Pubkey> {
+        (self.mint_authority_flag[0] == 1).then_some(&self.mint_authority)
+    }
+
+    pub fn supply(&self) -> u64 {
+        u64::from_le_bytes(self.supply)
+    }
+
+    pub fn decimals(&self) -> u8 {
+        self.decimals
+    }
+
+    /// Returns whether the Token-2022 mint at `account_info` has an extension of
+    /// `extension_type`. Extensions follow the account type, each as its type and length, both
+    /// u16, and its data.
+    pub fn has_extension(
+        account_info: &AccountInfo,
+        extension_type: u16,
+    ) -> Result<bool, ProgramError> {
+        check_account(account_info, Self::LEN, ACCOUNT_TYPE_MINT)?;
+        let data = account_info.try_borrow_data()?;
+        let mut offset = ACCOUNT_TYPE_OFFSET + 1;
+        while let Some(header) = data.get(offset..offset + 4) {
+            if u16::from_le_bytes([header[0], header[1]]) == extension_type {
+                return Ok(true);
+            }
+            offset += 4 + u16::from_le_bytes([header[2], header[3]]) as usize;
+        }
+        Ok(false)
+    }
+}
+
+/// Base layout of a token account, shared by both token programs.
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct TokenAccount {
+    pub mint: Pubkey,
+    pub owner: Pubkey,
+    pub amount: [u8; 8],
+    pub delegate_flag: [u8; 4],
+    pub delegate: Pubkey,
+    pub state: u8,
+    pub is_native: [u8; 4],
+    pub native_amount: [u8; 8],
+    pub delegated_amount: [u8; 8],
+    pub close_authority_flag: [u8; 4],
+    pub close_authority: Pubkey,
+}
+
+impl TokenAccount {
+    pub const LEN: usize = core::mem::size_of::<TokenAccount>();
+
+    pub fn from_account_info(
+        account_info: &AccountInfo,
+    ) -> Result<Ref<TokenAccount>, ProgramError> {
+        check_account(account_info, Self::LEN, ACCOUNT_TYPE_ACCOUNT)?;
+        Ok(Ref::map(account_info.try_borrow_data()?, |data| {
+            bytemuck::from_bytes(&data[..Self::LEN])
+        }))
+    }
+
+    pub fn mint(&self) -> &Pubkey {
+        &self.mint
+    }
+
+    pub fn owner(&self) -> &Pubkey {
+        &self.owner
+    }
+
+    pub fn amount(&self) -> u64 {
+        u64::from_le_bytes(self.amount)
+    }
+}
+
+/// Checks that `account_info` belongs to a token program and holds an account of size `len`, or a
+/// Token-2022 account of type `account_type` with extensions.
+fn check_account(account_info: &AccountInfo, len: usize, account_type: u8) -> ProgramResult {
+    if !account_info.is_owned_by(&pinocchio_token::ID) && !account_info.is_owned_by(&TOKEN_2022_ID)
+    {
+        return Err(ProgramError::InvalidAccountOwner);
+    }
+
+    let data = account_info.try_borrow_data()?;
+    let extended = account_info.is_owned_by(&TOKEN_2022_ID)
+        && data.len() > ACCOUNT_TYPE_OFFSET
+        && data[ACCOUNT_TYPE_OFFSET] == account_type;
+    if data.len() != len && !extended {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(())
+}
@@ -0,0 +1,210 @@
+use crate::{
+    constants::{LP_SEED, VAULT_SEED},
+    error::CustomError,
+    state::{Config, Stats},
+    token::{
+        is_token_program, Mint, TokenAccount, EXTENSION_NON_TRANSFERABLE,
+        EXTENSION_PERMANENT_DELEGATE, EXTENSION_TRANSFER_HOOK, TOKEN_2022_ID,
+    },
+};
+use pinocchio::{
+    account_info::AccountInfo,
+    program_error::ProgramError,
+    pubkey::{self, Pubkey},
+    sysvars::{clock::Clock, Sysvar},
+    ProgramResult,
+};
+
+/// Validates that `config` is the config PDA derived from the seeds of `config_state`.
+pub fn validate_config(config: &AccountInfo, config_state: &Config) -> ProgramResult {
+    let config_pda = pubkey::create_program_address(&config_state.signer_seeds(), &crate::ID)?;
+    if config.key() != &config_pda {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(())
+}
+
+/// Validates that the passed mints are the ones the pool was created for.
+pub fn validate_mints(
+    config_state: &Config,
+    mint_x: &AccountInfo,
+    mint_y: &AccountInfo,
+) -> ProgramResult {
+    if config_state.mint_x.ne(mint_x.key()) || config_state.mint_y.ne(mint_y.key()) {
+        return Err(CustomError::MintMismatch.into());
+    }
+    Ok(())
+}
+
+/// Validates that `vault` is the vault PDA of the pool at `config` holding `mint`.
+fn validate_vault_address(
+    config: &AccountInfo,
+    mint: &AccountInfo,
+    vault: &AccountInfo,
+    vault_bump: u8,
+) -> ProgramResult {
+    let vault_pda = pubkey::create_program_address(
+        &[
+            VAULT_SEED.as_bytes(),
+            config.key().as_ref(),
+            mint.key().as_ref(),
+            &[vault_bump],
+        ],
+        &crate::ID,
+    )?;
+    if vault.key() != &vault_pda {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(())
+}
+
+/// Validates that the vaults are the two distinct vault PDAs of the pool at `config`, owned by
+/// it and holding `mint_x` and `mint_y` respectively. Returns the vault balances.
+pub fn validate_vaults(
+    config: &AccountInfo,
+    config_state: &Config,
+    mint_x: &AccountInfo,
+    mint_y: &AccountInfo,
+    vault_x: &AccountInfo,
+    vault_y: &AccountInfo,
+) -> Result<(u64, u64), ProgramError> {
+    if vault_x.key() == vault_y.key() {
+        return Err(CustomError::DuplicateAccount.into());
+    }
+    validate_vault_address(config, mint_x, vault_x, config_state.vault_x_bump)?;
+    validate_vault_address(config, mint_y, vault_y, config_state.vault_y_bump)?;
+
+    let vault_x_account = TokenAccount::from_account_info(vault_x)?;
+    let vault_y_account = TokenAccount::from_account_info(vault_y)?;
+    if vault_x_account.owner() != config.key() {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if vault_y_account.owner() != config.key() {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if vault_x_account.mint() != mint_x.key() || vault_y_account.mint() != mint_y.key() {
+        return Err(CustomError::VaultMintMismatch.into());
+    }
+
+    Ok((vault_x_account.amount(), vault_y_account.amount()))
+}
+
+/// Validates that `mint_lp` is the LP mint PDA of the pool at `config`, with `config` as its mint
+/// authority. Returns the LP supply.
+pub fn validate_mint_lp(
+    config: &AccountInfo,
+    config_state: &Config,
+    mint_lp: &AccountInfo,
+) -> Result<u64, ProgramError> {
+    let mint_lp_pda = pubkey::create_program_address(
+        &[
+            LP_SEED.as_bytes(),
+            config.key().as_ref(),
+            &[config_state.lp_bump],
+        ],
+        &crate::ID,
+    )?;
+    if mint_lp.key() != &mint_lp_pda {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let mint_lp_account = Mint::from_account_info(mint_lp)?;
+    if mint_lp_account.mint_authority() != Some(config.key()) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(mint_lp_account.supply())
+}
+
+/// Validates that `vault_lp` is the LP vault PDA of the pool at `config`, owned by it, which locks
+/// the minimum liquidity.
+pub fn validate_vault_lp(
+    config: &AccountInfo,
+    config_state: &Config,
+    mint_lp: &AccountInfo,
+    vault_lp: &AccountInfo,
+) -> ProgramResult {
+    validate_vault_address(config, mint_lp, vault_lp, config_state.vault_lp_bump)?;
+
+    let vault_lp_account = TokenAccount::from_account_info(vault_lp)?;
+    if vault_lp_account.owner() != config.key() {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if vault_lp_account.mint() != mint_lp.key() {
+        return Err(CustomError::VaultMintMismatch.into());
+    }
+    Ok(())
+}
+
+/// Validates that `stats_state` holds the statistics of the pool at `config`.
+pub fn validate_stats(config: &AccountInfo, stats_state: &Stats) -> ProgramResult {
+    if stats_state.pool.ne(config.key()) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(())
+}
+
+/// Validates that `token_account` belongs to `owner`.
+pub fn validate_token_account(token_account: &AccountInfo, owner: &AccountInfo) -> ProgramResult {
+    if TokenAccount::from_account_info(token_account)?.owner() != owner.key() {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(())
+}
+
+/// Validates that `authority` is the pool authority, and that it has not been renounced.
+pub fn validate_authority(config_state: &Config, authority: &AccountInfo) -> ProgramResult {
+    if config_state.authority == Pubkey::default() {
+        return Err(CustomError::AuthorityRenounced.into());
+    }
+    if config_state.authority.ne(authority.key()) {
+        return Err(ProgramError::IncorrectAuthority);
+    }
+    Ok(())
+}
+
+/// Validates that `token_program` is the token program or Token-2022.
+pub fn validate_token_program(token_program: &AccountInfo) -> ProgramResult {
+    if !is_token_program(token_program.key()) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    Ok(())
+}
+
+/// Validates that `mint` belongs to `token_program`, the token program or Token-2022.
+pub fn validate_mint_token_program(
+    mint: &AccountInfo,
+    token_program: &AccountInfo,
+) -> ProgramResult {
+    validate_token_program(token_program)?;
+    if !mint.is_owned_by(token_program.key()) {
+        return Err(ProgramError::InvalidAccountOwner);
+    }
+    Ok(())
+}
+
+/// Validates that `mint` has none of the Token-2022 extensions that would let a third party move
+/// the reserves out of the vaults, run code on every transfer, or keep the vaults from paying out.
+pub fn validate_mint_extensions(mint: &AccountInfo) -> ProgramResult {
+    if !mint.is_owned_by(&TOKEN_2022_ID) {
+        return Ok(());
+    }
+    for extension_type in [
+        EXTENSION_NON_TRANSFERABLE,
+        EXTENSION_PERMANENT_DELEGATE,
+        EXTENSION_TRANSFER_HOOK,
+    ] {
+        if Mint::has_extension(mint, extension_type)? {
+            return Err(CustomError::UnsupportedMintExtension.into());
+        }
+    }
+    Ok(())
+}
+
+/// Validates that the unix timestamp `deadline` has not passed, 0 being no deadline.
+pub fn validate_deadline(deadline: [u8; 8]) -> ProgramResult {
+    let deadline = i64::from_le_bytes(deadline);
+    if deadline != 0 && Clock::get()?.unix_timestamp > deadline {
+        return Err(CustomError::DeadlineExceeded.into());
+    }
+    Ok(())
+}
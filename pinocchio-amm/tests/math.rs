@@ -0,0 +1,421 @@
+use pinocchio_amm::math::{
+    amount_after_fee, amount_before_fee, constant_product, deposit_amounts, deposit_liquidity,
+    stable_swap, weighted, withdraw_amounts,
+};
+
+// Reference values solved from the invariant `4A(x + y) + D = 4AD + D^3 / (4xy)` with 80 digits
+// of precision, then rounded towards the pool (outputs down, inputs up).
+
+// (x, y, amp, D)
+const D_VECTORS: [(u64, u64, u64, u128); 4] = [
+    (1_000_000_000, 1_000_000_000, 100, 2_000_000_000),
+    (1_500_000_000, 500_000_000, 100, 1_998_345_726),
+    (500_000_000, 1_500_000_000, 10, 1_984_484_956),
+    (2_000_000, 1_000_000, 1, 2_940_557),
+];
+
+// (reserve_in, reserve_out, amp, amount_in, amount_out)
+const SWAP_EXACT_IN_VECTORS: [(u64, u64, u64, u64, u64); 6] = [
+    (1_000_000_000, 1_000_000_000, 100, 1_000_000, 999_995),
+    (1_000_000_000, 1_000_000_000, 100, 100_000_000, 99_949_776),
+    (1_500_000_000, 500_000_000, 100, 10_000_000, 9_910_453),
+    (500_000_000, 1_500_000_000, 10, 10_000_000, 10_813_370),
+    (
+        1_000_000_000_000,
+        1_000_000_000_000,
+        2_000,
+        5_000_000_000,
+        4_999_993_751,
+    ),
+    (2_000_000, 1_000_000, 1, 100_000, 75_784),
+];
+
+// (reserve_in, reserve_out, amp, amount_out, amount_in)
+const SWAP_EXACT_OUT_VECTORS: [(u64, u64, u64, u64, u64); 3] = [
+    (1_000_000_000, 1_000_000_000, 100, 1_000_000, 1_000_005),
+    (1_500_000_000, 500_000_000, 100, 10_000_000, 10_090_375),
+    (2_000_000, 1_000_000, 1, 75_000, 98_936),
+];
+
+#[test]
+fn test_stable_swap_compute_d() {
+    for (x, y, amp, expected) in D_VECTORS {
+        let d = stable_swap::compute_d(x, y, amp).unwrap();
+        assert!(
+            d.abs_diff(expected) <= 1,
+            "D({x}, {y}, {amp}) = {d}, expected {expected}"
+        );
+    }
+}
+
+#[test]
+fn test_stable_swap_exact_in() {
+    for (reserve_in, reserve_out, amp, amount_in, expected) in SWAP_EXACT_IN_VECTORS {
+        let amount_out =
+            stable_swap::swap_exact_in(reserve_in, reserve_out, amount_in, amp).unwrap();
+        // Never more than the exact output, and at most a few units less.
+        assert!(amount_out <= expected && expected - amount_out <= 2);
+    }
+}
+
+#[test]
+fn test_stable_swap_exact_out() {
+    for (reserve_in, reserve_out, amp, amount_out, expected) in SWAP_EXACT_OUT_VECTORS {
+        let amount_in =
+            stable_swap::swap_exact_out(reserve_in, reserve_out, amount_out, amp).unwrap();
+        // Never less than the exact input, and at most a few units more.
+        assert!(amount_in >= expected && amount_in - expected <= 4);
+    }
+}
+
+#[test]
+fn test_stable_swap_beats_constant_product_near_peg() {
+    let (reserve, amount_in) = (1_000_000_000u64, 10_000_000u64);
+    let stable_out = stable_swap::swap_exact_in(reserve, reserve, amount_in, 100).unwrap();
+    let constant_product_out =
+        constant_product::swap_exact_in(reserve, reserve, amount_in).unwrap();
+    assert!(stable_out > constant_product_out);
+}
+
+/// Deterministic xorshift generator, so failures are reproducible.
+fn next(state: &mut u64) -> u64 {
+    *state ^= *state << 13;
+    *state ^= *state >> 7;
+    *state ^= *state << 17;
+    *state
+}
+
+fn assert_swap_keeps_k(x: u64, y: u64, amount_in: u64, amount_out: u64) {
+    let k = x as u128 * y as u128;
+    let k_after = (x as u128 + amount_in as u128) * (y - amount_out) as u128;
+    assert!(
+        k_after >= k,
+        "k decreased: {x} * {y} -> in {amount_in}, out {amount_out}"
+    );
+}
+
+#[test]
+fn test_constant_product_exact_in_never_decreases_k() {
+    for x in 1..=64 {
+        for y in 1..=64 {
+            for amount_in in 1..=64 {
+                let amount_out = constant_product::swap_exact_in(x, y, amount_in).unwrap();
+                assert_swap_keeps_k(x, y, amount_in, amount_out);
+            }
+        }
+    }
+
+    let mut state = 0x2545_f491_4f6c_dd1d;
+    for _ in 0..100_000 {
+        let x = next(&mut state) >> 2;
+        let y = next(&mut state) >> 2;
+        let amount_in = next(&mut state) >> (2 + next(&mut state) % 60);
+        if let Some(amount_out) = constant_product::swap_exact_in(x, y, amount_in) {
+            assert_swap_keeps_k(x, y, amount_in, amount_out);
+        }
+    }
+}
+
+#[test]
+fn test_constant_product_exact_out_never_decreases_k() {
+    for x in 1..=64 {
+        for y in 1..=64 {
+            for amount_out in 1..y {
+                let amount_in = constant_product::swap_exact_out(x, y, amount_out).unwrap();
+                assert_swap_keeps_k(x, y, amount_in, amount_out);
+            }
+        }
+    }
+
+    let mut state = 0x9e37_79b9_7f4a_7c15;
+    for _ in 0..100_000 {
+        let x = next(&mut state) >> 2;
+        let y = next(&mut state) >> 2;
+        let amount_out = y >> (1 + next(&mut state) % 62);
+        if let Some(amount_in) = constant_product::swap_exact_out(x, y, amount_out) {
+            assert_swap_keeps_k(x, y, amount_in, amount_out);
+        }
+    }
+}
+
+#[test]
+fn test_constant_product_with_fees_never_decreases_k() {
+    for fee in [0, 1, 30, 500, 1_000] {
+        for x in 1..=32 {
+            for y in 1..=32 {
+                for amount in 1..=32 {
+                    let net = amount_after_fee(amount, fee).unwrap();
+                    if let Some(amount_out) = constant_product::swap_exact_in(x, y, net) {
+                        assert_swap_keeps_k(x, y, net, amount_out);
+                    }
+                    if amount < y {
+                        let net = constant_product::swap_exact_out(x, y, amount).unwrap();
+                        let amount_in = amount_before_fee(net, fee).unwrap();
+                        // The pool keeps at least `net` once the fee is taken out.
+                        assert!(amount_after_fee(amount_in, fee).unwrap() >= net);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_liquidity_never_decreases_k_per_lp() {
+    // k / supply^2 must never decrease, so deposits and withdrawals cannot dilute other LPs.
+    for x in 1..=24u64 {
+        for y in 1..=24u64 {
+            for supply in 1..=24u64 {
+                let k = x as u128 * y as u128;
+                for lp in 1..=24u64 {
+                    let (dx, dy) = deposit_amounts(x, y, supply, lp).unwrap();
+                    let k_after = (x + dx) as u128 * (y + dy) as u128;
+                    assert!(
+                        k_after * (supply as u128).pow(2) >= k * ((supply + lp) as u128).pow(2)
+                    );
+
+                    if lp <= supply {
+                        let (dx, dy) = withdraw_amounts(x, y, supply, lp).unwrap();
+                        let k_after = (x - dx) as u128 * (y - dy) as u128;
+                        assert!(
+                            k_after * (supply as u128).pow(2) >= k * ((supply - lp) as u128).pow(2)
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_deposit_liquidity() {
+    for x in 1..=24u64 {
+        for y in 1..=24u64 {
+            for supply in 1..=24u64 {
+                let k = x as u128 * y as u128;
+                // Depositing what `deposit_amounts` asks for mints at least the LP asked for.
+                for lp in 1..=24u64 {
+                    let (dx, dy) = deposit_amounts(x, y, supply, lp).unwrap();
+                    assert!(deposit_liquidity(x, y, supply, dx, dy).unwrap() >= lp);
+                }
+                // Any deposit, such as one short of a transfer fee, cannot dilute other LPs.
+                for dx in 0..=24u64 {
+                    for dy in 0..=24u64 {
+                        let lp = deposit_liquidity(x, y, supply, dx, dy).unwrap();
+                        let k_after = (x + dx) as u128 * (y + dy) as u128;
+                        assert!(
+                            k_after * (supply as u128).pow(2) >= k * ((supply + lp) as u128).pow(2)
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Reference values of the weighted product invariant `x^wx * y^wy = V` and of the powers behind
+// it, computed with 400 digits of precision, then rounded towards the pool (outputs down, inputs
+// up). Powers are in Q64.64, rounded to nearest.
+
+// (base, numerator, denominator, base^(numerator / denominator))
+const POW_VECTORS: [(u128, u64, u64, u128); 9] = [
+    // 0.5^(1/4)
+    (9_223_372_036_854_775_808, 1, 4, 15_511_800_964_685_064_948),
+    // 0.5^3
+    (9_223_372_036_854_775_808, 3, 1, 2_305_843_009_213_693_952),
+    // 0.9^4
+    (16_602_069_666_338_596_454, 4, 1, 12_102_908_786_760_836_814),
+    // 0.999^(1/99)
+    (
+        18_428_297_329_635_842_064,
+        1,
+        99,
+        18_446_557_650_675_804_488,
+    ),
+    // 2^(3/2)
+    (36_893_488_147_419_103_232, 3, 2, 52_175_271_301_331_128_849),
+    // 1_000_000^(1/2)
+    (
+        18_446_744_073_709_551_616_000_000,
+        1,
+        2,
+        18_446_744_073_709_551_616_000,
+    ),
+    // 1.0000001^(7/3)
+    (18_446_745_918_383_958_986, 7, 3, 18_446_748_377_950_122_429),
+    // 0.8^(1/4)
+    (
+        14_757_395_258_967_641_292,
+        2_000,
+        8_000,
+        17_445_853_421_139_869_359,
+    ),
+    // 0.3^4
+    (
+        5_534_023_222_112_865_484,
+        8_000,
+        2_000,
+        149_418_626_997_047_368,
+    ),
+];
+
+// (reserve_in, reserve_out, weight_in, weight_out, amount_in, amount_out)
+const WEIGHTED_SWAP_EXACT_IN_VECTORS: [(u64, u64, u16, u16, u64, u64); 7] = [
+    (
+        1_000_000_000,
+        1_000_000_000,
+        5_000,
+        5_000,
+        1_000_000,
+        999_000,
+    ),
+    (
+        8_000_000_000,
+        2_000_000_000,
+        8_000,
+        2_000,
+        10_000_000,
+        9_968_827,
+    ),
+    (
+        2_000_000_000,
+        8_000_000_000,
+        2_000,
+        8_000,
+        10_000_000,
+        9_968_866,
+    ),
+    (5_000_000, 20_000_000, 2_000, 8_000, 1_000_000, 891_144),
+    (1_000_000, 1_000_000, 9_900, 100, 999_000_000, 999_999),
+    (1_000_000_000_000, 500_000_000, 100, 9_900, 123_456_789, 623),
+    (
+        1_000_000_000_000_000_000,
+        1_000_000_000_000_000_000,
+        8_000,
+        2_000,
+        300_000_000_000_000_000,
+        649_872_203_354_224_291,
+    ),
+];
+
+// (reserve_in, reserve_out, weight_in, weight_out, amount_out, amount_in)
+const WEIGHTED_SWAP_EXACT_OUT_VECTORS: [(u64, u64, u16, u16, u64, u64); 5] = [
+    (
+        1_000_000_000,
+        1_000_000_000,
+        5_000,
+        5_000,
+        1_000_000,
+        1_001_002,
+    ),
+    (
+        8_000_000_000,
+        2_000_000_000,
+        8_000,
+        2_000,
+        10_000_000,
+        10_031_368,
+    ),
+    (
+        2_000_000_000,
+        8_000_000_000,
+        2_000,
+        8_000,
+        10_000_000,
+        10_031_329,
+    ),
+    (1_000_000, 1_000_000, 9_900, 100, 999_000, 72_268),
+    (
+        1_000_000_000_000_000_000,
+        1_000_000_000_000_000_000,
+        8_000,
+        2_000,
+        300_000_000_000_000_000,
+        93_265_113_929_093_432,
+    ),
+];
+
+// (x, y, weight_x, weight_y, V)
+const WEIGHTED_INVARIANT_VECTORS: [(u64, u64, u16, u16, u64); 5] = [
+    (1_000_000, 4_000_000, 5_000, 5_000, 2_000_000),
+    (8_000_000, 2_000_000, 8_000, 2_000, 6_062_866),
+    (123_456_789, 987_654_321, 2_500, 7_500, 587_262_771),
+    (
+        1_000_000_000_000_000_000,
+        1_000_000_000_000,
+        100,
+        9_900,
+        1_148_153_621_496,
+    ),
+    (1, 18_446_744_073_709_551_615, 5_000, 5_000, 4_294_967_295),
+];
+
+#[test]
+fn test_weighted_pow() {
+    for (base, numerator, denominator, expected) in POW_VECTORS {
+        let power = weighted::pow(base, numerator, denominator).unwrap();
+        // Within a relative 2^-58 of the exact power.
+        assert!(
+            power.abs_diff(expected) <= (expected >> 58) + 1,
+            "{base}^({numerator}/{denominator}) = {power}, expected {expected}"
+        );
+    }
+}
+
+#[test]
+fn test_weighted_exact_in() {
+    for (reserve_in, reserve_out, weight_in, weight_out, amount_in, expected) in
+        WEIGHTED_SWAP_EXACT_IN_VECTORS
+    {
+        let amount_out =
+            weighted::swap_exact_in(reserve_in, reserve_out, amount_in, weight_in, weight_out)
+                .unwrap();
+        // Never more than the exact output, and less by at most a relative 1e-12 of the reserve.
+        assert!(
+            amount_out <= expected && expected - amount_out <= reserve_out / 1_000_000_000_000 + 1
+        );
+    }
+}
+
+#[test]
+fn test_weighted_exact_out() {
+    for (reserve_in, reserve_out, weight_in, weight_out, amount_out, expected) in
+        WEIGHTED_SWAP_EXACT_OUT_VECTORS
+    {
+        let amount_in =
+            weighted::swap_exact_out(reserve_in, reserve_out, amount_out, weight_in, weight_out)
+                .unwrap();
+        // Never less than the exact input, and more by at most a relative 1e-12 of the reserve.
+        assert!(
+            amount_in >= expected && amount_in - expected <= reserve_in / 1_000_000_000_000 + 1
+        );
+    }
+}
+
+#[test]
+fn test_weighted_invariant() {
+    for (x, y, weight_x, weight_y, expected) in WEIGHTED_INVARIANT_VECTORS {
+        let v = weighted::compute_invariant(x, y, weight_x, weight_y).unwrap();
+        assert!(v <= expected && expected - v <= expected / 1_000_000_000_000 + 1);
+    }
+}
+
+#[test]
+fn test_weighted_even_weights_match_constant_product() {
+    let mut state = 0x5851_f42d_4c95_7f2d;
+    for _ in 0..10_000 {
+        let x = next(&mut state) >> 2;
+        let y = next(&mut state) >> 2;
+        let amount = x >> (1 + next(&mut state) % 62);
+        if let Some(expected) = constant_product::swap_exact_in(x, y, amount) {
+            let amount_out = weighted::swap_exact_in(x, y, amount, 5_000, 5_000).unwrap();
+            assert!(amount_out <= expected && expected - amount_out <= y / 1_000_000_000_000 + 1);
+        }
+        let amount = y >> (1 + next(&mut state) % 62);
+        if let Some(expected) = constant_product::swap_exact_out(x, y, amount) {
+            if let Some(amount_in) = weighted::swap_exact_out(x, y, amount, 5_000, 5_000) {
+                assert!(amount_in >= expected && amount_in - expected <= x / 1_000_000_000_000 + 1);
+            }
+        }
+    }
+}
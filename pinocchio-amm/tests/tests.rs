@@ -1,17 +1,32 @@
 use mollusk_svm::result::Check;
 use mollusk_svm::{program, Mollusk};
-use pinocchio_amm::constants::CONFIG_SEED;
+use pinocchio_amm::constants::{
+    CONFIG_SEED, CONFIG_VERSION, LP_SEED, MINIMUM_LIQUIDITY, POOL_SEED, REGISTRY_SEED, STATS_SEED,
+    VAULT_SEED, VOLATILITY_WINDOW,
+};
+use pinocchio_amm::error::CustomError;
 use pinocchio_amm::instruction::{
-    DepositInstructionData, InitializeInstructionData, SwapInstructionData, WithdrawInstructionData,
+    ClosePoolInstructionData, DepositInstructionData, DepositSingleInstructionData,
+    FlashLoanInstructionData, GetTwapInstructionData, InitializeInstructionData,
+    InitializeStatsInstructionData, ProposeAuthorityInstructionData, QuoteInstructionData,
+    QuoteResult, RouteSwapInstructionData, SwapExactOutInstructionData, SwapInstructionData,
+    TwapResult, UpdateConfigInstructionData, UpdateDynamicFeeInstructionData,
+    WithdrawInstructionData,
+};
+use pinocchio_amm::math::{
+    amount_after_fee, amount_before_fee, constant_product, stable_swap, weighted, withdraw_amounts,
 };
-use pinocchio_amm::state::Config;
+use pinocchio_amm::state::{Config, Observation, PriceSample, Registry, Stats};
+use solana_instruction::{BorrowedAccountMeta, BorrowedInstruction};
 use solana_sdk::account::Account;
 use solana_sdk::instruction::{AccountMeta, Instruction};
 use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::program_error::ProgramError;
 use solana_sdk::pubkey;
 use solana_sdk::pubkey::Pubkey;
 extern crate alloc;
 use alloc::vec;
+use bytemuck::Zeroable;
 
 use solana_sdk::{account::WritableAccount, program_option::COption, program_pack::Pack};
 use spl_token::state::AccountState;
@@ -35,6 +50,406 @@ pub fn mollusk() -> Mollusk {
     mollusk
 }
 
+pub fn pack_mint(mollusk: &Mollusk, mint_authority: Option<Pubkey>, supply: u64) -> Account {
+    let mut account = Account::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Mint::LEN),
+        spl_token::state::Mint::LEN,
+        &spl_token::ID,
+    );
+    solana_sdk::program_pack::Pack::pack(
+        spl_token::state::Mint {
+            mint_authority: mint_authority.into(),
+            supply,
+            decimals: 6,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        },
+        account.data_as_mut_slice(),
+    )
+    .unwrap();
+    account
+}
+
+pub fn pack_token_account(mollusk: &Mollusk, mint: Pubkey, owner: Pubkey, amount: u64) -> Account {
+    let mut account = Account::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Account::LEN),
+        spl_token::state::Account::LEN,
+        &spl_token::ID,
+    );
+    solana_sdk::program_pack::Pack::pack(
+        spl_token::state::Account {
+            mint,
+            owner,
+            amount,
+            delegate: COption::None,
+            state: AccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        account.data_as_mut_slice(),
+    )
+    .unwrap();
+    account
+}
+
+/// Reads the balance of a token account, leaving out any Token-2022 extensions.
+pub fn token_amount(account: &Account) -> u64 {
+    spl_token::state::Account::unpack(&account.data[..spl_token::state::Account::LEN])
+        .unwrap()
+        .amount
+}
+
+pub const TOKEN_2022: Pubkey = pubkey!("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb");
+
+/// Loads Token-2022 along with the token program, for pools with Token-2022 mints.
+pub fn mollusk_token_2022() -> Mollusk {
+    let mut mollusk = mollusk();
+    mollusk.add_program(
+        &TOKEN_2022,
+        "tests/elf_files/spl_token_2022",
+        &mollusk_svm::program::loader_keys::LOADER_V3,
+    );
+    mollusk
+}
+
+/// Packs a Token-2022 mint charging `transfer_fee_basis_points` of every transfer, up to
+/// `maximum_fee`. Its TransferFeeConfig extension follows the base mint, padded to the size of a
+/// token account, and the account type.
+pub fn pack_transfer_fee_mint(
+    mollusk: &Mollusk,
+    supply: u64,
+    transfer_fee_basis_points: u16,
+    maximum_fee: u64,
+) -> Account {
+    let mut data = vec![0; spl_token::state::Account::LEN];
+    spl_token::state::Mint {
+        mint_authority: COption::None,
+        supply,
+        decimals: 6,
+        is_initialized: true,
+        freeze_authority: COption::None,
+    }
+    .pack_into_slice(&mut data[..spl_token::state::Mint::LEN]);
+    data.push(1);
+
+    // No authorities and nothing withheld, with the same fee from epoch 0 on.
+    let transfer_fee = [
+        &0u64.to_le_bytes()[..],
+        &maximum_fee.to_le_bytes(),
+        &transfer_fee_basis_points.to_le_bytes(),
+    ]
+    .concat();
+    data.extend_from_slice(&1u16.to_le_bytes());
+    data.extend_from_slice(&108u16.to_le_bytes());
+    data.extend_from_slice(&[0; 72]);
+    data.extend_from_slice(&transfer_fee);
+    data.extend_from_slice(&transfer_fee);
+
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(data.len()),
+        data.len(),
+        &TOKEN_2022,
+    );
+    account.data = data;
+    account
+}
+
+/// Packs a Token-2022 account of a transfer-fee mint. Its TransferFeeAmount extension, which
+/// holds the fees withheld from incoming transfers, follows the base account and the account type.
+pub fn pack_transfer_fee_token_account(
+    mollusk: &Mollusk,
+    mint: Pubkey,
+    owner: Pubkey,
+    amount: u64,
+) -> Account {
+    let mut data = pack_token_account(mollusk, mint, owner, amount).data;
+    data.push(2);
+    data.extend_from_slice(&2u16.to_le_bytes());
+    data.extend_from_slice(&8u16.to_le_bytes());
+    data.extend_from_slice(&0u64.to_le_bytes());
+
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(data.len()),
+        data.len(),
+        &TOKEN_2022,
+    );
+    account.data = data;
+    account
+}
+
+/// Builds the Instructions sysvar of a transaction made of `instructions`.
+pub fn instructions_sysvar(mollusk: &Mollusk, instructions: &[Instruction]) -> (Pubkey, Account) {
+    let data = solana_instructions_sysvar::construct_instructions_data(
+        &instructions
+            .iter()
+            .map(|instruction| BorrowedInstruction {
+                program_id: &instruction.program_id,
+                accounts: instruction
+                    .accounts
+                    .iter()
+                    .map(|meta| BorrowedAccountMeta {
+                        pubkey: &meta.pubkey,
+                        is_signer: meta.is_signer,
+                        is_writable: meta.is_writable,
+                    })
+                    .collect(),
+                data: &instruction.data,
+            })
+            .collect::<Vec<_>>(),
+    );
+    let mut account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(data.len()),
+        data.len(),
+        &solana_sdk::sysvar::ID,
+    );
+    account.data = data;
+    (solana_sdk::sysvar::instructions::ID, account)
+}
+
+/// Derives the vault and LP mint PDAs of the pool at `config` and records their bumps in
+/// `config_state`. Returns vault_x, vault_y, mint_lp and vault_lp.
+pub fn pool_pdas(config: &Pubkey, config_state: &mut Config) -> [Pubkey; 4] {
+    let vault_pda = |mint: &[u8]| {
+        solana_sdk::pubkey::Pubkey::find_program_address(
+            &[VAULT_SEED.as_bytes(), config.as_ref(), mint],
+            &PROGRAM,
+        )
+    };
+    let (vault_x, vault_x_bump) = vault_pda(&config_state.mint_x);
+    let (vault_y, vault_y_bump) = vault_pda(&config_state.mint_y);
+    let (mint_lp, lp_bump) = solana_sdk::pubkey::Pubkey::find_program_address(
+        &[LP_SEED.as_bytes(), config.as_ref()],
+        &PROGRAM,
+    );
+    let (vault_lp, vault_lp_bump) = vault_pda(mint_lp.as_ref());
+
+    config_state.vault_x_bump = vault_x_bump;
+    config_state.vault_y_bump = vault_y_bump;
+    config_state.lp_bump = lp_bump;
+    config_state.vault_lp_bump = vault_lp_bump;
+    [vault_x, vault_y, mint_lp, vault_lp]
+}
+
+/// Accounts shared by the deposit, swap and withdraw instructions, in instruction order.
+pub struct PoolFixture {
+    pub user: Pubkey,
+    pub mint_x: Pubkey,
+    pub mint_y: Pubkey,
+    pub vault_x: Pubkey,
+    pub vault_y: Pubkey,
+    pub user_x: Pubkey,
+    pub user_y: Pubkey,
+    pub mint_lp: Pubkey,
+    pub user_lp: Pubkey,
+    pub vault_lp: Pubkey,
+    pub config: Pubkey,
+    pub config_state: Config,
+    pub token_program_x: Pubkey,
+    pub token_program_y: Pubkey,
+    pub stats: Pubkey,
+    pub accounts: Vec<(Pubkey, Account)>,
+}
+
+/// Sets up a pool holding `vault_x_amount`/`vault_y_amount` whose whole `lp_supply` belongs to
+/// the user. `configure` can adjust the config state before it is written.
+pub fn pool_fixture(
+    mollusk: &Mollusk,
+    vault_x_amount: u64,
+    vault_y_amount: u64,
+    lp_supply: u64,
+    configure: impl FnOnce(&mut Config),
+) -> PoolFixture {
+    let (system_program, system_account) = mollusk_svm::program::keyed_account_for_system_program();
+    let (token_program, token_account) = (
+        spl_token::ID,
+        program::create_program_account_loader_v3(&spl_token::ID),
+    );
+
+    let authority = Pubkey::new_from_array([0x00; 32]);
+    let user = Pubkey::new_from_array([0x01; 32]);
+    let mint_x = Pubkey::new_from_array([0x02; 32]);
+    let mint_y = Pubkey::new_from_array([0x03; 32]);
+    let user_x = Pubkey::new_from_array([0x06; 32]);
+    let user_y = Pubkey::new_from_array([0x07; 32]);
+    let user_lp = Pubkey::new_from_array([0x09; 32]);
+
+    let seed: u64 = 1;
+    let (config, config_bump) = solana_sdk::pubkey::Pubkey::find_program_address(
+        &[(CONFIG_SEED.as_bytes()), &seed.to_le_bytes()],
+        &PROGRAM,
+    );
+    let mut config_state = Config {
+        seed: seed.to_le_bytes(),
+        authority: *authority.as_array(),
+        mint_x: *mint_x.as_array(),
+        mint_y: *mint_y.as_array(),
+        fee: 500u16.to_le_bytes(),
+        config_bump,
+        ..Default::default()
+    };
+    let [vault_x, vault_y, mint_lp, vault_lp] = pool_pdas(&config, &mut config_state);
+    configure(&mut config_state);
+    let mut config_account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Config::LEN),
+        Config::LEN,
+        &PROGRAM.into(),
+    );
+    config_account.data = bytemuck::bytes_of(&config_state).to_vec();
+
+    let accounts = vec![
+        (user, Account::new(LAMPORTS_PER_SOL, 0, &system_program)),
+        (mint_x, pack_mint(mollusk, None, 100_000_000)),
+        (mint_y, pack_mint(mollusk, None, 100_000_000)),
+        (
+            vault_x,
+            pack_token_account(mollusk, mint_x, config, vault_x_amount),
+        ),
+        (
+            vault_y,
+            pack_token_account(mollusk, mint_y, config, vault_y_amount),
+        ),
+        (
+            user_x,
+            pack_token_account(mollusk, mint_x, user, 100_000_000),
+        ),
+        (
+            user_y,
+            pack_token_account(mollusk, mint_y, user, 100_000_000),
+        ),
+        (mint_lp, pack_mint(mollusk, Some(config), lp_supply)),
+        (
+            user_lp,
+            pack_token_account(mollusk, mint_lp, user, lp_supply),
+        ),
+        (config, config_account),
+        (system_program, system_account),
+        (token_program, token_account),
+        (vault_lp, pack_token_account(mollusk, mint_lp, config, 0)),
+        stats_fixture(mollusk, config),
+    ];
+
+    PoolFixture {
+        user,
+        mint_x,
+        mint_y,
+        vault_x,
+        vault_y,
+        user_x,
+        user_y,
+        mint_lp,
+        user_lp,
+        vault_lp,
+        config,
+        config_state,
+        token_program_x: token_program,
+        token_program_y: token_program,
+        stats: accounts[13].0,
+        accounts,
+    }
+}
+
+/// Builds the stats account of the pool at `config`, with every counter at zero.
+pub fn stats_fixture(mollusk: &Mollusk, config: Pubkey) -> (Pubkey, Account) {
+    let (stats, stats_bump) = solana_sdk::pubkey::Pubkey::find_program_address(
+        &[STATS_SEED.as_bytes(), config.as_ref()],
+        &PROGRAM,
+    );
+    let stats_state = Stats {
+        pool: *config.as_array(),
+        bump: stats_bump,
+        ..Zeroable::zeroed()
+    };
+    let mut stats_account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Stats::LEN),
+        Stats::LEN,
+        &PROGRAM,
+    );
+    stats_account.data = bytemuck::bytes_of(&stats_state).to_vec();
+    (stats, stats_account)
+}
+
+impl PoolFixture {
+    pub fn instruction(&self, discriminator: u8, data: &[u8]) -> Instruction {
+        let mut ser_instruction_data = vec![discriminator];
+        ser_instruction_data.extend_from_slice(data);
+
+        Instruction::new_with_bytes(
+            PROGRAM,
+            &ser_instruction_data,
+            vec![
+                AccountMeta::new(self.user, true),
+                AccountMeta::new_readonly(self.mint_x, false),
+                AccountMeta::new_readonly(self.mint_y, false),
+                AccountMeta::new(self.vault_x, false),
+                AccountMeta::new(self.vault_y, false),
+                AccountMeta::new(self.user_x, false),
+                AccountMeta::new(self.user_y, false),
+                AccountMeta::new(self.mint_lp, false),
+                AccountMeta::new(self.user_lp, false),
+                AccountMeta::new(self.config, false),
+                AccountMeta::new_readonly(self.accounts[10].0, false),
+                AccountMeta::new_readonly(self.token_program_x, false),
+                AccountMeta::new_readonly(self.token_program_y, false),
+                AccountMeta::new(self.stats, false),
+            ],
+        )
+    }
+
+    pub fn flash_loan_instruction(&self, data: &[u8]) -> Instruction {
+        let mut ser_instruction_data = vec![8];
+        ser_instruction_data.extend_from_slice(data);
+
+        Instruction::new_with_bytes(
+            PROGRAM,
+            &ser_instruction_data,
+            vec![
+                AccountMeta::new(self.user, true),
+                AccountMeta::new_readonly(self.mint_x, false),
+                AccountMeta::new_readonly(self.mint_y, false),
+                AccountMeta::new(self.vault_x, false),
+                AccountMeta::new(self.vault_y, false),
+                AccountMeta::new(self.user_x, false),
+                AccountMeta::new(self.user_y, false),
+                AccountMeta::new(self.config, false),
+                AccountMeta::new_readonly(solana_sdk::sysvar::instructions::ID, false),
+                AccountMeta::new_readonly(self.token_program_x, false),
+                AccountMeta::new_readonly(self.token_program_y, false),
+                AccountMeta::new(self.stats, false),
+            ],
+        )
+    }
+
+    pub fn flash_repay_instruction(&self) -> Instruction {
+        Instruction::new_with_bytes(
+            PROGRAM,
+            &[9],
+            vec![
+                AccountMeta::new_readonly(self.mint_x, false),
+                AccountMeta::new_readonly(self.mint_y, false),
+                AccountMeta::new_readonly(self.vault_x, false),
+                AccountMeta::new_readonly(self.vault_y, false),
+                AccountMeta::new(self.config, false),
+            ],
+        )
+    }
+
+    /// Deposit also takes the vault locking the minimum liquidity, after `user_lp`.
+    pub fn deposit_instruction(&self, data: &[u8]) -> Instruction {
+        let mut instruction = self.instruction(1, data);
+        instruction
+            .accounts
+            .insert(9, AccountMeta::new(self.vault_lp, false));
+        instruction
+    }
+}
+
 #[test]
 
 fn test_initialize() {
@@ -98,74 +513,39 @@ fn test_initialize() {
     )
     .unwrap();
 
-    let vault_x = Pubkey::new_from_array([0x04; 32]);
-    let mut vault_x_account = Account::new(
-        mollusk
-            .sysvars
-            .rent
-            .minimum_balance(spl_token::state::Account::LEN),
-        spl_token::state::Account::LEN,
-        &token_program,
+    // The vaults and mint_lp are PDAs created by the program.
+    let (vault_x, vault_x_bump) = solana_sdk::pubkey::Pubkey::find_program_address(
+        &[VAULT_SEED.as_bytes(), config.as_ref(), mint_x.as_ref()],
+        &PROGRAM,
     );
-    solana_sdk::program_pack::Pack::pack(
-        spl_token::state::Account {
-            mint: mint_x,
-            owner: config,
-            amount: 100_000_000,
-            delegate: COption::None,
-            state: AccountState::Initialized,
-            is_native: COption::None,
-            delegated_amount: 0,
-            close_authority: COption::None,
-        },
-        vault_x_account.data_as_mut_slice(),
-    )
-    .unwrap();
+    let vault_x_account = Account::new(0, 0, &system_program);
 
-    let vault_y = Pubkey::new_from_array([0x05; 32]);
-    let mut vault_y_account = Account::new(
-        mollusk
-            .sysvars
-            .rent
-            .minimum_balance(spl_token::state::Account::LEN),
-        spl_token::state::Account::LEN,
-        &token_program,
+    let (vault_y, vault_y_bump) = solana_sdk::pubkey::Pubkey::find_program_address(
+        &[VAULT_SEED.as_bytes(), config.as_ref(), mint_y.as_ref()],
+        &PROGRAM,
     );
-    solana_sdk::program_pack::Pack::pack(
-        spl_token::state::Account {
-            mint: mint_y,
-            owner: config,
-            amount: 100_000_000,
-            delegate: COption::None,
-            state: AccountState::Initialized,
-            is_native: COption::None,
-            delegated_amount: 0,
-            close_authority: COption::None,
-        },
-        vault_y_account.data_as_mut_slice(),
-    )
-    .unwrap();
+    let vault_y_account = Account::new(0, 0, &system_program);
 
-    let mint_lp = Pubkey::new_from_array([0x06; 32]);
-    let mut mint_lp_account = Account::new(
-        mollusk
-            .sysvars
-            .rent
-            .minimum_balance(spl_token::state::Mint::LEN),
-        spl_token::state::Mint::LEN,
-        &token_program,
+    let (mint_lp, lp_bump) = solana_sdk::pubkey::Pubkey::find_program_address(
+        &[LP_SEED.as_bytes(), config.as_ref()],
+        &PROGRAM,
+    );
+    let mint_lp_account = Account::new(0, 0, &system_program);
+
+    let (vault_lp, vault_lp_bump) = solana_sdk::pubkey::Pubkey::find_program_address(
+        &[VAULT_SEED.as_bytes(), config.as_ref(), mint_lp.as_ref()],
+        &PROGRAM,
+    );
+    let vault_lp_account = Account::new(0, 0, &system_program);
+
+    // The registry is created along with the first pool.
+    let (registry, registry_bump) =
+        solana_sdk::pubkey::Pubkey::find_program_address(&[REGISTRY_SEED.as_bytes()], &PROGRAM);
+    let registry_account = Account::new(0, 0, &system_program);
+    let (stats, stats_bump) = solana_sdk::pubkey::Pubkey::find_program_address(
+        &[STATS_SEED.as_bytes(), config.as_ref()],
+        &PROGRAM,
     );
-    solana_sdk::program_pack::Pack::pack(
-        spl_token::state::Mint {
-            mint_authority: COption::Some(config),
-            supply: 100_000_000,
-            decimals: 6,
-            is_initialized: true,
-            freeze_authority: COption::None,
-        },
-        mint_lp_account.data_as_mut_slice(),
-    )
-    .unwrap();
 
     // Create the instruction data
     let fee: u16 = 500;
@@ -173,6 +553,18 @@ fn test_initialize() {
         seed: seed.to_le_bytes(),
         fee: fee.to_le_bytes(),
         config_bump,
+        protocol_fee: 0u16.to_le_bytes(),
+        lp_bump,
+        vault_x_bump,
+        vault_y_bump,
+        curve_type: 0,
+        amp: 0u64.to_le_bytes(),
+        vault_lp_bump,
+        factory: 0,
+        registry_bump,
+        stats_bump,
+        weight_x: 0u16.to_le_bytes(),
+        weight_y: 0u16.to_le_bytes(),
     };
 
     // instruction discriminator = 0
@@ -191,9 +583,13 @@ fn test_initialize() {
             AccountMeta::new(vault_x, false),
             AccountMeta::new(vault_y, false),
             AccountMeta::new(mint_lp, false),
+            AccountMeta::new(vault_lp, false),
             AccountMeta::new(config, true),
+            AccountMeta::new(registry, false),
             AccountMeta::new_readonly(system_program, false),
             AccountMeta::new_readonly(token_program, false),
+            AccountMeta::new_readonly(token_program, false),
+            AccountMeta::new(stats, false),
         ],
     );
 
@@ -206,11 +602,25 @@ fn test_initialize() {
             (vault_x, vault_x_account),
             (vault_y, vault_y_account),
             (mint_lp, mint_lp_account),
+            (vault_lp, vault_lp_account),
             (config, config_account),
+            (registry, registry_account),
             (system_program, system_account),
             (token_program, token_account),
+            (stats, Account::new(0, 0, &system_program)),
+        ],
+        &[
+            Check::success(),
+            Check::account(&mint_lp).owner(&token_program).build(),
+            Check::account(&vault_x).owner(&token_program).build(),
+            Check::account(&vault_y).owner(&token_program).build(),
+            Check::account(&vault_lp).owner(&token_program).build(),
+            Check::account(&registry)
+                .owner(&PROGRAM)
+                .data(&[&1u64.to_le_bytes()[..], config.as_ref()].concat())
+                .build(),
+            Check::account(&stats).owner(&PROGRAM).build(),
         ],
-        &[Check::success()],
     );
 }
 
@@ -285,17 +695,19 @@ fn test_deposit() {
         Config::LEN,
         &PROGRAM.into(),
     );
-    let config_state = Config {
+    let mut config_state = Config {
         seed: seed.to_le_bytes(),
         authority: *authority.as_array(),
         mint_x: *mint_x.as_array(),
         mint_y: *mint_y.as_array(),
         fee: fee.to_le_bytes(),
         config_bump: config_bump,
+        ..Default::default()
     };
+    let [vault_x, vault_y, mint_lp, vault_lp] = pool_pdas(&config, &mut config_state);
     config_account.data = bytemuck::bytes_of(&config_state).to_vec();
+    let (stats, stats_account) = stats_fixture(&mollusk, config);
 
-    let vault_x = Pubkey::new_from_array([0x04; 32]);
     let mut vault_x_account = Account::new(
         mollusk
             .sysvars
@@ -319,7 +731,6 @@ fn test_deposit() {
     )
     .unwrap();
 
-    let vault_y = Pubkey::new_from_array([0x05; 32]);
     let mut vault_y_account = Account::new(
         mollusk
             .sysvars
@@ -391,7 +802,6 @@ fn test_deposit() {
     )
     .unwrap();
 
-    let mint_lp = Pubkey::new_from_array([0x08; 32]);
     let mut mint_lp_account = Account::new(
         mollusk
             .sysvars
@@ -436,10 +846,33 @@ fn test_deposit() {
     )
     .unwrap();
 
+    let mut vault_lp_account = Account::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Account::LEN),
+        spl_token::state::Account::LEN,
+        &token_program,
+    );
+    solana_sdk::program_pack::Pack::pack(
+        spl_token::state::Account {
+            mint: mint_lp,
+            owner: config,
+            amount: MINIMUM_LIQUIDITY,
+            delegate: COption::None,
+            state: AccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        vault_lp_account.data_as_mut_slice(),
+    )
+    .unwrap();
+
     // Create the instruction data
     let amount: u64 = 100;
-    let max_x: u64 = 50;
-    let max_y: u64 = 50;
+    let max_x: u64 = 110;
+    let max_y: u64 = 110;
     let instruction_data = DepositInstructionData {
         amount: amount.to_le_bytes(),
         max_x: max_x.to_le_bytes(),
@@ -465,9 +898,12 @@ fn test_deposit() {
             AccountMeta::new(user_y, false),
             AccountMeta::new(mint_lp, false),
             AccountMeta::new(user_lp, false),
+            AccountMeta::new(vault_lp, false),
             AccountMeta::new(config, true),
             AccountMeta::new_readonly(system_program, false),
             AccountMeta::new_readonly(token_program, false),
+            AccountMeta::new_readonly(token_program, false),
+            AccountMeta::new(stats, false),
         ],
     );
 
@@ -483,9 +919,11 @@ fn test_deposit() {
             (user_y, user_y_account),
             (mint_lp, mint_lp_account),
             (user_lp, user_lp_account),
+            (vault_lp, vault_lp_account),
             (config, config_account),
             (system_program, system_account),
             (token_program, token_account),
+            (stats, stats_account),
         ],
         &[Check::success()],
     );
@@ -561,17 +999,19 @@ fn test_swap() {
         Config::LEN,
         &PROGRAM.into(),
     );
-    let config_state = Config {
+    let mut config_state = Config {
         seed: seed.to_le_bytes(),
         authority: *authority.as_array(),
         mint_x: *mint_x.as_array(),
         mint_y: *mint_y.as_array(),
         fee: fee.to_le_bytes(),
         config_bump: config_bump,
+        ..Default::default()
     };
+    let [vault_x, vault_y, mint_lp, _] = pool_pdas(&config, &mut config_state);
     config_account.data = bytemuck::bytes_of(&config_state).to_vec();
+    let (stats, stats_account) = stats_fixture(&mollusk, config);
 
-    let vault_x = Pubkey::new_from_array([0x04; 32]);
     let mut vault_x_account = Account::new(
         mollusk
             .sysvars
@@ -595,7 +1035,6 @@ fn test_swap() {
     )
     .unwrap();
 
-    let vault_y = Pubkey::new_from_array([0x05; 32]);
     let mut vault_y_account = Account::new(
         mollusk
             .sysvars
@@ -667,7 +1106,6 @@ fn test_swap() {
     )
     .unwrap();
 
-    let mint_lp = Pubkey::new_from_array([0x08; 32]);
     let mut mint_lp_account = Account::new(
         mollusk
             .sysvars
@@ -719,6 +1157,7 @@ fn test_swap() {
         is_x: 1,
         amount: amount.to_le_bytes(),
         min: min.to_le_bytes(),
+        deadline: 0i64.to_le_bytes(),
     };
 
     // instruction discriminator = 2
@@ -743,6 +1182,8 @@ fn test_swap() {
             AccountMeta::new(config, true),
             AccountMeta::new_readonly(system_program, false),
             AccountMeta::new_readonly(token_program, false),
+            AccountMeta::new_readonly(token_program, false),
+            AccountMeta::new(stats, false),
         ],
     );
 
@@ -761,6 +1202,7 @@ fn test_swap() {
             (config, config_account),
             (system_program, system_account),
             (token_program, token_account),
+            (stats, stats_account),
         ],
         &[Check::success()],
     );
@@ -836,17 +1278,19 @@ fn test_withdraw() {
         Config::LEN,
         &PROGRAM.into(),
     );
-    let config_state = Config {
+    let mut config_state = Config {
         seed: seed.to_le_bytes(),
         authority: *authority.as_array(),
         mint_x: *mint_x.as_array(),
         mint_y: *mint_y.as_array(),
         fee: fee.to_le_bytes(),
         config_bump: config_bump,
+        ..Default::default()
     };
+    let [vault_x, vault_y, mint_lp, _] = pool_pdas(&config, &mut config_state);
     config_account.data = bytemuck::bytes_of(&config_state).to_vec();
+    let (stats, stats_account) = stats_fixture(&mollusk, config);
 
-    let vault_x = Pubkey::new_from_array([0x04; 32]);
     let mut vault_x_account = Account::new(
         mollusk
             .sysvars
@@ -870,7 +1314,6 @@ fn test_withdraw() {
     )
     .unwrap();
 
-    let vault_y = Pubkey::new_from_array([0x05; 32]);
     let mut vault_y_account = Account::new(
         mollusk
             .sysvars
@@ -942,7 +1385,6 @@ fn test_withdraw() {
     )
     .unwrap();
 
-    let mint_lp = Pubkey::new_from_array([0x08; 32]);
     let mut mint_lp_account = Account::new(
         mollusk
             .sysvars
@@ -989,12 +1431,12 @@ fn test_withdraw() {
 
     // Create the instruction data
     let amount: u64 = 100;
-    let max_x: u64 = 50;
-    let max_y: u64 = 50;
+    let min_x: u64 = 50;
+    let min_y: u64 = 50;
     let instruction_data = WithdrawInstructionData {
         amount: amount.to_le_bytes(),
-        max_x: max_x.to_le_bytes(),
-        max_y: max_y.to_le_bytes(),
+        min_x: min_x.to_le_bytes(),
+        min_y: min_y.to_le_bytes(),
     };
 
     // instruction discriminator = 3
@@ -1019,6 +1461,8 @@ fn test_withdraw() {
             AccountMeta::new(config, true),
             AccountMeta::new_readonly(system_program, false),
             AccountMeta::new_readonly(token_program, false),
+            AccountMeta::new_readonly(token_program, false),
+            AccountMeta::new(stats, false),
         ],
     );
 
@@ -1037,7 +1481,2791 @@ fn test_withdraw() {
             (config, config_account),
             (system_program, system_account),
             (token_program, token_account),
+            (stats, stats_account),
         ],
         &[Check::success()],
     );
 }
+
+#[test]
+
+fn test_withdraw_excludes_protocol_fees() {
+    let mollusk = mollusk();
+
+    let protocol_fees_x: u64 = 1_000_000;
+    let protocol_fees_y: u64 = 2_000_000;
+    let pool = pool_fixture(&mollusk, 100_000_000, 100_000_000, 100_000_000, |config| {
+        config.protocol_fee = 1_000u16.to_le_bytes();
+        config.protocol_fees_x = protocol_fees_x.to_le_bytes();
+        config.protocol_fees_y = protocol_fees_y.to_le_bytes();
+    });
+
+    // Redeem 10% of the LP supply.
+    let instruction_data = WithdrawInstructionData {
+        amount: 10_000_000u64.to_le_bytes(),
+        min_x: 0u64.to_le_bytes(),
+        min_y: 0u64.to_le_bytes(),
+    };
+
+    let result = mollusk.process_and_validate_instruction(
+        &pool.instruction(3, bytemuck::bytes_of(&instruction_data)),
+        &pool.accounts,
+        &[Check::success()],
+    );
+
+    // The LP share is computed on the reserves net of protocol fees.
+    let received_x = token_amount(result.get_account(&pool.user_x).unwrap()) - 100_000_000;
+    let received_y = token_amount(result.get_account(&pool.user_y).unwrap()) - 100_000_000;
+    assert!(received_x > 0 && received_x <= (100_000_000 - protocol_fees_x) / 10);
+    assert!(received_y > 0 && received_y <= (100_000_000 - protocol_fees_y) / 10);
+
+    // The vaults still hold the protocol fees on top of the remaining LP share.
+    let vault_x_after = token_amount(result.get_account(&pool.vault_x).unwrap());
+    let vault_y_after = token_amount(result.get_account(&pool.vault_y).unwrap());
+    assert!(vault_x_after >= protocol_fees_x + (100_000_000 - protocol_fees_x) / 10 * 9);
+    assert!(vault_y_after >= protocol_fees_y + (100_000_000 - protocol_fees_y) / 10 * 9);
+}
+
+#[test]
+
+fn test_claim_fees() {
+    let mollusk = mollusk();
+
+    let (token_program, token_account) = (
+        spl_token::ID,
+        program::create_program_account_loader_v3(&spl_token::ID),
+    );
+
+    let authority = Pubkey::new_from_array([0x01; 32]);
+    let authority_account = Account::new(1 * LAMPORTS_PER_SOL, 0, &Pubkey::default());
+
+    let seed: u64 = 1;
+    let protocol_fees_x: u64 = 1_000;
+    let protocol_fees_y: u64 = 2_000;
+
+    let mint_x = Pubkey::new_from_array([0x02; 32]);
+    let mint_x_account = pack_mint(&mollusk, None, 100_000_000);
+
+    let mint_y = Pubkey::new_from_array([0x03; 32]);
+    let mint_y_account = pack_mint(&mollusk, None, 100_000_000);
+
+    // Config account.
+    let (config, config_bump) = solana_sdk::pubkey::Pubkey::find_program_address(
+        &[(CONFIG_SEED.as_bytes()), &seed.to_le_bytes()],
+        &PROGRAM,
+    );
+    let mut config_account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Config::LEN),
+        Config::LEN,
+        &PROGRAM.into(),
+    );
+    let mut config_state = Config {
+        seed: seed.to_le_bytes(),
+        authority: *authority.as_array(),
+        mint_x: *mint_x.as_array(),
+        mint_y: *mint_y.as_array(),
+        fee: 500u16.to_le_bytes(),
+        config_bump: config_bump,
+        protocol_fee: 1_000u16.to_le_bytes(),
+        protocol_fees_x: protocol_fees_x.to_le_bytes(),
+        protocol_fees_y: protocol_fees_y.to_le_bytes(),
+        ..Default::default()
+    };
+    let [vault_x, vault_y, _, _] = pool_pdas(&config, &mut config_state);
+    config_account.data = bytemuck::bytes_of(&config_state).to_vec();
+
+    let vault_x_account = pack_token_account(&mollusk, mint_x, config, 100_000_000);
+
+    let vault_y_account = pack_token_account(&mollusk, mint_y, config, 100_000_000);
+
+    let treasury_x = Pubkey::new_from_array([0x06; 32]);
+    let treasury_x_account = pack_token_account(&mollusk, mint_x, authority, 0);
+
+    let treasury_y = Pubkey::new_from_array([0x07; 32]);
+    let treasury_y_account = pack_token_account(&mollusk, mint_y, authority, 0);
+
+    let instruction = Instruction::new_with_bytes(
+        PROGRAM,
+        // instruction discriminator = 4
+        &[4],
+        vec![
+            AccountMeta::new(authority, true),
+            AccountMeta::new_readonly(mint_x, false),
+            AccountMeta::new_readonly(mint_y, false),
+            AccountMeta::new(vault_x, false),
+            AccountMeta::new(vault_y, false),
+            AccountMeta::new(treasury_x, false),
+            AccountMeta::new(treasury_y, false),
+            AccountMeta::new(config, false),
+            AccountMeta::new_readonly(token_program, false),
+            AccountMeta::new_readonly(token_program, false),
+        ],
+    );
+
+    let mut claimed_config_state = config_state;
+    claimed_config_state.protocol_fees_x = 0u64.to_le_bytes();
+    claimed_config_state.protocol_fees_y = 0u64.to_le_bytes();
+
+    let result = mollusk.process_and_validate_instruction(
+        &instruction,
+        &vec![
+            (authority, authority_account),
+            (mint_x, mint_x_account),
+            (mint_y, mint_y_account),
+            (vault_x, vault_x_account),
+            (vault_y, vault_y_account),
+            (treasury_x, treasury_x_account),
+            (treasury_y, treasury_y_account),
+            (config, config_account),
+            (token_program, token_account),
+        ],
+        &[
+            Check::success(),
+            Check::account(&config)
+                .data(bytemuck::bytes_of(&claimed_config_state))
+                .build(),
+        ],
+    );
+
+    assert_eq!(
+        token_amount(result.get_account(&treasury_x).unwrap()),
+        protocol_fees_x
+    );
+    assert_eq!(
+        token_amount(result.get_account(&treasury_y).unwrap()),
+        protocol_fees_y
+    );
+}
+
+#[test]
+
+fn test_update_config() {
+    let mollusk = mollusk();
+
+    let authority = Pubkey::new_from_array([0x01; 32]);
+    let authority_account = Account::new(1 * LAMPORTS_PER_SOL, 0, &Pubkey::default());
+
+    let seed: u64 = 1;
+
+    // Config account.
+    let (config, config_bump) = solana_sdk::pubkey::Pubkey::find_program_address(
+        &[(CONFIG_SEED.as_bytes()), &seed.to_le_bytes()],
+        &PROGRAM,
+    );
+    let mut config_account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Config::LEN),
+        Config::LEN,
+        &PROGRAM.into(),
+    );
+    let config_state = Config {
+        seed: seed.to_le_bytes(),
+        authority: *authority.as_array(),
+        mint_x: [0x02; 32],
+        mint_y: [0x03; 32],
+        fee: 500u16.to_le_bytes(),
+        config_bump: config_bump,
+        ..Default::default()
+    };
+    config_account.data = bytemuck::bytes_of(&config_state).to_vec();
+
+    // Lower the fee and lock the pool.
+    let fee: u16 = 30;
+    let instruction_data = UpdateConfigInstructionData {
+        fee: fee.to_le_bytes(),
+        locked: 1,
+        referral_fee: 0u16.to_le_bytes(),
+    };
+
+    // instruction discriminator = 5
+    let mut ser_instruction_data = vec![5];
+
+    // Serialize the instruction data
+    ser_instruction_data.extend_from_slice(bytemuck::bytes_of(&instruction_data));
+
+    let instruction = Instruction::new_with_bytes(
+        PROGRAM,
+        &ser_instruction_data,
+        vec![
+            AccountMeta::new(authority, true),
+            AccountMeta::new(config, false),
+        ],
+    );
+
+    let mut updated_config_state = config_state;
+    updated_config_state.fee = fee.to_le_bytes();
+    updated_config_state.locked = 1;
+
+    mollusk.process_and_validate_instruction(
+        &instruction,
+        &vec![
+            (authority, authority_account.clone()),
+            (config, config_account.clone()),
+        ],
+        &[
+            Check::success(),
+            Check::account(&config)
+                .data(bytemuck::bytes_of(&updated_config_state))
+                .build(),
+        ],
+    );
+
+    // Only the authority may update the config.
+    let impostor = Pubkey::new_from_array([0x09; 32]);
+    let instruction = Instruction::new_with_bytes(
+        PROGRAM,
+        &ser_instruction_data,
+        vec![
+            AccountMeta::new(impostor, true),
+            AccountMeta::new(config, false),
+        ],
+    );
+
+    mollusk.process_and_validate_instruction(
+        &instruction,
+        &vec![(impostor, authority_account), (config, config_account)],
+        &[Check::err(ProgramError::IncorrectAuthority)],
+    );
+}
+
+#[test]
+
+fn test_swap_locked_pool() {
+    let mollusk = mollusk();
+
+    let pool = pool_fixture(&mollusk, 100_000_000, 100_000_000, 100_000_000, |config| {
+        config.locked = 1;
+    });
+
+    let instruction_data = SwapInstructionData {
+        is_x: 1,
+        amount: 100u64.to_le_bytes(),
+        min: 1u64.to_le_bytes(),
+        deadline: 0i64.to_le_bytes(),
+    };
+
+    mollusk.process_and_validate_instruction(
+        &pool.instruction(2, bytemuck::bytes_of(&instruction_data)),
+        &pool.accounts,
+        &[Check::err(ProgramError::Custom(
+            CustomError::PoolLocked as u32,
+        ))],
+    );
+}
+
+#[test]
+
+fn test_deposit_slippage_exceeded() {
+    let mollusk = mollusk();
+
+    let pool = pool_fixture(&mollusk, 100_000_000, 100_000_000, 100_000_000, |_| {});
+
+    // 1% of the supply requires 1_000_000 of each token.
+    let instruction_data = DepositInstructionData {
+        amount: 1_000_000u64.to_le_bytes(),
+        max_x: 500_000u64.to_le_bytes(),
+        max_y: 2_000_000u64.to_le_bytes(),
+    };
+
+    mollusk.process_and_validate_instruction(
+        &pool.deposit_instruction(bytemuck::bytes_of(&instruction_data)),
+        &pool.accounts,
+        &[Check::err(ProgramError::Custom(
+            CustomError::DepositSlippageExceeded as u32,
+        ))],
+    );
+}
+
+#[test]
+
+fn test_withdraw_slippage_exceeded() {
+    let mollusk = mollusk();
+
+    let pool = pool_fixture(&mollusk, 100_000_000, 100_000_000, 100_000_000, |_| {});
+
+    // 1% of the supply only redeems 1_000_000 of each token.
+    let instruction_data = WithdrawInstructionData {
+        amount: 1_000_000u64.to_le_bytes(),
+        min_x: 0u64.to_le_bytes(),
+        min_y: 2_000_000u64.to_le_bytes(),
+    };
+
+    mollusk.process_and_validate_instruction(
+        &pool.instruction(3, bytemuck::bytes_of(&instruction_data)),
+        &pool.accounts,
+        &[Check::err(ProgramError::Custom(
+            CustomError::WithdrawSlippageExceeded as u32,
+        ))],
+    );
+}
+
+#[test]
+
+fn test_swap_spoofed_mint() {
+    let mollusk = mollusk();
+
+    let mut pool = pool_fixture(&mollusk, 100_000_000, 100_000_000, 100_000_000, |_| {});
+
+    let instruction_data = SwapInstructionData {
+        is_x: 1,
+        amount: 100u64.to_le_bytes(),
+        min: 1u64.to_le_bytes(),
+        deadline: 0i64.to_le_bytes(),
+    };
+    let mut instruction = pool.instruction(2, bytemuck::bytes_of(&instruction_data));
+
+    // Pass a mint the pool was not created for.
+    let spoofed_mint = Pubkey::new_from_array([0x0a; 32]);
+    instruction.accounts[1].pubkey = spoofed_mint;
+    pool.accounts[1] = (spoofed_mint, pack_mint(&mollusk, None, 100_000_000));
+
+    mollusk.process_and_validate_instruction(
+        &instruction,
+        &pool.accounts,
+        &[Check::err(ProgramError::Custom(
+            CustomError::MintMismatch as u32,
+        ))],
+    );
+}
+
+#[test]
+
+fn test_deposit_spoofed_vault_mint() {
+    let mollusk = mollusk();
+
+    let mut pool = pool_fixture(&mollusk, 100_000_000, 100_000_000, 100_000_000, |_| {});
+
+    // vault_x is owned by the config but holds mint_y.
+    pool.accounts[3].1 = pack_token_account(&mollusk, pool.mint_y, pool.config, 100_000_000);
+
+    let instruction_data = DepositInstructionData {
+        amount: 1_000_000u64.to_le_bytes(),
+        max_x: 2_000_000u64.to_le_bytes(),
+        max_y: 2_000_000u64.to_le_bytes(),
+    };
+
+    mollusk.process_and_validate_instruction(
+        &pool.deposit_instruction(bytemuck::bytes_of(&instruction_data)),
+        &pool.accounts,
+        &[Check::err(ProgramError::Custom(
+            CustomError::VaultMintMismatch as u32,
+        ))],
+    );
+}
+
+#[test]
+
+fn test_deposit_spoofed_pool_accounts() {
+    let mollusk = mollusk();
+
+    let pool = pool_fixture(&mollusk, 100_000_000, 100_000_000, 100_000_000, |_| {});
+
+    let instruction_data = DepositInstructionData {
+        amount: 1_000_000u64.to_le_bytes(),
+        max_x: 2_000_000u64.to_le_bytes(),
+        max_y: 2_000_000u64.to_le_bytes(),
+    };
+    let instruction = pool.deposit_instruction(bytemuck::bytes_of(&instruction_data));
+
+    // Substitute, one at a time, vault_x, vault_y, mint_lp and vault_lp with lookalikes owned by
+    // the config, or with the config as mint authority, but which are not the pool PDAs. Each is
+    // given by its index in the instruction and in the fixture accounts.
+    let impostor = Pubkey::new_from_array([0x0d; 32]);
+    let lookalikes = [
+        (
+            3,
+            3,
+            pack_token_account(&mollusk, pool.mint_x, pool.config, 100_000_000),
+        ),
+        (
+            4,
+            4,
+            pack_token_account(&mollusk, pool.mint_y, pool.config, 100_000_000),
+        ),
+        (7, 7, pack_mint(&mollusk, Some(pool.config), 0)),
+        (
+            9,
+            12,
+            pack_token_account(&mollusk, pool.mint_lp, pool.config, MINIMUM_LIQUIDITY),
+        ),
+    ];
+    for (meta_index, account_index, lookalike) in lookalikes {
+        let mut instruction = instruction.clone();
+        instruction.accounts[meta_index].pubkey = impostor;
+        let mut accounts = pool.accounts.clone();
+        accounts[account_index] = (impostor, lookalike);
+
+        mollusk.process_and_validate_instruction(
+            &instruction,
+            &accounts,
+            &[Check::err(ProgramError::InvalidAccountData)],
+        );
+    }
+}
+
+#[test]
+
+fn test_withdraw_duplicate_vault() {
+    let mollusk = mollusk();
+
+    let mut pool = pool_fixture(&mollusk, 100_000_000, 100_000_000, 100_000_000, |_| {});
+
+    let instruction_data = WithdrawInstructionData {
+        amount: 1_000_000u64.to_le_bytes(),
+        min_x: 0u64.to_le_bytes(),
+        min_y: 0u64.to_le_bytes(),
+    };
+    let mut instruction = pool.instruction(3, bytemuck::bytes_of(&instruction_data));
+
+    // Pass vault_x for both sides of the pool.
+    instruction.accounts[4].pubkey = pool.vault_x;
+    pool.accounts.remove(4);
+
+    mollusk.process_and_validate_instruction(
+        &instruction,
+        &pool.accounts,
+        &[Check::err(ProgramError::Custom(
+            CustomError::DuplicateAccount as u32,
+        ))],
+    );
+}
+
+#[test]
+
+fn test_swap_fake_vault() {
+    let mollusk = mollusk();
+
+    let mut pool = pool_fixture(&mollusk, 100_000_000, 100_000_000, 100_000_000, |_| {});
+
+    let instruction_data = SwapInstructionData {
+        is_x: 1,
+        amount: 100u64.to_le_bytes(),
+        min: 1u64.to_le_bytes(),
+        deadline: 0i64.to_le_bytes(),
+    };
+    let mut instruction = pool.instruction(2, bytemuck::bytes_of(&instruction_data));
+
+    // Pass a token account owned by the config and holding mint_y, but which is not the vault PDA.
+    let fake_vault = Pubkey::new_from_array([0x05; 32]);
+    instruction.accounts[4].pubkey = fake_vault;
+    pool.accounts[4] = (
+        fake_vault,
+        pack_token_account(&mollusk, pool.mint_y, pool.config, 1),
+    );
+
+    mollusk.process_and_validate_instruction(
+        &instruction,
+        &pool.accounts,
+        &[Check::err(ProgramError::InvalidAccountData)],
+    );
+}
+
+#[test]
+
+fn test_withdraw_fake_mint_lp() {
+    let mollusk = mollusk();
+
+    let mut pool = pool_fixture(&mollusk, 100_000_000, 100_000_000, 100_000_000, |_| {});
+
+    let instruction_data = WithdrawInstructionData {
+        amount: 1u64.to_le_bytes(),
+        min_x: 0u64.to_le_bytes(),
+        min_y: 0u64.to_le_bytes(),
+    };
+    let mut instruction = pool.instruction(3, bytemuck::bytes_of(&instruction_data));
+
+    // Pass a mint with the config as mint authority, but which is not the LP mint PDA. Its single
+    // token would be worth the whole pool.
+    let fake_mint_lp = Pubkey::new_from_array([0x08; 32]);
+    instruction.accounts[7].pubkey = fake_mint_lp;
+    pool.accounts[7] = (fake_mint_lp, pack_mint(&mollusk, Some(pool.config), 1));
+    pool.accounts[8].1 = pack_token_account(&mollusk, fake_mint_lp, pool.user, 1);
+
+    mollusk.process_and_validate_instruction(
+        &instruction,
+        &pool.accounts,
+        &[Check::err(ProgramError::InvalidAccountData)],
+    );
+}
+
+#[test]
+
+fn test_swap_exact_out() {
+    let mollusk = mollusk();
+
+    let pool = pool_fixture(&mollusk, 100_000_000, 100_000_000, 100_000_000, |_| {});
+
+    // Buying 1_000_000 y needs ceil(1e8 * 1e6 / 99e6) = 1_010_102 x after the 5% fee,
+    // i.e. ceil(1_010_102 / 0.95) = 1_063_266 x in.
+    let instruction_data = SwapExactOutInstructionData {
+        is_x: 1,
+        amount: 1_000_000u64.to_le_bytes(),
+        max: 1_100_000u64.to_le_bytes(),
+        deadline: 0i64.to_le_bytes(),
+    };
+
+    let result = mollusk.process_and_validate_instruction(
+        &pool.instruction(6, bytemuck::bytes_of(&instruction_data)),
+        &pool.accounts,
+        &[Check::success()],
+    );
+
+    assert_eq!(
+        token_amount(result.get_account(&pool.user_x).unwrap()),
+        100_000_000 - 1_063_266
+    );
+    assert_eq!(
+        token_amount(result.get_account(&pool.user_y).unwrap()),
+        100_000_000 + 1_000_000
+    );
+}
+
+#[test]
+
+fn test_swap_exact_out_slippage_exceeded() {
+    let mollusk = mollusk();
+
+    let pool = pool_fixture(&mollusk, 100_000_000, 100_000_000, 100_000_000, |_| {});
+
+    let instruction_data = SwapExactOutInstructionData {
+        is_x: 1,
+        amount: 1_000_000u64.to_le_bytes(),
+        max: 1_063_265u64.to_le_bytes(),
+        deadline: 0i64.to_le_bytes(),
+    };
+
+    mollusk.process_and_validate_instruction(
+        &pool.instruction(6, bytemuck::bytes_of(&instruction_data)),
+        &pool.accounts,
+        &[Check::err(ProgramError::Custom(
+            CustomError::SwapSlippageExceeded as u32,
+        ))],
+    );
+}
+
+#[test]
+
+fn test_get_twap() {
+    let mut mollusk = mollusk();
+
+    // The price of x was 1 y over the last 1000 seconds, and the pool now holds 2 y per x.
+    let last_update_timestamp: i64 = 1_000_000;
+    let pool = pool_fixture(&mollusk, 100_000_000, 200_000_000, 100_000_000, |config| {
+        config.price_x_cumulative = ((1u128 << 64) * 1_000).to_le_bytes();
+        config.price_y_cumulative = ((1u128 << 64) * 1_000).to_le_bytes();
+        config.last_update_timestamp = last_update_timestamp.to_le_bytes();
+        config.observations[0] = Observation {
+            timestamp: (last_update_timestamp - 1_000).to_le_bytes(),
+            price_x_cumulative: 0u128.to_le_bytes(),
+            price_y_cumulative: 0u128.to_le_bytes(),
+        };
+    });
+    mollusk.sysvars.clock.unix_timestamp = last_update_timestamp + 500;
+
+    let instruction_data = GetTwapInstructionData {
+        window: 1_000i64.to_le_bytes(),
+    };
+
+    // instruction discriminator = 7
+    let mut ser_instruction_data = vec![7];
+
+    // Serialize the instruction data
+    ser_instruction_data.extend_from_slice(bytemuck::bytes_of(&instruction_data));
+
+    let instruction = Instruction::new_with_bytes(
+        PROGRAM,
+        &ser_instruction_data,
+        vec![
+            AccountMeta::new_readonly(pool.mint_x, false),
+            AccountMeta::new_readonly(pool.mint_y, false),
+            AccountMeta::new_readonly(pool.vault_x, false),
+            AccountMeta::new_readonly(pool.vault_y, false),
+            AccountMeta::new_readonly(pool.config, false),
+        ],
+    );
+
+    // The only observation old enough is 1500 seconds old: 1000 seconds at a price of 1 and
+    // 500 seconds at the current price.
+    let expected = TwapResult {
+        price_x: ((2_000u128 << 64) / 1_500).to_le_bytes(),
+        price_y: ((1_250u128 << 64) / 1_500).to_le_bytes(),
+        elapsed: 1_500i64.to_le_bytes(),
+    };
+
+    mollusk.process_and_validate_instruction(
+        &instruction,
+        &pool.accounts,
+        &[
+            Check::success(),
+            Check::return_data(bytemuck::bytes_of(&expected)),
+        ],
+    );
+}
+
+#[test]
+
+fn test_swap_stable_swap_curve() {
+    let mollusk = mollusk();
+
+    let amp: u64 = 100;
+    let pool = pool_fixture(&mollusk, 100_000_000, 100_000_000, 100_000_000, |config| {
+        config.curve_type = 1;
+        config.amp = amp.to_le_bytes();
+    });
+
+    let amount: u64 = 1_000_000;
+    let instruction_data = SwapInstructionData {
+        is_x: 1,
+        amount: amount.to_le_bytes(),
+        min: 1u64.to_le_bytes(),
+        deadline: 0i64.to_le_bytes(),
+    };
+
+    let result = mollusk.process_and_validate_instruction(
+        &pool.instruction(2, bytemuck::bytes_of(&instruction_data)),
+        &pool.accounts,
+        &[Check::success()],
+    );
+
+    // The pool prices the swap on the StableSwap invariant, after the 5% fee.
+    let expected = stable_swap::swap_exact_in(
+        100_000_000,
+        100_000_000,
+        amount_after_fee(amount, 500).unwrap(),
+        amp,
+    )
+    .unwrap();
+    assert_eq!(
+        token_amount(result.get_account(&pool.user_y).unwrap()),
+        100_000_000 + expected
+    );
+}
+
+#[test]
+
+fn test_swap_weighted_curve() {
+    let mollusk = mollusk();
+
+    // An 80/20 pool, holding four times as much x as y at a price of one.
+    let pool = pool_fixture(&mollusk, 400_000_000, 100_000_000, 100_000_000, |config| {
+        config.curve_type = 2;
+        config.weight_x = 8_000u16.to_le_bytes();
+        config.weight_y = 2_000u16.to_le_bytes();
+    });
+
+    let amount: u64 = 1_000_000;
+    for (is_x, reserve_in, reserve_out, weight_in, weight_out) in [
+        (1, 400_000_000, 100_000_000, 8_000, 2_000),
+        (0, 100_000_000, 400_000_000, 2_000, 8_000),
+    ] {
+        let instruction_data = SwapInstructionData {
+            is_x,
+            amount: amount.to_le_bytes(),
+            min: 1u64.to_le_bytes(),
+            deadline: 0i64.to_le_bytes(),
+        };
+        let result = mollusk.process_and_validate_instruction(
+            &pool.instruction(2, bytemuck::bytes_of(&instruction_data)),
+            &pool.accounts,
+            &[Check::success()],
+        );
+
+        // The pool prices the swap on the weighted invariant, with the weights of the side
+        // traded in and out, after the 5% fee.
+        let expected = weighted::swap_exact_in(
+            reserve_in,
+            reserve_out,
+            amount_after_fee(amount, 500).unwrap(),
+            weight_in,
+            weight_out,
+        )
+        .unwrap();
+        let user_out = match is_x {
+            1 => pool.user_y,
+            _ => pool.user_x,
+        };
+        assert_eq!(
+            token_amount(result.get_account(&user_out).unwrap()),
+            100_000_000 + expected
+        );
+    }
+
+    // Weights that do not add up to 10_000 leave the pool unusable.
+    let mut accounts = pool.accounts.clone();
+    let mut config_state = pool.config_state;
+    config_state.weight_y = 1_000u16.to_le_bytes();
+    accounts[9].1.data = bytemuck::bytes_of(&config_state).to_vec();
+    let instruction_data = SwapInstructionData {
+        is_x: 1,
+        amount: amount.to_le_bytes(),
+        min: 1u64.to_le_bytes(),
+        deadline: 0i64.to_le_bytes(),
+    };
+    mollusk.process_and_validate_instruction(
+        &pool.instruction(2, bytemuck::bytes_of(&instruction_data)),
+        &accounts,
+        &[Check::err(ProgramError::Custom(
+            CustomError::InvalidCurve as u32,
+        ))],
+    );
+}
+
+#[test]
+
+fn test_deposit_locks_minimum_liquidity() {
+    let mollusk = mollusk();
+
+    let pool = pool_fixture(&mollusk, 0, 0, 0, |_| {});
+
+    // sqrt(4_000_000 * 1_000_000) = 2_000_000 LP, of which MINIMUM_LIQUIDITY stay in the pool.
+    let instruction_data = DepositInstructionData {
+        amount: (2_000_000 - MINIMUM_LIQUIDITY).to_le_bytes(),
+        max_x: 4_000_000u64.to_le_bytes(),
+        max_y: 1_000_000u64.to_le_bytes(),
+    };
+
+    let result = mollusk.process_and_validate_instruction(
+        &pool.deposit_instruction(bytemuck::bytes_of(&instruction_data)),
+        &pool.accounts,
+        &[Check::success()],
+    );
+
+    assert_eq!(
+        token_amount(result.get_account(&pool.user_lp).unwrap()),
+        2_000_000 - MINIMUM_LIQUIDITY
+    );
+    assert_eq!(
+        token_amount(result.get_account(&pool.vault_lp).unwrap()),
+        MINIMUM_LIQUIDITY
+    );
+    assert_eq!(
+        token_amount(result.get_account(&pool.vault_x).unwrap()),
+        4_000_000
+    );
+    assert_eq!(
+        token_amount(result.get_account(&pool.vault_y).unwrap()),
+        1_000_000
+    );
+}
+
+#[test]
+
+fn test_deposit_donation_attack() {
+    let mollusk = mollusk();
+
+    // An attacker opened the pool with 1_001 of each token, receiving a single LP token on top of
+    // the locked ones, then donated 100_000_000 of each token to the vaults to inflate the price.
+    let donation: u64 = 100_000_000;
+    let mut pool = pool_fixture(
+        &mollusk,
+        1_001 + donation,
+        1_001 + donation,
+        1 + MINIMUM_LIQUIDITY,
+        |_| {},
+    );
+    pool.accounts[8].1 = pack_token_account(&mollusk, pool.mint_lp, pool.user, 0);
+    pool.accounts[12].1 =
+        pack_token_account(&mollusk, pool.mint_lp, pool.config, MINIMUM_LIQUIDITY);
+
+    // The victim deposits for 100 LP tokens.
+    let instruction_data = DepositInstructionData {
+        amount: 100u64.to_le_bytes(),
+        max_x: 10_000_000u64.to_le_bytes(),
+        max_y: 10_000_000u64.to_le_bytes(),
+    };
+    let result = mollusk.process_and_validate_instruction(
+        &pool.deposit_instruction(bytemuck::bytes_of(&instruction_data)),
+        &pool.accounts,
+        &[Check::success()],
+    );
+    let paid_x = 100_000_000 - token_amount(result.get_account(&pool.user_x).unwrap());
+    let paid_y = 100_000_000 - token_amount(result.get_account(&pool.user_y).unwrap());
+
+    // The victim gets back what they paid, less at most one unit of rounding.
+    let instruction_data = WithdrawInstructionData {
+        amount: 100u64.to_le_bytes(),
+        min_x: (paid_x - 1).to_le_bytes(),
+        min_y: (paid_y - 1).to_le_bytes(),
+    };
+    let result = mollusk.process_and_validate_instruction(
+        &pool.instruction(3, bytemuck::bytes_of(&instruction_data)),
+        &result.resulting_accounts,
+        &[Check::success()],
+    );
+
+    // Most of the donation went to the locked LP tokens, so the attack is a loss.
+    let (attacker_x, attacker_y) = withdraw_amounts(
+        token_amount(result.get_account(&pool.vault_x).unwrap()),
+        token_amount(result.get_account(&pool.vault_y).unwrap()),
+        1 + MINIMUM_LIQUIDITY,
+        1,
+    )
+    .unwrap();
+    assert!(attacker_x < (1_001 + donation) / 1_000);
+    assert!(attacker_y < (1_001 + donation) / 1_000);
+}
+
+#[test]
+
+fn test_flash_loan() {
+    let mut mollusk = mollusk();
+
+    let mut pool = pool_fixture(&mollusk, 100_000_000, 100_000_000, 100_000_000, |_| {});
+
+    let amount: u64 = 1_000_000;
+    let instruction_data = FlashLoanInstructionData {
+        amount_x: amount.to_le_bytes(),
+        amount_y: 0u64.to_le_bytes(),
+    };
+    let loan = pool.flash_loan_instruction(bytemuck::bytes_of(&instruction_data));
+    let repay = pool.flash_repay_instruction();
+    pool.accounts.push(instructions_sysvar(
+        &mollusk,
+        &[loan.clone(), repay.clone()],
+    ));
+
+    let result =
+        mollusk.process_and_validate_instruction(&loan, &pool.accounts, &[Check::success()]);
+    assert_eq!(
+        token_amount(result.get_account(&pool.user_x).unwrap()),
+        100_000_000 + amount
+    );
+    assert_eq!(
+        token_amount(result.get_account(&pool.vault_x).unwrap()),
+        100_000_000 - amount
+    );
+
+    // The fee is counted in the pool statistics, but the loan is not a swap.
+    let stats_state =
+        *bytemuck::from_bytes::<Stats>(&result.get_account(&pool.stats).unwrap().data);
+    assert_eq!(
+        u128::from_le_bytes(stats_state.fees_x),
+        (amount_before_fee(amount, 500).unwrap() - amount) as u128
+    );
+    assert_eq!(u128::from_le_bytes(stats_state.fees_y), 0);
+    assert_eq!(u64::from_le_bytes(stats_state.swap_count), 0);
+
+    // The pool is locked until the loan is repaid.
+    let swap_data = SwapInstructionData {
+        is_x: 0,
+        amount: 100u64.to_le_bytes(),
+        min: 1u64.to_le_bytes(),
+        deadline: 0i64.to_le_bytes(),
+    };
+    mollusk.process_and_validate_instruction(
+        &pool.instruction(2, bytemuck::bytes_of(&swap_data)),
+        &result.resulting_accounts,
+        &[Check::err(ProgramError::Custom(
+            CustomError::PoolLocked as u32,
+        ))],
+    );
+
+    // The borrower returns the loan plus the 5% fee.
+    let mut accounts = result.resulting_accounts;
+    let repaid = amount_before_fee(amount, 500).unwrap();
+    accounts[3].1 = pack_token_account(
+        &mollusk,
+        pool.mint_x,
+        pool.config,
+        100_000_000 - amount + repaid,
+    );
+
+    let result = mollusk.process_and_validate_instruction(&repay, &accounts, &[Check::success()]);
+    let config_state =
+        *bytemuck::from_bytes::<Config>(&result.get_account(&pool.config).unwrap().data);
+    assert_eq!(config_state.flash_loan, 0);
+
+    // In dynamic-fee mode the loan is charged the swap fee raised by the volatility up to now. Half
+    // a window ago the price was twice what it is now, a 50% move that has faded to 25%, a fifth of
+    // which is added to the 5% base fee.
+    let now = 1_000;
+    mollusk.sysvars.clock.unix_timestamp = now;
+    let volatile_pool = |mollusk: &Mollusk| {
+        let mut pool = pool_fixture(mollusk, 100_000_000, 100_000_000, 100_000_000, |config| {
+            config.dynamic_fee = 1;
+            config.max_dynamic_fee = 2_000u16.to_le_bytes();
+            config.volatility_factor = 2_000u16.to_le_bytes();
+            config.volatility = 2_500u32.to_le_bytes();
+            config.price_samples[0] = PriceSample {
+                timestamp: (now - VOLATILITY_WINDOW / 2).to_le_bytes(),
+                price: (2u128 << 64).to_le_bytes(),
+            };
+            config.protocol_fee = 1_000u16.to_le_bytes();
+        });
+        pool.accounts
+            .push(instructions_sysvar(mollusk, &[loan.clone(), repay.clone()]));
+        pool
+    };
+    let result = mollusk.process_and_validate_instruction(
+        &loan,
+        &volatile_pool(&mollusk).accounts,
+        &[Check::success()],
+    );
+    let config_state =
+        *bytemuck::from_bytes::<Config>(&result.get_account(&pool.config).unwrap().data);
+    let repaid = amount_before_fee(amount, 1_000).unwrap();
+    assert_eq!(
+        u64::from_le_bytes(config_state.flash_loan_x),
+        100_000_000 - amount + repaid
+    );
+    assert_eq!(
+        u64::from_le_bytes(config_state.protocol_fees_x),
+        (repaid as u128 * 1_000 / 10_000 * 1_000 / 10_000) as u64
+    );
+
+    // Once the move is a whole window old, the stored volatility no longer raises the fee.
+    mollusk.sysvars.clock.unix_timestamp = now + VOLATILITY_WINDOW / 2;
+    let result = mollusk.process_and_validate_instruction(
+        &loan,
+        &volatile_pool(&mollusk).accounts,
+        &[Check::success()],
+    );
+    let config_state =
+        *bytemuck::from_bytes::<Config>(&result.get_account(&pool.config).unwrap().data);
+    assert_eq!(u32::from_le_bytes(config_state.volatility), 0);
+    assert_eq!(
+        u64::from_le_bytes(config_state.flash_loan_x),
+        100_000_000 - amount + amount_before_fee(amount, 500).unwrap()
+    );
+}
+
+#[test]
+
+fn test_flash_loan_not_repaid() {
+    let mollusk = mollusk();
+
+    let mut pool = pool_fixture(&mollusk, 100_000_000, 100_000_000, 100_000_000, |_| {});
+
+    let amount: u64 = 1_000_000;
+    let instruction_data = FlashLoanInstructionData {
+        amount_x: amount.to_le_bytes(),
+        amount_y: amount.to_le_bytes(),
+    };
+    let loan = pool.flash_loan_instruction(bytemuck::bytes_of(&instruction_data));
+    let repay = pool.flash_repay_instruction();
+
+    // Without a repay instruction later in the transaction, nothing is lent.
+    let mut accounts = pool.accounts.clone();
+    accounts.push(instructions_sysvar(&mollusk, &[loan.clone()]));
+    mollusk.process_and_validate_instruction(
+        &loan,
+        &accounts,
+        &[Check::err(ProgramError::Custom(
+            CustomError::FlashLoanNotRepaid as u32,
+        ))],
+    );
+
+    // Returning the loan without the fee fails the repay instruction, and the transaction.
+    pool.accounts.push(instructions_sysvar(
+        &mollusk,
+        &[loan.clone(), repay.clone()],
+    ));
+    let result =
+        mollusk.process_and_validate_instruction(&loan, &pool.accounts, &[Check::success()]);
+    let mut accounts = result.resulting_accounts;
+    accounts[3].1 = pack_token_account(&mollusk, pool.mint_x, pool.config, 100_000_000);
+    accounts[4].1 = pack_token_account(&mollusk, pool.mint_y, pool.config, 100_000_000);
+
+    mollusk.process_and_validate_instruction(
+        &repay,
+        &accounts,
+        &[Check::err(ProgramError::Custom(
+            CustomError::FlashLoanNotRepaid as u32,
+        ))],
+    );
+}
+
+/// Adds a second pool, between mint_y and a new mint_z, next to the one of `pool`. Returns the
+/// hop accounts of the new pool and the user's mint_z token account.
+pub fn add_second_pool(mollusk: &Mollusk, pool: &mut PoolFixture) -> ([Pubkey; 6], Pubkey) {
+    let mint_z = Pubkey::new_from_array([0x0c; 32]);
+    let user_z = Pubkey::new_from_array([0x0f; 32]);
+
+    let seed: u64 = 2;
+    let (config, config_bump) = solana_sdk::pubkey::Pubkey::find_program_address(
+        &[(CONFIG_SEED.as_bytes()), &seed.to_le_bytes()],
+        &PROGRAM,
+    );
+    let mut config_state = Config {
+        seed: seed.to_le_bytes(),
+        authority: pool.config_state.authority,
+        mint_x: *pool.mint_y.as_array(),
+        mint_y: *mint_z.as_array(),
+        fee: 500u16.to_le_bytes(),
+        config_bump,
+        ..Default::default()
+    };
+    let [vault_y, vault_z, _, _] = pool_pdas(&config, &mut config_state);
+    let mut config_account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Config::LEN),
+        Config::LEN,
+        &PROGRAM.into(),
+    );
+    config_account.data = bytemuck::bytes_of(&config_state).to_vec();
+
+    pool.accounts.extend([
+        (config, config_account),
+        (mint_z, pack_mint(mollusk, None, 100_000_000)),
+        (
+            vault_y,
+            pack_token_account(mollusk, pool.mint_y, config, 100_000_000),
+        ),
+        (
+            vault_z,
+            pack_token_account(mollusk, mint_z, config, 100_000_000),
+        ),
+        (user_z, pack_token_account(mollusk, mint_z, pool.user, 0)),
+    ]);
+    let (stats, stats_account) = stats_fixture(mollusk, config);
+    pool.accounts.push((stats, stats_account));
+
+    (
+        [config, pool.mint_y, mint_z, vault_y, vault_z, stats],
+        user_z,
+    )
+}
+
+pub fn route_swap_instruction(
+    pool: &PoolFixture,
+    user_in: Pubkey,
+    user_out: Pubkey,
+    hops: &[[Pubkey; 6]],
+    data: &[u8],
+) -> Instruction {
+    let mut ser_instruction_data = vec![10];
+    ser_instruction_data.extend_from_slice(data);
+
+    let mut accounts = vec![
+        AccountMeta::new(pool.user, true),
+        AccountMeta::new(user_in, false),
+        AccountMeta::new(user_out, false),
+        AccountMeta::new_readonly(pool.token_program_x, false),
+        AccountMeta::new_readonly(pool.token_program_y, false),
+    ];
+    for [config, mint_x, mint_y, vault_x, vault_y, stats] in hops {
+        accounts.extend([
+            AccountMeta::new(*config, false),
+            AccountMeta::new_readonly(*mint_x, false),
+            AccountMeta::new_readonly(*mint_y, false),
+            AccountMeta::new(*vault_x, false),
+            AccountMeta::new(*vault_y, false),
+            AccountMeta::new(*stats, false),
+        ]);
+    }
+
+    Instruction::new_with_bytes(PROGRAM, &ser_instruction_data, accounts)
+}
+
+#[test]
+
+fn test_route_swap() {
+    let mollusk = mollusk();
+
+    let mut pool = pool_fixture(&mollusk, 100_000_000, 100_000_000, 100_000_000, |_| {});
+    let (second_hop, user_z) = add_second_pool(&mollusk, &mut pool);
+    let first_hop = [
+        pool.config,
+        pool.mint_x,
+        pool.mint_y,
+        pool.vault_x,
+        pool.vault_y,
+        pool.stats,
+    ];
+
+    // Swap x for y in the first pool, then y for z in the second one.
+    let amount: u64 = 1_000_000;
+    let amount_y = constant_product::swap_exact_in(
+        100_000_000,
+        100_000_000,
+        amount_after_fee(amount, 500).unwrap(),
+    )
+    .unwrap();
+    let amount_z = constant_product::swap_exact_in(
+        100_000_000,
+        100_000_000,
+        amount_after_fee(amount_y, 500).unwrap(),
+    )
+    .unwrap();
+
+    let instruction_data = RouteSwapInstructionData {
+        amount: amount.to_le_bytes(),
+        min: amount_z.to_le_bytes(),
+        deadline: 0i64.to_le_bytes(),
+    };
+    let result = mollusk.process_and_validate_instruction(
+        &route_swap_instruction(
+            &pool,
+            pool.user_x,
+            user_z,
+            &[first_hop, second_hop],
+            bytemuck::bytes_of(&instruction_data),
+        ),
+        &pool.accounts,
+        &[Check::success()],
+    );
+
+    assert_eq!(
+        token_amount(result.get_account(&pool.user_x).unwrap()),
+        100_000_000 - amount
+    );
+    assert_eq!(token_amount(result.get_account(&user_z).unwrap()), amount_z);
+    // The intermediate y moved from one pool to the other without touching the user.
+    assert_eq!(
+        token_amount(result.get_account(&pool.user_y).unwrap()),
+        100_000_000
+    );
+    assert_eq!(
+        token_amount(result.get_account(&second_hop[3]).unwrap()),
+        100_000_000 + amount_y
+    );
+
+    // Each hop is counted in the statistics of its pool.
+    for (stats, amount_in, amount_out) in [
+        (pool.stats, amount, amount_y),
+        (second_hop[5], amount_y, amount_z),
+    ] {
+        let stats_state = *bytemuck::from_bytes::<Stats>(&result.get_account(&stats).unwrap().data);
+        assert_eq!(u128::from_le_bytes(stats_state.volume_x), amount_in as u128);
+        assert_eq!(
+            u128::from_le_bytes(stats_state.volume_y),
+            amount_out as u128
+        );
+        assert_eq!(
+            u128::from_le_bytes(stats_state.fees_x),
+            (amount_in - amount_after_fee(amount_in, 500).unwrap()) as u128
+        );
+        assert_eq!(u64::from_le_bytes(stats_state.swap_count), 1);
+    }
+
+    // The final minimum output applies to the whole route.
+    let instruction_data = RouteSwapInstructionData {
+        amount: amount.to_le_bytes(),
+        min: (amount_z + 1).to_le_bytes(),
+        deadline: 0i64.to_le_bytes(),
+    };
+    mollusk.process_and_validate_instruction(
+        &route_swap_instruction(
+            &pool,
+            pool.user_x,
+            user_z,
+            &[first_hop, second_hop],
+            bytemuck::bytes_of(&instruction_data),
+        ),
+        &pool.accounts,
+        &[Check::err(ProgramError::Custom(
+            CustomError::SwapSlippageExceeded as u32,
+        ))],
+    );
+}
+
+#[test]
+
+fn test_route_swap_mint_mismatch() {
+    let mollusk = mollusk();
+
+    let mut pool = pool_fixture(&mollusk, 100_000_000, 100_000_000, 100_000_000, |_| {});
+    let (second_hop, user_z) = add_second_pool(&mollusk, &mut pool);
+    let first_hop = [
+        pool.config,
+        pool.mint_x,
+        pool.mint_y,
+        pool.vault_x,
+        pool.vault_y,
+        pool.stats,
+    ];
+
+    // Swapping y in the first pool gives out x, which the second pool does not trade.
+    let instruction_data = RouteSwapInstructionData {
+        amount: 1_000_000u64.to_le_bytes(),
+        min: 1u64.to_le_bytes(),
+        deadline: 0i64.to_le_bytes(),
+    };
+    mollusk.process_and_validate_instruction(
+        &route_swap_instruction(
+            &pool,
+            pool.user_y,
+            user_z,
+            &[first_hop, second_hop],
+            bytemuck::bytes_of(&instruction_data),
+        ),
+        &pool.accounts,
+        &[Check::err(ProgramError::Custom(
+            CustomError::RouteMintMismatch as u32,
+        ))],
+    );
+}
+
+#[test]
+
+fn test_initialize_factory_pool() {
+    let mollusk = mollusk();
+
+    let (system_program, system_account) = mollusk_svm::program::keyed_account_for_system_program();
+    let (token_program, token_account) = (
+        spl_token::ID,
+        program::create_program_account_loader_v3(&spl_token::ID),
+    );
+
+    let authority = Pubkey::new_from_array([0x01; 32]);
+    let mint_x = Pubkey::new_from_array([0x02; 32]);
+    let mint_y = Pubkey::new_from_array([0x03; 32]);
+
+    // Factory pools derive from their sorted mints and fee tier, not from a seed.
+    let fee: u16 = 30;
+    let (config, config_bump) = solana_sdk::pubkey::Pubkey::find_program_address(
+        &[
+            POOL_SEED.as_bytes(),
+            mint_x.as_ref(),
+            mint_y.as_ref(),
+            &fee.to_le_bytes(),
+        ],
+        &PROGRAM,
+    );
+    let (vault_x, vault_x_bump) = solana_sdk::pubkey::Pubkey::find_program_address(
+        &[VAULT_SEED.as_bytes(), config.as_ref(), mint_x.as_ref()],
+        &PROGRAM,
+    );
+    let (vault_y, vault_y_bump) = solana_sdk::pubkey::Pubkey::find_program_address(
+        &[VAULT_SEED.as_bytes(), config.as_ref(), mint_y.as_ref()],
+        &PROGRAM,
+    );
+    let (mint_lp, lp_bump) = solana_sdk::pubkey::Pubkey::find_program_address(
+        &[LP_SEED.as_bytes(), config.as_ref()],
+        &PROGRAM,
+    );
+    let (vault_lp, vault_lp_bump) = solana_sdk::pubkey::Pubkey::find_program_address(
+        &[VAULT_SEED.as_bytes(), config.as_ref(), mint_lp.as_ref()],
+        &PROGRAM,
+    );
+
+    // The registry already holds one pool.
+    let (registry, registry_bump) =
+        solana_sdk::pubkey::Pubkey::find_program_address(&[REGISTRY_SEED.as_bytes()], &PROGRAM);
+    let existing_pool = Pubkey::new_from_array([0x0a; 32]);
+    let mut registry_account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Registry::space(1)),
+        Registry::space(1),
+        &PROGRAM,
+    );
+    registry_account.data = [&1u64.to_le_bytes()[..], existing_pool.as_ref()].concat();
+    let (stats, stats_bump) = solana_sdk::pubkey::Pubkey::find_program_address(
+        &[STATS_SEED.as_bytes(), config.as_ref()],
+        &PROGRAM,
+    );
+
+    let instruction_data = InitializeInstructionData {
+        seed: 0u64.to_le_bytes(),
+        fee: fee.to_le_bytes(),
+        config_bump,
+        protocol_fee: 0u16.to_le_bytes(),
+        lp_bump,
+        vault_x_bump,
+        vault_y_bump,
+        curve_type: 0,
+        amp: 0u64.to_le_bytes(),
+        vault_lp_bump,
+        factory: 1,
+        registry_bump,
+        stats_bump,
+        weight_x: 0u16.to_le_bytes(),
+        weight_y: 0u16.to_le_bytes(),
+    };
+    let mut ser_instruction_data = vec![0];
+    ser_instruction_data.extend_from_slice(bytemuck::bytes_of(&instruction_data));
+
+    let instruction = Instruction::new_with_bytes(
+        PROGRAM,
+        &ser_instruction_data,
+        vec![
+            AccountMeta::new(authority, true),
+            AccountMeta::new_readonly(mint_x, false),
+            AccountMeta::new_readonly(mint_y, false),
+            AccountMeta::new(vault_x, false),
+            AccountMeta::new(vault_y, false),
+            AccountMeta::new(mint_lp, false),
+            AccountMeta::new(vault_lp, false),
+            AccountMeta::new(config, false),
+            AccountMeta::new(registry, false),
+            AccountMeta::new_readonly(system_program, false),
+            AccountMeta::new_readonly(token_program, false),
+            AccountMeta::new_readonly(token_program, false),
+            AccountMeta::new(stats, false),
+        ],
+    );
+    let accounts = vec![
+        (
+            authority,
+            Account::new(LAMPORTS_PER_SOL, 0, &system_program),
+        ),
+        (mint_x, pack_mint(&mollusk, None, 100_000_000)),
+        (mint_y, pack_mint(&mollusk, None, 100_000_000)),
+        (vault_x, Account::new(0, 0, &system_program)),
+        (vault_y, Account::new(0, 0, &system_program)),
+        (mint_lp, Account::new(0, 0, &system_program)),
+        (vault_lp, Account::new(0, 0, &system_program)),
+        (config, Account::new(0, 0, &system_program)),
+        (registry, registry_account),
+        (system_program, system_account),
+        (token_program, token_account),
+        (stats, Account::new(0, 0, &system_program)),
+    ];
+
+    let result = mollusk.process_and_validate_instruction(
+        &instruction,
+        &accounts,
+        &[
+            Check::success(),
+            Check::account(&config).owner(&PROGRAM).build(),
+            // The new pool is appended to the registry.
+            Check::account(&registry)
+                .data(
+                    &[
+                        &2u64.to_le_bytes()[..],
+                        existing_pool.as_ref(),
+                        config.as_ref(),
+                    ]
+                    .concat(),
+                )
+                .build(),
+        ],
+    );
+    let config_state = *bytemuck::from_bytes::<Config>(&result.get_account(&config).unwrap().data);
+    assert_eq!(config_state.version, CONFIG_VERSION);
+    assert_eq!(config_state.factory, 1);
+    assert_eq!(u16::from_le_bytes(config_state.fee_tier), fee);
+
+    // The same pair with the mints swapped is rejected rather than opening a second pool.
+    let mut swapped_instruction = instruction.clone();
+    swapped_instruction.accounts.swap(1, 2);
+    mollusk.process_and_validate_instruction(
+        &swapped_instruction,
+        &accounts,
+        &[Check::err(ProgramError::Custom(
+            CustomError::InvalidMintOrder as u32,
+        ))],
+    );
+
+    // Neither is a second pool for the pair and tier at another bump than the canonical one.
+    let (other_config, other_bump) = (0..config_bump)
+        .rev()
+        .find_map(|bump| {
+            solana_sdk::pubkey::Pubkey::create_program_address(
+                &[
+                    POOL_SEED.as_bytes(),
+                    mint_x.as_ref(),
+                    mint_y.as_ref(),
+                    &fee.to_le_bytes(),
+                    &[bump],
+                ],
+                &PROGRAM,
+            )
+            .ok()
+            .map(|config| (config, bump))
+        })
+        .unwrap();
+    let mut other_instruction = instruction.clone();
+    other_instruction.data = [
+        &[0][..],
+        bytemuck::bytes_of(&InitializeInstructionData {
+            config_bump: other_bump,
+            ..instruction_data
+        }),
+    ]
+    .concat();
+    other_instruction.accounts[7].pubkey = other_config;
+    let mut other_accounts = accounts.clone();
+    other_accounts[7].0 = other_config;
+    mollusk.process_and_validate_instruction(
+        &other_instruction,
+        &other_accounts,
+        &[Check::err(ProgramError::InvalidSeeds)],
+    );
+
+    // Anyone can send lamports to the pool addresses, and to the registry before the first pool,
+    // which does not keep the pool from opening.
+    let mut accounts = accounts;
+    for index in [3, 4, 5, 6, 7, 8, 11] {
+        accounts[index].1 = Account::new(1_000, 0, &system_program);
+    }
+    mollusk.process_and_validate_instruction(
+        &instruction,
+        &accounts,
+        &[
+            Check::success(),
+            Check::account(&config)
+                .owner(&PROGRAM)
+                .lamports(mollusk.sysvars.rent.minimum_balance(Config::LEN))
+                .build(),
+            Check::account(&vault_x).owner(&token_program).build(),
+            Check::account(&mint_lp).owner(&token_program).build(),
+            Check::account(&stats).owner(&PROGRAM).build(),
+            Check::account(&registry)
+                .owner(&PROGRAM)
+                .data(&[&1u64.to_le_bytes()[..], config.as_ref()].concat())
+                .build(),
+        ],
+    );
+}
+
+#[test]
+
+fn test_quote() {
+    let mollusk = mollusk();
+
+    let pool = pool_fixture(&mollusk, 100_000_000, 100_000_000, 100_000_000, |_| {});
+    let quote = |data: &QuoteInstructionData| {
+        let mut ser_instruction_data = vec![11];
+        ser_instruction_data.extend_from_slice(bytemuck::bytes_of(data));
+        Instruction::new_with_bytes(
+            PROGRAM,
+            &ser_instruction_data,
+            vec![
+                AccountMeta::new_readonly(pool.mint_x, false),
+                AccountMeta::new_readonly(pool.mint_y, false),
+                AccountMeta::new_readonly(pool.vault_x, false),
+                AccountMeta::new_readonly(pool.vault_y, false),
+                AccountMeta::new_readonly(pool.mint_lp, false),
+                AccountMeta::new_readonly(pool.config, false),
+            ],
+        )
+    };
+
+    // Swap 1_000_000 x in: 5% goes to fees, the rest trades below the 1:1 spot price.
+    let amount: u64 = 1_000_000;
+    let amount_in_after_fee = amount_after_fee(amount, 500).unwrap();
+    let amount_out =
+        constant_product::swap_exact_in(100_000_000, 100_000_000, amount_in_after_fee).unwrap();
+    let expected = QuoteResult {
+        amount_x: amount.to_le_bytes(),
+        amount_y: amount_out.to_le_bytes(),
+        amount_lp: 0u64.to_le_bytes(),
+        fee: (amount - amount_in_after_fee).to_le_bytes(),
+        price_impact: (((amount_in_after_fee - amount_out) * 10_000 / amount_in_after_fee) as u16)
+            .to_le_bytes(),
+    };
+    mollusk.process_and_validate_instruction(
+        &quote(&QuoteInstructionData {
+            kind: 0,
+            is_x: 1,
+            amount: amount.to_le_bytes(),
+        }),
+        &pool.accounts,
+        &[
+            Check::success(),
+            Check::return_data(bytemuck::bytes_of(&expected)),
+            // The pool is left untouched.
+            Check::account(&pool.config)
+                .data(bytemuck::bytes_of(&pool.config_state))
+                .build(),
+        ],
+    );
+
+    // Withdrawing 1% of the LP supply redeems 1% of each reserve.
+    let expected = QuoteResult {
+        amount_x: 1_000_000u64.to_le_bytes(),
+        amount_y: 1_000_000u64.to_le_bytes(),
+        amount_lp: 1_000_000u64.to_le_bytes(),
+        ..Zeroable::zeroed()
+    };
+    mollusk.process_and_validate_instruction(
+        &quote(&QuoteInstructionData {
+            kind: 3,
+            is_x: 0,
+            amount: 1_000_000u64.to_le_bytes(),
+        }),
+        &pool.accounts,
+        &[
+            Check::success(),
+            Check::return_data(bytemuck::bytes_of(&expected)),
+        ],
+    );
+
+    // In dynamic-fee mode the fee is quoted with the volatility a swap would measure. Half a
+    // window ago the price was twice what it is now, which raises the fee to 10%.
+    let mut mollusk = mollusk;
+    let now = 1_000;
+    mollusk.sysvars.clock.unix_timestamp = now;
+    let volatile_pool = pool_fixture(&mollusk, 100_000_000, 100_000_000, 100_000_000, |config| {
+        config.dynamic_fee = 1;
+        config.max_dynamic_fee = 2_000u16.to_le_bytes();
+        config.volatility_factor = 2_000u16.to_le_bytes();
+        config.price_samples[0] = PriceSample {
+            timestamp: (now - VOLATILITY_WINDOW / 2).to_le_bytes(),
+            price: (2u128 << 64).to_le_bytes(),
+        };
+    });
+    let result = mollusk.process_and_validate_instruction(
+        &quote(&QuoteInstructionData {
+            kind: 0,
+            is_x: 1,
+            amount: amount.to_le_bytes(),
+        }),
+        &volatile_pool.accounts,
+        &[
+            Check::success(),
+            Check::account(&volatile_pool.config)
+                .data(bytemuck::bytes_of(&volatile_pool.config_state))
+                .build(),
+        ],
+    );
+    let quote_result = *bytemuck::from_bytes::<QuoteResult>(&result.return_data);
+    assert_eq!(
+        u64::from_le_bytes(quote_result.fee),
+        amount - amount_after_fee(amount, 1_000).unwrap()
+    );
+}
+
+#[test]
+
+fn test_deposit_single() {
+    let mollusk = mollusk();
+
+    let pool = pool_fixture(&mollusk, 1_000_000, 1_000_000, 1_000_000, |_| {});
+    let deposit_single = |data: &DepositSingleInstructionData| {
+        let mut ser_instruction_data = vec![12];
+        ser_instruction_data.extend_from_slice(bytemuck::bytes_of(data));
+        Instruction::new_with_bytes(
+            PROGRAM,
+            &ser_instruction_data,
+            vec![
+                AccountMeta::new(pool.user, true),
+                AccountMeta::new_readonly(pool.mint_x, false),
+                AccountMeta::new_readonly(pool.mint_y, false),
+                AccountMeta::new(pool.vault_x, false),
+                AccountMeta::new(pool.vault_y, false),
+                AccountMeta::new(pool.user_x, false),
+                AccountMeta::new(pool.mint_lp, false),
+                AccountMeta::new(pool.user_lp, false),
+                AccountMeta::new(pool.config, false),
+                AccountMeta::new_readonly(pool.accounts[10].0, false),
+                AccountMeta::new_readonly(pool.token_program_x, false),
+                AccountMeta::new_readonly(pool.token_program_y, false),
+                AccountMeta::new(pool.stats, false),
+            ],
+        )
+    };
+
+    // The best split of the deposit between the swap and the deposit, by brute force.
+    let amount: u64 = 10_000;
+    let lp = (1..=amount)
+        .map(|swap_in| {
+            // Tiny swaps round down to nothing.
+            let swap_out = amount_after_fee(swap_in, 500)
+                .and_then(|amount_in| {
+                    constant_product::swap_exact_in(1_000_000, 1_000_000, amount_in)
+                })
+                .unwrap_or(0);
+            let lp_x = (amount - swap_in) * 1_000_000 / (1_000_000 + swap_in);
+            let lp_y = swap_out * 1_000_000 / (1_000_000 - swap_out);
+            lp_x.min(lp_y)
+        })
+        .max()
+        .unwrap();
+
+    let instruction_data = DepositSingleInstructionData {
+        is_x: 1,
+        amount: amount.to_le_bytes(),
+        min: lp.to_le_bytes(),
+    };
+    let result = mollusk.process_and_validate_instruction(
+        &deposit_single(&instruction_data),
+        &pool.accounts,
+        &[
+            Check::success(),
+            Check::account(&pool.user_lp)
+                .data(&pack_token_account(&mollusk, pool.mint_lp, pool.user, 1_000_000 + lp).data)
+                .build(),
+            // The swap output stays in the pool.
+            Check::account(&pool.vault_y)
+                .data(&pack_token_account(&mollusk, pool.mint_y, pool.config, 1_000_000).data)
+                .build(),
+        ],
+    );
+
+    // The whole amount goes in, and what the split leaves over stays in the pool.
+    assert_eq!(
+        token_amount(result.get_account(&pool.user_x).unwrap()),
+        100_000_000 - amount
+    );
+    assert_eq!(
+        token_amount(result.get_account(&pool.vault_x).unwrap()),
+        1_000_000 + amount
+    );
+
+    // Slippage protection.
+    let instruction_data = DepositSingleInstructionData {
+        min: (lp + 1).to_le_bytes(),
+        ..instruction_data
+    };
+    mollusk.process_and_validate_instruction(
+        &deposit_single(&instruction_data),
+        &pool.accounts,
+        &[Check::err(ProgramError::Custom(
+            CustomError::DepositSlippageExceeded as u32,
+        ))],
+    );
+}
+
+#[test]
+
+fn test_swap_rejects_unknown_token_program() {
+    let mollusk = mollusk();
+
+    let pool = pool_fixture(&mollusk, 100_000_000, 100_000_000, 100_000_000, |_| {});
+
+    let instruction_data = SwapInstructionData {
+        is_x: 1,
+        amount: 1_000_000u64.to_le_bytes(),
+        min: 1u64.to_le_bytes(),
+        deadline: 0i64.to_le_bytes(),
+    };
+    // Pass the system program where the token program goes.
+    let mut instruction = pool.instruction(2, bytemuck::bytes_of(&instruction_data));
+    instruction.accounts[11].pubkey = pool.accounts[10].0;
+
+    mollusk.process_and_validate_instruction(
+        &instruction,
+        &pool.accounts,
+        &[Check::err(ProgramError::IncorrectProgramId)],
+    );
+}
+
+#[test]
+
+fn test_initialize_mixed_token_programs() {
+    let mollusk = mollusk_token_2022();
+
+    let (system_program, system_account) = mollusk_svm::program::keyed_account_for_system_program();
+
+    // mint_x is a Token-2022 mint with a transfer fee, and mint_y a token program mint.
+    let authority = Pubkey::new_from_array([0x01; 32]);
+    let mint_x = Pubkey::new_from_array([0x02; 32]);
+    let mint_y = Pubkey::new_from_array([0x03; 32]);
+
+    let seed: u64 = 1;
+    let (config, config_bump) = solana_sdk::pubkey::Pubkey::find_program_address(
+        &[(CONFIG_SEED.as_bytes()), &seed.to_le_bytes()],
+        &PROGRAM,
+    );
+    let mut config_state = Config {
+        mint_x: *mint_x.as_array(),
+        mint_y: *mint_y.as_array(),
+        ..Default::default()
+    };
+    let [vault_x, vault_y, mint_lp, vault_lp] = pool_pdas(&config, &mut config_state);
+    let (registry, registry_bump) =
+        solana_sdk::pubkey::Pubkey::find_program_address(&[REGISTRY_SEED.as_bytes()], &PROGRAM);
+    let (stats, stats_bump) = solana_sdk::pubkey::Pubkey::find_program_address(
+        &[STATS_SEED.as_bytes(), config.as_ref()],
+        &PROGRAM,
+    );
+
+    let instruction_data = InitializeInstructionData {
+        seed: seed.to_le_bytes(),
+        fee: 500u16.to_le_bytes(),
+        config_bump,
+        protocol_fee: 0u16.to_le_bytes(),
+        lp_bump: config_state.lp_bump,
+        vault_x_bump: config_state.vault_x_bump,
+        vault_y_bump: config_state.vault_y_bump,
+        curve_type: 0,
+        amp: 0u64.to_le_bytes(),
+        vault_lp_bump: config_state.vault_lp_bump,
+        factory: 0,
+        registry_bump,
+        stats_bump,
+        weight_x: 0u16.to_le_bytes(),
+        weight_y: 0u16.to_le_bytes(),
+    };
+    let mut ser_instruction_data = vec![0];
+    ser_instruction_data.extend_from_slice(bytemuck::bytes_of(&instruction_data));
+
+    let instruction = Instruction::new_with_bytes(
+        PROGRAM,
+        &ser_instruction_data,
+        vec![
+            AccountMeta::new(authority, true),
+            AccountMeta::new_readonly(mint_x, false),
+            AccountMeta::new_readonly(mint_y, false),
+            AccountMeta::new(vault_x, false),
+            AccountMeta::new(vault_y, false),
+            AccountMeta::new(mint_lp, false),
+            AccountMeta::new(vault_lp, false),
+            AccountMeta::new(config, false),
+            AccountMeta::new(registry, false),
+            AccountMeta::new_readonly(system_program, false),
+            AccountMeta::new_readonly(TOKEN_2022, false),
+            AccountMeta::new_readonly(spl_token::ID, false),
+            AccountMeta::new(stats, false),
+        ],
+    );
+    let empty = Account::new(0, 0, &system_program);
+    let accounts = vec![
+        (
+            authority,
+            Account::new(LAMPORTS_PER_SOL, 0, &system_program),
+        ),
+        (
+            mint_x,
+            pack_transfer_fee_mint(&mollusk, 100_000_000, 100, u64::MAX),
+        ),
+        (mint_y, pack_mint(&mollusk, None, 100_000_000)),
+        (vault_x, empty.clone()),
+        (vault_y, empty.clone()),
+        (mint_lp, empty.clone()),
+        (vault_lp, empty.clone()),
+        (config, empty.clone()),
+        (registry, empty.clone()),
+        (system_program, system_account),
+        (
+            TOKEN_2022,
+            program::create_program_account_loader_v3(&TOKEN_2022),
+        ),
+        (
+            spl_token::ID,
+            program::create_program_account_loader_v3(&spl_token::ID),
+        ),
+        (stats, empty),
+    ];
+
+    // Each vault belongs to the token program of its mint, and the LP mint and its vault to the
+    // one of mint_x.
+    let result = mollusk.process_and_validate_instruction(
+        &instruction,
+        &accounts,
+        &[
+            Check::success(),
+            Check::account(&vault_x).owner(&TOKEN_2022).build(),
+            Check::account(&vault_y).owner(&spl_token::ID).build(),
+            Check::account(&mint_lp).owner(&TOKEN_2022).build(),
+            Check::account(&vault_lp).owner(&TOKEN_2022).build(),
+        ],
+    );
+    // The vault of the transfer-fee mint has room for the fees it withholds.
+    assert_eq!(
+        result.get_account(&vault_x).unwrap().data.len(),
+        spl_token::state::Account::LEN + 1 + 4 + 8
+    );
+    assert_eq!(
+        result.get_account(&vault_y).unwrap().data.len(),
+        spl_token::state::Account::LEN
+    );
+
+    // The token programs must match the mints.
+    let mut swapped_instruction = instruction.clone();
+    swapped_instruction.accounts.swap(10, 11);
+    mollusk.process_and_validate_instruction(
+        &swapped_instruction,
+        &accounts,
+        &[Check::err(ProgramError::InvalidAccountOwner)],
+    );
+
+    // Mints that are non-transferable, have a permanent delegate or a transfer hook are refused.
+    for (extension_type, len) in [(9u16, 0u16), (12, 32), (14, 64)] {
+        let mut accounts = accounts.clone();
+        let mint_x_account = &mut accounts[1].1;
+        mint_x_account
+            .data
+            .extend_from_slice(&extension_type.to_le_bytes());
+        mint_x_account.data.extend_from_slice(&len.to_le_bytes());
+        mint_x_account.data.extend(vec![0; len as usize]);
+        mollusk.process_and_validate_instruction(
+            &instruction,
+            &accounts,
+            &[Check::err(ProgramError::Custom(
+                CustomError::UnsupportedMintExtension as u32,
+            ))],
+        );
+    }
+}
+
+#[test]
+
+fn test_swap_transfer_fee_mint() {
+    let mollusk = mollusk_token_2022();
+
+    // mint_y is a Token-2022 mint taking 1% of every transfer, next to a token program mint_x.
+    let mut pool = pool_fixture(&mollusk, 100_000_000, 100_000_000, 100_000_000, |_| {});
+    pool.accounts[2].1 = pack_transfer_fee_mint(&mollusk, 100_000_000, 100, u64::MAX);
+    pool.accounts[4].1 =
+        pack_transfer_fee_token_account(&mollusk, pool.mint_y, pool.config, 100_000_000);
+    pool.accounts[6].1 =
+        pack_transfer_fee_token_account(&mollusk, pool.mint_y, pool.user, 100_000_000);
+    pool.token_program_y = TOKEN_2022;
+    pool.accounts.push((
+        TOKEN_2022,
+        program::create_program_account_loader_v3(&TOKEN_2022),
+    ));
+
+    // The swap is priced on what reaches the vault, net of the transfer fee.
+    let amount: u64 = 1_000_000;
+    let received = amount - amount / 100;
+    let amount_out = constant_product::swap_exact_in(
+        100_000_000,
+        100_000_000,
+        amount_after_fee(received, 500).unwrap(),
+    )
+    .unwrap();
+    let instruction_data = SwapInstructionData {
+        is_x: 0,
+        amount: amount.to_le_bytes(),
+        min: amount_out.to_le_bytes(),
+        deadline: 0i64.to_le_bytes(),
+    };
+    let result = mollusk.process_and_validate_instruction(
+        &pool.instruction(2, bytemuck::bytes_of(&instruction_data)),
+        &pool.accounts,
+        &[Check::success()],
+    );
+    assert_eq!(
+        token_amount(result.get_account(&pool.user_y).unwrap()),
+        100_000_000 - amount
+    );
+    assert_eq!(
+        token_amount(result.get_account(&pool.vault_y).unwrap()),
+        100_000_000 + received
+    );
+    assert_eq!(
+        token_amount(result.get_account(&pool.user_x).unwrap()),
+        100_000_000 + amount_out
+    );
+
+    // The minimum output is checked against what the user receives, net of the transfer fee.
+    let amount_out = constant_product::swap_exact_in(
+        100_000_000,
+        100_000_000,
+        amount_after_fee(amount, 500).unwrap(),
+    )
+    .unwrap();
+    let received = amount_out - (amount_out * 100).div_ceil(10_000);
+    let mut instruction_data = SwapInstructionData {
+        is_x: 1,
+        amount: amount.to_le_bytes(),
+        min: amount_out.to_le_bytes(),
+        deadline: 0i64.to_le_bytes(),
+    };
+    mollusk.process_and_validate_instruction(
+        &pool.instruction(2, bytemuck::bytes_of(&instruction_data)),
+        &pool.accounts,
+        &[Check::err(ProgramError::Custom(
+            CustomError::SwapSlippageExceeded as u32,
+        ))],
+    );
+    instruction_data.min = received.to_le_bytes();
+    let result = mollusk.process_and_validate_instruction(
+        &pool.instruction(2, bytemuck::bytes_of(&instruction_data)),
+        &pool.accounts,
+        &[Check::success()],
+    );
+    assert_eq!(
+        token_amount(result.get_account(&pool.user_y).unwrap()),
+        100_000_000 + received
+    );
+
+    // Exact output swaps are priced before the input is sent, so the fee leaves them short.
+    let instruction_data = SwapExactOutInstructionData {
+        is_x: 0,
+        amount: amount_out.to_le_bytes(),
+        max: amount.to_le_bytes(),
+        deadline: 0i64.to_le_bytes(),
+    };
+    mollusk.process_and_validate_instruction(
+        &pool.instruction(6, bytemuck::bytes_of(&instruction_data)),
+        &pool.accounts,
+        &[Check::err(ProgramError::Custom(
+            CustomError::InvalidSwap as u32,
+        ))],
+    );
+}
+
+/// Sets up a config owned by `authority`, for the authority instructions.
+pub fn authority_fixture(mollusk: &Mollusk, authority: Pubkey) -> (Pubkey, Config, Account) {
+    let seed: u64 = 1;
+    let (config, config_bump) = solana_sdk::pubkey::Pubkey::find_program_address(
+        &[(CONFIG_SEED.as_bytes()), &seed.to_le_bytes()],
+        &PROGRAM,
+    );
+    let mut config_account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Config::LEN),
+        Config::LEN,
+        &PROGRAM.into(),
+    );
+    let config_state = Config {
+        seed: seed.to_le_bytes(),
+        authority: *authority.as_array(),
+        mint_x: [0x02; 32],
+        mint_y: [0x03; 32],
+        fee: 500u16.to_le_bytes(),
+        config_bump,
+        ..Default::default()
+    };
+    config_account.data = bytemuck::bytes_of(&config_state).to_vec();
+    (config, config_state, config_account)
+}
+
+pub fn authority_instruction(
+    discriminator: u8,
+    signer: Pubkey,
+    config: Pubkey,
+    data: &[u8],
+) -> Instruction {
+    let mut ser_instruction_data = vec![discriminator];
+    ser_instruction_data.extend_from_slice(data);
+    Instruction::new_with_bytes(
+        PROGRAM,
+        &ser_instruction_data,
+        vec![
+            AccountMeta::new(signer, true),
+            AccountMeta::new(config, false),
+        ],
+    )
+}
+
+#[test]
+
+fn test_transfer_authority() {
+    let mollusk = mollusk();
+
+    let authority = Pubkey::new_from_array([0x01; 32]);
+    let new_authority = Pubkey::new_from_array([0x0a; 32]);
+    let impostor = Pubkey::new_from_array([0x09; 32]);
+    let signer_account = Account::new(1 * LAMPORTS_PER_SOL, 0, &Pubkey::default());
+    let (config, config_state, config_account) = authority_fixture(&mollusk, authority);
+
+    // Propose the new authority, which leaves the current one in charge.
+    let instruction_data = ProposeAuthorityInstructionData {
+        new_authority: *new_authority.as_array(),
+    };
+    let mut proposed_config_state = config_state;
+    proposed_config_state.pending_authority = *new_authority.as_array();
+
+    let result = mollusk.process_and_validate_instruction(
+        &authority_instruction(13, authority, config, bytemuck::bytes_of(&instruction_data)),
+        &vec![
+            (authority, signer_account.clone()),
+            (config, config_account),
+        ],
+        &[
+            Check::success(),
+            Check::account(&config)
+                .data(bytemuck::bytes_of(&proposed_config_state))
+                .build(),
+        ],
+    );
+    let config_account = result.get_account(&config).unwrap().clone();
+
+    // Only the proposed authority can accept.
+    mollusk.process_and_validate_instruction(
+        &authority_instruction(14, impostor, config, &[]),
+        &vec![
+            (impostor, signer_account.clone()),
+            (config, config_account.clone()),
+        ],
+        &[Check::err(ProgramError::IncorrectAuthority)],
+    );
+
+    let mut accepted_config_state = config_state;
+    accepted_config_state.authority = *new_authority.as_array();
+
+    let result = mollusk.process_and_validate_instruction(
+        &authority_instruction(14, new_authority, config, &[]),
+        &vec![
+            (new_authority, signer_account.clone()),
+            (config, config_account),
+        ],
+        &[
+            Check::success(),
+            Check::account(&config)
+                .data(bytemuck::bytes_of(&accepted_config_state))
+                .build(),
+        ],
+    );
+    let config_account = result.get_account(&config).unwrap().clone();
+
+    // The previous authority has no say anymore.
+    let instruction_data = UpdateConfigInstructionData {
+        fee: 30u16.to_le_bytes(),
+        locked: 0,
+        referral_fee: 0u16.to_le_bytes(),
+    };
+    mollusk.process_and_validate_instruction(
+        &authority_instruction(5, authority, config, bytemuck::bytes_of(&instruction_data)),
+        &vec![(authority, signer_account), (config, config_account)],
+        &[Check::err(ProgramError::IncorrectAuthority)],
+    );
+}
+
+#[test]
+
+fn test_renounce_authority() {
+    let mollusk = mollusk();
+
+    let authority = Pubkey::new_from_array([0x01; 32]);
+    let signer_account = Account::new(1 * LAMPORTS_PER_SOL, 0, &Pubkey::default());
+    let (config, mut config_state, mut config_account) = authority_fixture(&mollusk, authority);
+    config_state.protocol_fee = 1_000u16.to_le_bytes();
+    config_state.protocol_fees_x = 200u64.to_le_bytes();
+    config_state.protocol_fees_y = 300u64.to_le_bytes();
+    config_account.data = bytemuck::bytes_of(&config_state).to_vec();
+
+    // The protocol fees can no longer be claimed, so they are released to the pool and no more
+    // accrue.
+    let mut renounced_config_state = config_state;
+    renounced_config_state.authority = [0; 32];
+    renounced_config_state.protocol_fee = 0u16.to_le_bytes();
+    renounced_config_state.protocol_fees_x = 0u64.to_le_bytes();
+    renounced_config_state.protocol_fees_y = 0u64.to_le_bytes();
+
+    // A locked pool cannot be renounced, as it would stay locked for good.
+    let mut locked_config_state = config_state;
+    locked_config_state.locked = 1;
+    let mut locked_config_account = config_account.clone();
+    locked_config_account.data = bytemuck::bytes_of(&locked_config_state).to_vec();
+    mollusk.process_and_validate_instruction(
+        &authority_instruction(15, authority, config, &[]),
+        &vec![
+            (authority, signer_account.clone()),
+            (config, locked_config_account),
+        ],
+        &[Check::err(ProgramError::Custom(
+            CustomError::PoolLocked as u32,
+        ))],
+    );
+
+    let result = mollusk.process_and_validate_instruction(
+        &authority_instruction(15, authority, config, &[]),
+        &vec![
+            (authority, signer_account.clone()),
+            (config, config_account),
+        ],
+        &[
+            Check::success(),
+            Check::account(&config)
+                .data(bytemuck::bytes_of(&renounced_config_state))
+                .build(),
+        ],
+    );
+    let config_account = result.get_account(&config).unwrap().clone();
+
+    // The pool cannot be updated anymore.
+    let instruction_data = UpdateConfigInstructionData {
+        fee: 30u16.to_le_bytes(),
+        locked: 0,
+        referral_fee: 0u16.to_le_bytes(),
+    };
+    mollusk.process_and_validate_instruction(
+        &authority_instruction(5, authority, config, bytemuck::bytes_of(&instruction_data)),
+        &vec![(authority, signer_account), (config, config_account)],
+        &[Check::err(ProgramError::Custom(
+            CustomError::AuthorityRenounced as u32,
+        ))],
+    );
+}
+
+#[test]
+
+fn test_close_pool() {
+    let mollusk = mollusk();
+
+    // Only the locked minimum liquidity is left, with the reserves behind it and unclaimed
+    // protocol fees in the vaults.
+    let user = Pubkey::new_from_array([0x01; 32]);
+    let mut pool = pool_fixture(&mollusk, 1_000, 1_000, MINIMUM_LIQUIDITY, |config| {
+        config.authority = *user.as_array();
+        config.protocol_fees_x = 200u64.to_le_bytes();
+    });
+    pool.accounts[8].1 = pack_token_account(&mollusk, pool.mint_lp, pool.user, 0);
+    pool.accounts[12].1 =
+        pack_token_account(&mollusk, pool.mint_lp, pool.config, MINIMUM_LIQUIDITY);
+
+    let other_pool = Pubkey::new_from_array([0x0c; 32]);
+    let (registry, registry_bump) =
+        solana_sdk::pubkey::Pubkey::find_program_address(&[REGISTRY_SEED.as_bytes()], &PROGRAM);
+    let mut registry_account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Registry::space(2)),
+        Registry::space(2),
+        &PROGRAM,
+    );
+    registry_account.data = [
+        &2u64.to_le_bytes()[..],
+        pool.config.as_ref(),
+        other_pool.as_ref(),
+    ]
+    .concat();
+
+    let instruction_data = ClosePoolInstructionData { registry_bump };
+    let mut ser_instruction_data = vec![16];
+    ser_instruction_data.extend_from_slice(bytemuck::bytes_of(&instruction_data));
+    let instruction = Instruction::new_with_bytes(
+        PROGRAM,
+        &ser_instruction_data,
+        vec![
+            AccountMeta::new(pool.user, true),
+            AccountMeta::new_readonly(pool.mint_x, false),
+            AccountMeta::new_readonly(pool.mint_y, false),
+            AccountMeta::new(pool.vault_x, false),
+            AccountMeta::new(pool.vault_y, false),
+            AccountMeta::new(pool.user_x, false),
+            AccountMeta::new(pool.user_y, false),
+            AccountMeta::new(pool.mint_lp, false),
+            AccountMeta::new(pool.vault_lp, false),
+            AccountMeta::new(pool.config, false),
+            AccountMeta::new(registry, false),
+            AccountMeta::new_readonly(pool.token_program_x, false),
+            AccountMeta::new_readonly(pool.token_program_y, false),
+            AccountMeta::new(pool.stats, false),
+        ],
+    );
+    let mut accounts = pool.accounts.clone();
+    accounts.push((registry, registry_account));
+
+    // The authority gets back the rent of the config, the stats, the vaults and the registry entry.
+    let token_account_rent = mollusk
+        .sysvars
+        .rent
+        .minimum_balance(spl_token::state::Account::LEN);
+    let reclaimed = mollusk.sysvars.rent.minimum_balance(Config::LEN)
+        + mollusk.sysvars.rent.minimum_balance(Stats::LEN)
+        + 3 * token_account_rent
+        + mollusk.sysvars.rent.minimum_balance(Registry::space(2))
+        - mollusk.sysvars.rent.minimum_balance(Registry::space(1));
+
+    let result = mollusk.process_and_validate_instruction(
+        &instruction,
+        &accounts,
+        &[
+            Check::success(),
+            Check::account(&pool.config).lamports(0).data(&[]).build(),
+            Check::account(&pool.stats).lamports(0).data(&[]).build(),
+            Check::account(&pool.vault_x).lamports(0).build(),
+            Check::account(&pool.vault_y).lamports(0).build(),
+            Check::account(&pool.vault_lp).lamports(0).build(),
+            Check::account(&pool.user)
+                .lamports(LAMPORTS_PER_SOL + reclaimed)
+                .build(),
+            // The other pools stay listed, in order.
+            Check::account(&registry)
+                .data(&[&1u64.to_le_bytes()[..], other_pool.as_ref()].concat())
+                .build(),
+        ],
+    );
+    // The reserves behind the locked liquidity and the protocol fees are swept to the treasuries.
+    assert_eq!(
+        token_amount(result.get_account(&pool.user_x).unwrap()),
+        100_000_000 + 800 + 200
+    );
+    assert_eq!(
+        token_amount(result.get_account(&pool.user_y).unwrap()),
+        100_000_000 + 1_000
+    );
+
+    // The pool can be reopened, taking over the LP mint it left behind. The accounts it closed
+    // are gone, as the runtime purges accounts without lamports.
+    let (system_program, system_account) = mollusk_svm::program::keyed_account_for_system_program();
+    let (_, stats_bump) = solana_sdk::pubkey::Pubkey::find_program_address(
+        &[STATS_SEED.as_bytes(), pool.config.as_ref()],
+        &PROGRAM,
+    );
+    let instruction_data = InitializeInstructionData {
+        seed: pool.config_state.seed,
+        fee: pool.config_state.fee,
+        config_bump: pool.config_state.config_bump,
+        protocol_fee: 0u16.to_le_bytes(),
+        lp_bump: pool.config_state.lp_bump,
+        vault_x_bump: pool.config_state.vault_x_bump,
+        vault_y_bump: pool.config_state.vault_y_bump,
+        curve_type: 0,
+        amp: 0u64.to_le_bytes(),
+        vault_lp_bump: pool.config_state.vault_lp_bump,
+        factory: 0,
+        registry_bump,
+        stats_bump,
+        weight_x: 0u16.to_le_bytes(),
+        weight_y: 0u16.to_le_bytes(),
+    };
+    let mut ser_instruction_data = vec![0];
+    ser_instruction_data.extend_from_slice(bytemuck::bytes_of(&instruction_data));
+    let reopen = Instruction::new_with_bytes(
+        PROGRAM,
+        &ser_instruction_data,
+        vec![
+            AccountMeta::new(pool.user, true),
+            AccountMeta::new_readonly(pool.mint_x, false),
+            AccountMeta::new_readonly(pool.mint_y, false),
+            AccountMeta::new(pool.vault_x, false),
+            AccountMeta::new(pool.vault_y, false),
+            AccountMeta::new(pool.mint_lp, false),
+            AccountMeta::new(pool.vault_lp, false),
+            AccountMeta::new(pool.config, false),
+            AccountMeta::new(registry, false),
+            AccountMeta::new_readonly(system_program, false),
+            AccountMeta::new_readonly(pool.token_program_x, false),
+            AccountMeta::new_readonly(pool.token_program_y, false),
+            AccountMeta::new(pool.stats, false),
+        ],
+    );
+    let closed = Account::new(0, 0, &system_program);
+    let reopen_accounts = vec![
+        (pool.user, result.get_account(&pool.user).unwrap().clone()),
+        pool.accounts[1].clone(),
+        pool.accounts[2].clone(),
+        (pool.vault_x, closed.clone()),
+        (pool.vault_y, closed.clone()),
+        (
+            pool.mint_lp,
+            result.get_account(&pool.mint_lp).unwrap().clone(),
+        ),
+        (pool.vault_lp, closed.clone()),
+        (pool.config, closed.clone()),
+        (registry, result.get_account(&registry).unwrap().clone()),
+        (system_program, system_account),
+        pool.accounts[11].clone(),
+        (pool.stats, closed),
+    ];
+    mollusk.process_and_validate_instruction(
+        &reopen,
+        &reopen_accounts,
+        &[
+            Check::success(),
+            Check::account(&pool.config).owner(&PROGRAM).build(),
+            Check::account(&registry)
+                .data(
+                    &[
+                        &2u64.to_le_bytes()[..],
+                        other_pool.as_ref(),
+                        pool.config.as_ref(),
+                    ]
+                    .concat(),
+                )
+                .build(),
+        ],
+    );
+
+    // An LP mint with tokens still out cannot be taken over.
+    let mut reopen_accounts = reopen_accounts;
+    reopen_accounts[5].1 = pack_mint(&mollusk, Some(pool.config), 1);
+    mollusk.process_and_validate_instruction(
+        &reopen,
+        &reopen_accounts,
+        &[Check::err(ProgramError::InvalidAccountData)],
+    );
+
+    // Pools opened before the registry are not listed in it, and close all the same.
+    let mut accounts = accounts;
+    let mut legacy_registry_account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Registry::space(1)),
+        Registry::space(1),
+        &PROGRAM,
+    );
+    legacy_registry_account.data = [&1u64.to_le_bytes()[..], other_pool.as_ref()].concat();
+    accounts[14].1 = legacy_registry_account.clone();
+    mollusk.process_and_validate_instruction(
+        &instruction,
+        &accounts,
+        &[
+            Check::success(),
+            Check::account(&pool.config).lamports(0).build(),
+            Check::account(&registry)
+                .lamports(legacy_registry_account.lamports)
+                .data(&legacy_registry_account.data)
+                .build(),
+        ],
+    );
+
+    // A pool with LP tokens still out cannot be closed.
+    accounts[8].1 = pack_token_account(&mollusk, pool.mint_lp, pool.user, 1);
+    accounts[7].1 = pack_mint(&mollusk, Some(pool.config), MINIMUM_LIQUIDITY + 1);
+    mollusk.process_and_validate_instruction(
+        &instruction,
+        &accounts,
+        &[Check::err(ProgramError::Custom(
+            CustomError::PoolNotEmpty as u32,
+        ))],
+    );
+}
+
+#[test]
+
+fn test_migrate_config() {
+    let mollusk = mollusk();
+
+    let pool = pool_fixture(&mollusk, 100_000_000, 100_000_000, 100_000_000, |_| {});
+    let (system_program, system_account) = mollusk_svm::program::keyed_account_for_system_program();
+
+    // A config from before the header and the pending authority were added.
+    let fields = &bytemuck::bytes_of(&pool.config_state)
+        [Config::HEADER_LEN..core::mem::offset_of!(Config, pending_authority)];
+    let mut legacy_config_account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(fields.len()),
+        fields.len(),
+        &PROGRAM,
+    );
+    legacy_config_account.data = fields.to_vec();
+    let mut accounts = pool.accounts.clone();
+    accounts[9].1 = legacy_config_account;
+
+    // The pool is unusable until it is migrated.
+    let instruction_data = SwapInstructionData {
+        is_x: 1,
+        amount: 100u64.to_le_bytes(),
+        min: 1u64.to_le_bytes(),
+        deadline: 0i64.to_le_bytes(),
+    };
+    let swap = pool.instruction(2, bytemuck::bytes_of(&instruction_data));
+    mollusk.process_and_validate_instruction(
+        &swap,
+        &accounts,
+        &[Check::err(ProgramError::InvalidAccountData)],
+    );
+
+    let instruction = Instruction::new_with_bytes(
+        PROGRAM,
+        &[17],
+        vec![
+            AccountMeta::new(pool.user, true),
+            AccountMeta::new(pool.config, false),
+            AccountMeta::new_readonly(system_program, false),
+        ],
+    );
+    let migrate_accounts = vec![
+        accounts[0].clone(),
+        accounts[9].clone(),
+        (system_program, system_account),
+    ];
+    let result = mollusk.process_and_validate_instruction(
+        &instruction,
+        &migrate_accounts,
+        &[
+            Check::success(),
+            Check::account(&pool.config)
+                .lamports(mollusk.sysvars.rent.minimum_balance(Config::LEN))
+                .data(bytemuck::bytes_of(&pool.config_state))
+                .build(),
+        ],
+    );
+    accounts[9].1 = result.get_account(&pool.config).unwrap().clone();
+    mollusk.process_and_validate_instruction(&swap, &accounts, &[Check::success()]);
+
+    // A config from the first versioned layout, before the referral fee was added.
+    let mut v1_config_state = pool.config_state;
+    v1_config_state.version = 1;
+    let v1_data =
+        &bytemuck::bytes_of(&v1_config_state)[..core::mem::offset_of!(Config, referral_fee)];
+    let mut v1_config_account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(v1_data.len()),
+        v1_data.len(),
+        &PROGRAM,
+    );
+    v1_config_account.data = v1_data.to_vec();
+    let mut migrate_accounts = migrate_accounts;
+    migrate_accounts[1].1 = v1_config_account;
+    mollusk.process_and_validate_instruction(
+        &instruction,
+        &migrate_accounts,
+        &[
+            Check::success(),
+            Check::account(&pool.config)
+                .data(bytemuck::bytes_of(&pool.config_state))
+                .build(),
+        ],
+    );
+
+    // A config of the original program, whose vaults and LP mint may be at any address.
+    let config_state = pool.config_state;
+    let baseline_data = [
+        &config_state.seed[..],
+        &config_state.authority,
+        &config_state.mint_x,
+        &config_state.mint_y,
+        &config_state.fee,
+        &[config_state.config_bump],
+    ]
+    .concat();
+    assert_eq!(baseline_data.len(), 107);
+    let mut baseline_config_account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(baseline_data.len()),
+        baseline_data.len(),
+        &PROGRAM,
+    );
+    baseline_config_account.data = baseline_data;
+    migrate_accounts[1].1 = baseline_config_account;
+    mollusk.process_and_validate_instruction(
+        &instruction,
+        &migrate_accounts,
+        &[Check::err(ProgramError::Custom(
+            CustomError::UnmigratableConfig as u32,
+        ))],
+    );
+
+    // Configs from a newer program are rejected.
+    let pool = pool_fixture(&mollusk, 100_000_000, 100_000_000, 100_000_000, |config| {
+        config.version = CONFIG_VERSION + 1;
+    });
+    mollusk.process_and_validate_instruction(
+        &pool.instruction(2, bytemuck::bytes_of(&instruction_data)),
+        &pool.accounts,
+        &[Check::err(ProgramError::Custom(
+            CustomError::UnsupportedConfigVersion as u32,
+        ))],
+    );
+}
+
+#[test]
+
+fn test_swap_deadline() {
+    let mut mollusk = mollusk();
+    mollusk.sysvars.clock.unix_timestamp = 1_000;
+
+    let mut pool = pool_fixture(&mollusk, 100_000_000, 100_000_000, 100_000_000, |_| {});
+
+    let mut instruction_data = SwapInstructionData {
+        is_x: 1,
+        amount: 100u64.to_le_bytes(),
+        min: 1u64.to_le_bytes(),
+        deadline: 999i64.to_le_bytes(),
+    };
+    mollusk.process_and_validate_instruction(
+        &pool.instruction(2, bytemuck::bytes_of(&instruction_data)),
+        &pool.accounts,
+        &[Check::err(ProgramError::Custom(
+            CustomError::DeadlineExceeded as u32,
+        ))],
+    );
+
+    // The swap still goes through at the deadline itself.
+    instruction_data.deadline = 1_000i64.to_le_bytes();
+    mollusk.process_and_validate_instruction(
+        &pool.instruction(2, bytemuck::bytes_of(&instruction_data)),
+        &pool.accounts,
+        &[Check::success()],
+    );
+
+    // Exact output and route swaps take a deadline too.
+    let (second_hop, user_z) = add_second_pool(&mollusk, &mut pool);
+    let first_hop = [
+        pool.config,
+        pool.mint_x,
+        pool.mint_y,
+        pool.vault_x,
+        pool.vault_y,
+        pool.stats,
+    ];
+    for deadline in [999i64, 1_000] {
+        let check = || match deadline {
+            999 => Check::err(ProgramError::Custom(CustomError::DeadlineExceeded as u32)),
+            _ => Check::success(),
+        };
+        let instruction_data = SwapExactOutInstructionData {
+            is_x: 1,
+            amount: 100u64.to_le_bytes(),
+            max: 1_000u64.to_le_bytes(),
+            deadline: deadline.to_le_bytes(),
+        };
+        mollusk.process_and_validate_instruction(
+            &pool.instruction(6, bytemuck::bytes_of(&instruction_data)),
+            &pool.accounts,
+            &[check()],
+        );
+
+        let instruction_data = RouteSwapInstructionData {
+            amount: 100u64.to_le_bytes(),
+            min: 1u64.to_le_bytes(),
+            deadline: deadline.to_le_bytes(),
+        };
+        mollusk.process_and_validate_instruction(
+            &route_swap_instruction(
+                &pool,
+                pool.user_x,
+                user_z,
+                &[first_hop, second_hop],
+                bytemuck::bytes_of(&instruction_data),
+            ),
+            &pool.accounts,
+            &[check()],
+        );
+    }
+}
+
+#[test]
+
+fn test_swap_referral_fee() {
+    let mollusk = mollusk();
+
+    // The referrer earns half of the 5% swap fee.
+    let pool = pool_fixture(&mollusk, 100_000_000, 100_000_000, 100_000_000, |config| {
+        config.referral_fee = 5_000u16.to_le_bytes();
+    });
+    let referrer = Pubkey::new_from_array([0x0c; 32]);
+    let referrer_owner = Pubkey::new_from_array([0x0d; 32]);
+
+    let instruction_data = SwapInstructionData {
+        is_x: 1,
+        amount: 10_000u64.to_le_bytes(),
+        min: 1u64.to_le_bytes(),
+        deadline: 0i64.to_le_bytes(),
+    };
+    let mut instruction = pool.instruction(2, bytemuck::bytes_of(&instruction_data));
+    instruction.accounts.push(AccountMeta::new(referrer, false));
+    let mut accounts = pool.accounts.clone();
+    accounts.push((
+        referrer,
+        pack_token_account(&mollusk, pool.mint_x, referrer_owner, 0),
+    ));
+
+    let result =
+        mollusk.process_and_validate_instruction(&instruction, &accounts, &[Check::success()]);
+    assert_eq!(token_amount(result.get_account(&referrer).unwrap()), 250);
+    assert_eq!(
+        token_amount(result.get_account(&pool.vault_x).unwrap()),
+        100_000_000 + 10_000 - 250
+    );
+
+    // The referrer must hold the input mint.
+    accounts.last_mut().unwrap().1 = pack_token_account(&mollusk, pool.mint_y, referrer_owner, 0);
+    mollusk.process_and_validate_instruction(
+        &instruction,
+        &accounts,
+        &[Check::err(ProgramError::Custom(
+            CustomError::MintMismatch as u32,
+        ))],
+    );
+
+    // The protocol and referral fees cannot add up to more than the swap fee.
+    let authority = Pubkey::new_from_array([0x01; 32]);
+    let (config, _, config_account) = authority_fixture(&mollusk, authority);
+    let instruction_data = UpdateConfigInstructionData {
+        fee: 500u16.to_le_bytes(),
+        locked: 0,
+        referral_fee: 10_001u16.to_le_bytes(),
+    };
+    mollusk.process_and_validate_instruction(
+        &authority_instruction(5, authority, config, bytemuck::bytes_of(&instruction_data)),
+        &vec![
+            (
+                authority,
+                Account::new(1 * LAMPORTS_PER_SOL, 0, &Pubkey::default()),
+            ),
+            (config, config_account),
+        ],
+        &[Check::err(ProgramError::Custom(
+            CustomError::InvalidFee as u32,
+        ))],
+    );
+}
+
+#[test]
+
+fn test_swap_dynamic_fee() {
+    let mut mollusk = mollusk();
+    let now = 1_000;
+    mollusk.sysvars.clock.unix_timestamp = now;
+
+    // Half a window ago the price was twice what it is now, a 50% move that has faded to 25%.
+    // A fifth of it is added to the 5% base fee, up to `max_fee`.
+    let volatile_pool = |mollusk: &Mollusk, max_fee: u16| {
+        pool_fixture(mollusk, 100_000_000, 100_000_000, 100_000_000, |config| {
+            config.dynamic_fee = 1;
+            config.max_dynamic_fee = max_fee.to_le_bytes();
+            config.volatility_factor = 2_000u16.to_le_bytes();
+            config.price_samples[0] = PriceSample {
+                timestamp: (now - VOLATILITY_WINDOW / 2).to_le_bytes(),
+                price: (2u128 << 64).to_le_bytes(),
+            };
+        })
+    };
+
+    // Swaps and checks that the output was priced at `fee`.
+    let amount: u64 = 1_000_000;
+    let swap = |mollusk: &Mollusk, pool: &PoolFixture, fee: u16| {
+        let instruction_data = SwapInstructionData {
+            is_x: 1,
+            amount: amount.to_le_bytes(),
+            min: 1u64.to_le_bytes(),
+            deadline: 0i64.to_le_bytes(),
+        };
+        let amount_out = constant_product::swap_exact_in(
+            100_000_000,
+            100_000_000,
+            amount_after_fee(amount, fee).unwrap(),
+        )
+        .unwrap();
+        let result = mollusk.process_and_validate_instruction(
+            &pool.instruction(2, bytemuck::bytes_of(&instruction_data)),
+            &pool.accounts,
+            &[Check::success()],
+        );
+        assert_eq!(
+            token_amount(result.get_account(&pool.user_y).unwrap()),
+            100_000_000 + amount_out
+        );
+        *bytemuck::from_bytes::<Config>(&result.get_account(&pool.config).unwrap().data)
+    };
+
+    let config_state = swap(&mollusk, &volatile_pool(&mollusk, 2_000), 1_000);
+    assert_eq!(u32::from_le_bytes(config_state.volatility), 2_500);
+
+    // The fee stops at the cap.
+    swap(&mollusk, &volatile_pool(&mollusk, 800), 800);
+
+    // Once the move is a whole window old, the fee is back to the base fee.
+    mollusk.sysvars.clock.unix_timestamp = now + VOLATILITY_WINDOW / 2;
+    let config_state = swap(&mollusk, &volatile_pool(&mollusk, 2_000), 500);
+    assert_eq!(u32::from_le_bytes(config_state.volatility), 0);
+}
+
+#[test]
+
+fn test_update_dynamic_fee() {
+    let mollusk = mollusk();
+
+    let authority = Pubkey::new_from_array([0x01; 32]);
+    let signer_account = Account::new(1 * LAMPORTS_PER_SOL, 0, &Pubkey::default());
+    let (config, config_state, config_account) = authority_fixture(&mollusk, authority);
+
+    let mut instruction_data = UpdateDynamicFeeInstructionData {
+        dynamic_fee: 1,
+        max_fee: 1_000u16.to_le_bytes(),
+        volatility_factor: 2_000u16.to_le_bytes(),
+    };
+    let mut updated_config_state = config_state;
+    updated_config_state.dynamic_fee = 1;
+    updated_config_state.max_dynamic_fee = 1_000u16.to_le_bytes();
+    updated_config_state.volatility_factor = 2_000u16.to_le_bytes();
+
+    mollusk.process_and_validate_instruction(
+        &authority_instruction(18, authority, config, bytemuck::bytes_of(&instruction_data)),
+        &vec![
+            (authority, signer_account.clone()),
+            (config, config_account.clone()),
+        ],
+        &[
+            Check::success(),
+            Check::account(&config)
+                .data(bytemuck::bytes_of(&updated_config_state))
+                .build(),
+        ],
+    );
+
+    // The cap cannot be below the base fee.
+    instruction_data.max_fee = 400u16.to_le_bytes();
+    mollusk.process_and_validate_instruction(
+        &authority_instruction(18, authority, config, bytemuck::bytes_of(&instruction_data)),
+        &vec![(authority, signer_account), (config, config_account)],
+        &[Check::err(ProgramError::Custom(
+            CustomError::InvalidFee as u32,
+        ))],
+    );
+}
+
+#[test]
+
+fn test_stats() {
+    let mut mollusk = mollusk();
+    mollusk.sysvars.clock.slot = 42;
+
+    let pool = pool_fixture(&mollusk, 100_000_000, 100_000_000, 100_000_000, |_| {});
+
+    // Swap x for y, then withdraw 1% of the liquidity.
+    let amount: u64 = 1_000_000;
+    let instruction_data = SwapInstructionData {
+        is_x: 1,
+        amount: amount.to_le_bytes(),
+        min: 1u64.to_le_bytes(),
+        deadline: 0i64.to_le_bytes(),
+    };
+    let result = mollusk.process_and_validate_instruction(
+        &pool.instruction(2, bytemuck::bytes_of(&instruction_data)),
+        &pool.accounts,
+        &[Check::success()],
+    );
+    let amount_out = token_amount(result.get_account(&pool.user_y).unwrap()) - 100_000_000;
+    let accounts = pool
+        .accounts
+        .iter()
+        .map(|(key, account)| (*key, result.get_account(key).unwrap_or(account).clone()))
+        .collect::<Vec<_>>();
+
+    let instruction_data = WithdrawInstructionData {
+        amount: 1_000_000u64.to_le_bytes(),
+        min_x: 0u64.to_le_bytes(),
+        min_y: 0u64.to_le_bytes(),
+    };
+    let result = mollusk.process_and_validate_instruction(
+        &pool.instruction(3, bytemuck::bytes_of(&instruction_data)),
+        &accounts,
+        &[Check::success()],
+    );
+
+    let stats_state =
+        *bytemuck::from_bytes::<Stats>(&result.get_account(&pool.stats).unwrap().data);
+    assert_eq!(u128::from_le_bytes(stats_state.volume_x), amount as u128);
+    assert_eq!(
+        u128::from_le_bytes(stats_state.volume_y),
+        amount_out as u128
+    );
+    assert_eq!(
+        u128::from_le_bytes(stats_state.fees_x),
+        (amount - amount_after_fee(amount, 500).unwrap()) as u128
+    );
+    assert_eq!(u128::from_le_bytes(stats_state.fees_y), 0);
+    assert_eq!(u64::from_le_bytes(stats_state.swap_count), 1);
+    assert_eq!(u64::from_le_bytes(stats_state.deposit_count), 0);
+    assert_eq!(u64::from_le_bytes(stats_state.withdraw_count), 1);
+    assert_eq!(u64::from_le_bytes(stats_state.last_trade_slot), 42);
+
+    // Stats from another pool are rejected.
+    let mut accounts = accounts;
+    accounts[13].1 = stats_fixture(&mollusk, pool.vault_lp).1;
+    mollusk.process_and_validate_instruction(
+        &pool.instruction(3, bytemuck::bytes_of(&instruction_data)),
+        &accounts,
+        &[Check::err(ProgramError::InvalidAccountData)],
+    );
+}
+
+#[test]
+
+fn test_initialize_stats() {
+    let mollusk = mollusk();
+
+    // A pool opened before pools came with stats.
+    let mut pool = pool_fixture(&mollusk, 100_000_000, 100_000_000, 100_000_000, |_| {});
+    let (system_program, system_account) = mollusk_svm::program::keyed_account_for_system_program();
+    let expected_stats_account = pool.accounts[13].1.clone();
+    pool.accounts[13].1 = Account::new(0, 0, &system_program);
+
+    let (_, stats_bump) = solana_sdk::pubkey::Pubkey::find_program_address(
+        &[STATS_SEED.as_bytes(), pool.config.as_ref()],
+        &PROGRAM,
+    );
+    let instruction_data = InitializeStatsInstructionData { stats_bump };
+    let mut ser_instruction_data = vec![19];
+    ser_instruction_data.extend_from_slice(bytemuck::bytes_of(&instruction_data));
+    let instruction = Instruction::new_with_bytes(
+        PROGRAM,
+        &ser_instruction_data,
+        vec![
+            AccountMeta::new(pool.user, true),
+            AccountMeta::new_readonly(pool.config, false),
+            AccountMeta::new(pool.stats, false),
+            AccountMeta::new_readonly(system_program, false),
+        ],
+    );
+
+    mollusk.process_and_validate_instruction(
+        &instruction,
+        &vec![
+            pool.accounts[0].clone(),
+            pool.accounts[9].clone(),
+            pool.accounts[13].clone(),
+            (system_program, system_account),
+        ],
+        &[
+            Check::success(),
+            Check::account(&pool.stats)
+                .owner(&PROGRAM)
+                .lamports(expected_stats_account.lamports)
+                .data(&expected_stats_account.data)
+                .build(),
+        ],
+    );
+}
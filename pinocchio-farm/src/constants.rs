@@ -0,0 +1,3 @@
+pub const FARM_SEED: &str = "farm";
+pub const POSITION_SEED: &str = "position";
+pub const VAULT_SEED: &str = "vault";
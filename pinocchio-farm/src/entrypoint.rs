@@ -0,0 +1,35 @@
+use crate::instruction::{self, FarmInstruction};
+use pinocchio::{
+    account_info::AccountInfo, default_panic_handler, no_allocator, program_entrypoint,
+    program_error::ProgramError, pubkey::Pubkey, ProgramResult,
+};
+
+// Define the program entrypoint.
+program_entrypoint!(process_instruction);
+// Do not allocate memory.
+no_allocator!();
+// Use the nostd panic handler.
+default_panic_handler!();
+
+#[inline(always)]
+fn process_instruction(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let (ix_disc, instruction_data) = instruction_data
+        .split_first()
+        .ok_or(ProgramError::InvalidInstructionData)?;
+
+    match FarmInstruction::try_from(ix_disc)? {
+        FarmInstruction::InitializeFarm => {
+            instruction::process_initialize_farm(accounts, &instruction_data)
+        }
+        FarmInstruction::Stake => instruction::process_stake(accounts, &instruction_data),
+        FarmInstruction::Unstake => instruction::process_unstake(accounts, &instruction_data),
+        FarmInstruction::Claim => instruction::process_claim(accounts),
+        FarmInstruction::FundRewards => {
+            instruction::process_fund_rewards(accounts, &instruction_data)
+        }
+    }
+}
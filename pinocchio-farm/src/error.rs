@@ -0,0 +1,16 @@
+use pinocchio::program_error::ProgramError;
+
+#[derive(Clone, PartialEq)]
+pub enum CustomError {
+    InvalidAmount,
+    InvalidRewardDuration,
+    InsufficientStake,
+    MintMismatch,
+    DuplicateMint,
+}
+
+impl From<CustomError> for ProgramError {
+    fn from(e: CustomError) -> Self {
+        Self::Custom(e as u32)
+    }
+}
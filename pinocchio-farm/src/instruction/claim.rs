@@ -0,0 +1,68 @@
+use pinocchio::{
+    account_info::AccountInfo,
+    instruction::{Seed, Signer},
+    program_error::ProgramError,
+    sysvars::{clock::Clock, Sysvar},
+    ProgramResult,
+};
+use pinocchio_amm::{
+    token::{self, Mint},
+    validation::validate_token_program,
+};
+
+use crate::{
+    state::{Farm, Position},
+    validation::{validate_farm, validate_mint_vault, validate_position},
+};
+
+pub fn process_claim(accounts: &[AccountInfo]) -> ProgramResult {
+    let [user, farm, reward_mint, user_reward, reward_vault, position, token_program] = accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if !user.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let farm_account = Farm::load(farm)?;
+    let position_account = Position::load(position)?;
+
+    // Validate farm accounts.
+    validate_farm(farm, farm_account)?;
+    validate_mint_vault(
+        reward_mint,
+        reward_vault,
+        &farm_account.reward_mint,
+        &farm_account.reward_vault,
+    )?;
+    validate_token_program(token_program)?;
+    validate_position(farm, user, position_account)?;
+
+    farm_account.update(Clock::get()?.unix_timestamp)?;
+    position_account.settle(farm_account)?;
+
+    let rewards = u64::from_le_bytes(position_account.pending_rewards);
+    if rewards == 0 {
+        return Ok(());
+    }
+    position_account.pending_rewards = 0u64.to_le_bytes();
+
+    // Setup signer seeds.
+    let seed = farm_account.signer_seeds().map(Seed::from);
+    let seeds = Signer::from(&seed);
+
+    // Pay the rewards to the user.
+    token::instructions::TransferChecked {
+        from: reward_vault,
+        mint: reward_mint,
+        to: user_reward,
+        authority: farm,
+        amount: rewards,
+        decimals: Mint::from_account_info(reward_mint)?.decimals(),
+        token_program: token_program.key(),
+    }
+    .invoke_signed(&[seeds])?;
+
+    Ok(())
+}
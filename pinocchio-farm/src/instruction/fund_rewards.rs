@@ -0,0 +1,106 @@
+use bytemuck::{Pod, Zeroable};
+use pinocchio::{
+    account_info::AccountInfo,
+    program_error::ProgramError,
+    sysvars::{clock::Clock, Sysvar},
+    ProgramResult,
+};
+use pinocchio_amm::{
+    token::{self, Mint},
+    validation::validate_token_program,
+};
+
+use crate::{
+    error::CustomError,
+    state::Farm,
+    validation::{validate_farm, validate_mint_vault},
+};
+
+/// Adds `amount` rewards and pays them, along with the rewards left from the current period,
+/// evenly over the next `duration` seconds.
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct FundRewardsInstructionData {
+    pub amount: [u8; 8],
+    pub duration: [u8; 8],
+}
+
+impl FundRewardsInstructionData {
+    pub const LEN: usize = core::mem::size_of::<FundRewardsInstructionData>();
+}
+
+pub fn process_fund_rewards(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let [authority, farm, reward_mint, authority_reward, reward_vault, token_program] = accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if !authority.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let instruction_data = bytemuck::try_from_bytes::<FundRewardsInstructionData>(&data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+
+    let amount = u64::from_le_bytes(instruction_data.amount);
+    if amount == 0 {
+        return Err(CustomError::InvalidAmount.into());
+    }
+    let duration = i64::from_le_bytes(instruction_data.duration);
+    if duration <= 0 {
+        return Err(CustomError::InvalidRewardDuration.into());
+    }
+
+    let farm_account = Farm::load(farm)?;
+
+    // Validate farm accounts.
+    validate_farm(farm, farm_account)?;
+    if &farm_account.authority != authority.key() {
+        return Err(ProgramError::IncorrectAuthority);
+    }
+    validate_mint_vault(
+        reward_mint,
+        reward_vault,
+        &farm_account.reward_mint,
+        &farm_account.reward_vault,
+    )?;
+    validate_token_program(token_program)?;
+
+    // Accrue the rewards of the current period at its own rate.
+    let now = Clock::get()?.unix_timestamp;
+    farm_account.update(now)?;
+
+    // Fund what reaches the vault, net of any transfer fee.
+    let amount = token::instructions::TransferChecked {
+        from: authority_reward,
+        mint: reward_mint,
+        to: reward_vault,
+        authority,
+        amount,
+        decimals: Mint::from_account_info(reward_mint)?.decimals(),
+        token_program: token_program.key(),
+    }
+    .invoke_signed_received(&[])?;
+
+    // Rewards not paid yet from the current period roll into the new one.
+    let end_timestamp = i64::from_le_bytes(farm_account.end_timestamp);
+    let leftover = match now < end_timestamp {
+        true => {
+            (end_timestamp - now) as u128 * u64::from_le_bytes(farm_account.reward_rate) as u128
+        }
+        false => 0,
+    };
+    let reward_rate = u64::try_from((amount as u128 + leftover) / duration as u128)
+        .map_err(|_| ProgramError::ArithmeticOverflow)?;
+    if reward_rate == 0 {
+        return Err(CustomError::InvalidRewardDuration.into());
+    }
+
+    farm_account.reward_rate = reward_rate.to_le_bytes();
+    farm_account.end_timestamp = now
+        .checked_add(duration)
+        .ok_or(ProgramError::ArithmeticOverflow)?
+        .to_le_bytes();
+
+    Ok(())
+}
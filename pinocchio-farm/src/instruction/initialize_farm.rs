@@ -0,0 +1,151 @@
+use bytemuck::{Pod, Zeroable};
+use pinocchio::{
+    account_info::AccountInfo,
+    instruction::Seed,
+    program_error::ProgramError,
+    pubkey,
+    sysvars::{clock::Clock, rent::Rent, Sysvar},
+    ProgramResult,
+};
+use pinocchio_amm::{
+    instruction::create_pda_account,
+    token::{self, TokenAccount, TOKEN_2022_ID},
+    validation::validate_token_program,
+};
+
+use crate::{constants::VAULT_SEED, error::CustomError, state::Farm, validation::validate_farm};
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct InitializeFarmInstructionData {
+    pub farm_bump: u8,
+    pub stake_vault_bump: u8,
+    pub reward_vault_bump: u8,
+}
+
+impl InitializeFarmInstructionData {
+    pub const LEN: usize = core::mem::size_of::<InitializeFarmInstructionData>();
+}
+
+pub fn process_initialize_farm(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let [authority, stake_mint, reward_mint, farm, stake_vault, reward_vault, _system_program, token_program] =
+        accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if !authority.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let instruction_data = bytemuck::try_from_bytes::<InitializeFarmInstructionData>(&data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+
+    // Both vaults derive from their mint, so the mints must differ.
+    if stake_mint.key() == reward_mint.key() {
+        return Err(CustomError::DuplicateMint.into());
+    }
+
+    // The vaults are created under the token program of the farm mints.
+    validate_token_program(token_program)?;
+    if !stake_mint.is_owned_by(token_program.key()) || !reward_mint.is_owned_by(token_program.key())
+    {
+        return Err(ProgramError::InvalidAccountOwner);
+    }
+
+    let mut farm_state = Farm::zeroed();
+    farm_state.authority = *authority.key();
+    farm_state.stake_mint = *stake_mint.key();
+    farm_state.reward_mint = *reward_mint.key();
+    farm_state.stake_vault = *stake_vault.key();
+    farm_state.reward_vault = *reward_vault.key();
+    farm_state.farm_bump = instruction_data.farm_bump;
+
+    // Validate farm account.
+    validate_farm(farm, &farm_state)?;
+
+    // Validate vault accounts.
+    for (vault, mint, vault_bump) in [
+        (stake_vault, stake_mint, instruction_data.stake_vault_bump),
+        (
+            reward_vault,
+            reward_mint,
+            instruction_data.reward_vault_bump,
+        ),
+    ] {
+        let vault_pda = pubkey::create_program_address(
+            &[
+                VAULT_SEED.as_bytes(),
+                farm.key().as_ref(),
+                mint.key().as_ref(),
+                &[vault_bump],
+            ],
+            &crate::ID,
+        )?;
+        if vault.key() != &vault_pda {
+            return Err(ProgramError::InvalidAccountData);
+        }
+    }
+
+    let rent = Rent::get()?;
+
+    // Create farm account.
+    let farm_seed = farm_state.signer_seeds().map(Seed::from);
+    create_pda_account(
+        authority,
+        farm,
+        Farm::LEN,
+        rent.minimum_balance(Farm::LEN),
+        &crate::ID,
+        &farm_seed,
+    )?;
+
+    // Create vault accounts, owned by the farm.
+    for (vault, mint, vault_bump) in [
+        (stake_vault, stake_mint, instruction_data.stake_vault_bump),
+        (
+            reward_vault,
+            reward_mint,
+            instruction_data.reward_vault_bump,
+        ),
+    ] {
+        let vault_bump = [vault_bump];
+        let vault_seed = [
+            Seed::from(VAULT_SEED.as_bytes()),
+            Seed::from(farm.key().as_ref()),
+            Seed::from(mint.key().as_ref()),
+            Seed::from(&vault_bump),
+        ];
+        // Token-2022 accounts grow with the extensions of their mint.
+        let space = match token_program.key() == &TOKEN_2022_ID {
+            true => token::instructions::GetAccountDataSize {
+                mint,
+                token_program: token_program.key(),
+            }
+            .invoke()?,
+            false => TokenAccount::LEN,
+        };
+        create_pda_account(
+            authority,
+            vault,
+            space,
+            rent.minimum_balance(space),
+            token_program.key(),
+            &vault_seed,
+        )?;
+
+        token::instructions::InitializeAccount3 {
+            account: vault,
+            mint,
+            owner: farm.key(),
+            token_program: token_program.key(),
+        }
+        .invoke()?;
+    }
+
+    // Initialize farm account. Rewards start flowing once the farm is funded.
+    farm_state.last_update_timestamp = Clock::get()?.unix_timestamp.to_le_bytes();
+    *Farm::load(farm)? = farm_state;
+
+    Ok(())
+}
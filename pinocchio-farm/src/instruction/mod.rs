@@ -0,0 +1,37 @@
+use pinocchio::program_error::ProgramError;
+
+pub mod claim;
+pub mod fund_rewards;
+pub mod initialize_farm;
+pub mod stake;
+pub mod unstake;
+
+pub use claim::*;
+pub use fund_rewards::*;
+pub use initialize_farm::*;
+pub use stake::*;
+pub use unstake::*;
+
+#[repr(u8)]
+pub enum FarmInstruction {
+    InitializeFarm,
+    Stake,
+    Unstake,
+    Claim,
+    FundRewards,
+}
+
+impl TryFrom<&u8> for FarmInstruction {
+    type Error = ProgramError;
+
+    fn try_from(value: &u8) -> Result<Self, Self::Error> {
+        match *value {
+            0 => Ok(FarmInstruction::InitializeFarm),
+            1 => Ok(FarmInstruction::Stake),
+            2 => Ok(FarmInstruction::Unstake),
+            3 => Ok(FarmInstruction::Claim),
+            4 => Ok(FarmInstruction::FundRewards),
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use bytemuck::{Pod, Zeroable};
+use pinocchio::{
+    account_info::AccountInfo,
+    instruction::Seed,
+    program_error::ProgramError,
+    pubkey,
+    sysvars::{clock::Clock, rent::Rent, Sysvar},
+    ProgramResult,
+};
+use pinocchio_amm::{
+    instruction::create_pda_account,
+    token::{self, Mint},
+    validation::validate_token_program,
+};
+
+use crate::{
+    constants::POSITION_SEED,
+    error::CustomError,
+    state::{Farm, Position},
+    validation::{validate_farm, validate_mint_vault, validate_position},
+};
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct StakeInstructionData {
+    pub amount: [u8; 8],
+    pub position_bump: u8,
+}
+
+impl StakeInstructionData {
+    pub const LEN: usize = core::mem::size_of::<StakeInstructionData>();
+}
+
+pub fn process_stake(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let [user, farm, stake_mint, user_stake, stake_vault, position, _system_program, token_program] =
+        accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if !user.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let instruction_data = bytemuck::try_from_bytes::<StakeInstructionData>(&data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+
+    let amount = u64::from_le_bytes(instruction_data.amount);
+    if amount == 0 {
+        return Err(CustomError::InvalidAmount.into());
+    }
+
+    let farm_account = Farm::load(farm)?;
+
+    // Validate farm accounts.
+    validate_farm(farm, farm_account)?;
+    validate_mint_vault(
+        stake_mint,
+        stake_vault,
+        &farm_account.stake_mint,
+        &farm_account.stake_vault,
+    )?;
+    validate_token_program(token_program)?;
+
+    // The first stake of a user opens their position.
+    if position.data_len() == 0 {
+        let position_pda = pubkey::create_program_address(
+            &[
+                POSITION_SEED.as_bytes(),
+                farm.key().as_ref(),
+                user.key().as_ref(),
+                &[instruction_data.position_bump],
+            ],
+            &crate::ID,
+        )?;
+        if position.key() != &position_pda {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let position_bump = [instruction_data.position_bump];
+        let position_seed = [
+            Seed::from(POSITION_SEED.as_bytes()),
+            Seed::from(farm.key().as_ref()),
+            Seed::from(user.key().as_ref()),
+            Seed::from(&position_bump),
+        ];
+        create_pda_account(
+            user,
+            position,
+            Position::LEN,
+            Rent::get()?.minimum_balance(Position::LEN),
+            &crate::ID,
+            &position_seed,
+        )?;
+
+        let position_account = Position::load(position)?;
+        position_account.owner = *user.key();
+        position_account.farm = *farm.key();
+        position_account.bump = instruction_data.position_bump;
+    }
+
+    // Validate position account.
+    let position_account = Position::load(position)?;
+    validate_position(farm, user, position_account)?;
+
+    // Accrue the rewards paid at the stake from before this instruction.
+    farm_account.update(Clock::get()?.unix_timestamp)?;
+
+    // Stake what reaches the vault, net of any transfer fee.
+    let amount = token::instructions::TransferChecked {
+        from: user_stake,
+        mint: stake_mint,
+        to: stake_vault,
+        authority: user,
+        amount,
+        decimals: Mint::from_account_info(stake_mint)?.decimals(),
+        token_program: token_program.key(),
+    }
+    .invoke_signed_received(&[])?;
+
+    let staked = u64::from_le_bytes(position_account.amount)
+        .checked_add(amount)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    position_account.set_amount(farm_account, staked)?;
+    farm_account.total_staked = u64::from_le_bytes(farm_account.total_staked)
+        .checked_add(amount)
+        .ok_or(ProgramError::ArithmeticOverflow)?
+        .to_le_bytes();
+
+    Ok(())
+}
@@ -0,0 +1,89 @@
+use bytemuck::{Pod, Zeroable};
+use pinocchio::{
+    account_info::AccountInfo,
+    instruction::{Seed, Signer},
+    program_error::ProgramError,
+    sysvars::{clock::Clock, Sysvar},
+    ProgramResult,
+};
+use pinocchio_amm::{
+    token::{self, Mint},
+    validation::validate_token_program,
+};
+
+use crate::{
+    error::CustomError,
+    state::{Farm, Position},
+    validation::{validate_farm, validate_mint_vault, validate_position},
+};
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct UnstakeInstructionData {
+    pub amount: [u8; 8],
+}
+
+impl UnstakeInstructionData {
+    pub const LEN: usize = core::mem::size_of::<UnstakeInstructionData>();
+}
+
+pub fn process_unstake(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let [user, farm, stake_mint, user_stake, stake_vault, position, token_program] = accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    if !user.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let instruction_data = bytemuck::try_from_bytes::<UnstakeInstructionData>(&data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+
+    let amount = u64::from_le_bytes(instruction_data.amount);
+    if amount == 0 {
+        return Err(CustomError::InvalidAmount.into());
+    }
+
+    let farm_account = Farm::load(farm)?;
+    let position_account = Position::load(position)?;
+
+    // Validate farm accounts.
+    validate_farm(farm, farm_account)?;
+    validate_mint_vault(
+        stake_mint,
+        stake_vault,
+        &farm_account.stake_mint,
+        &farm_account.stake_vault,
+    )?;
+    validate_token_program(token_program)?;
+    validate_position(farm, user, position_account)?;
+
+    let staked = u64::from_le_bytes(position_account.amount)
+        .checked_sub(amount)
+        .ok_or(CustomError::InsufficientStake)?;
+
+    // Rewards earned so far stay pending in the position, to be claimed.
+    farm_account.update(Clock::get()?.unix_timestamp)?;
+    position_account.set_amount(farm_account, staked)?;
+    farm_account.total_staked =
+        (u64::from_le_bytes(farm_account.total_staked) - amount).to_le_bytes();
+
+    // Setup signer seeds.
+    let seed = farm_account.signer_seeds().map(Seed::from);
+    let seeds = Signer::from(&seed);
+
+    // Return the stake to the user.
+    token::instructions::TransferChecked {
+        from: stake_vault,
+        mint: stake_mint,
+        to: user_stake,
+        authority: farm,
+        amount,
+        decimals: Mint::from_account_info(stake_mint)?.decimals(),
+        token_program: token_program.key(),
+    }
+    .invoke_signed(&[seeds])?;
+
+    Ok(())
+}
@@ -0,0 +1,10 @@
+pub mod constants;
+pub mod error;
+pub mod instruction;
+pub mod state;
+pub mod validation;
+
+#[cfg(not(feature = "no-bpf-entrypoint"))]
+mod entrypoint;
+
+pinocchio_pubkey::declare_id!("6nuCzAXFnQuqJA1WC8nciefdBLFmmhm1CK6J9TxXURy8");
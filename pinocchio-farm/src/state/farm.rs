@@ -0,0 +1,89 @@
+use bytemuck::{Pod, Zeroable};
+use pinocchio::{account_info::AccountInfo, program_error::ProgramError, pubkey::Pubkey};
+
+use crate::constants::FARM_SEED;
+
+/// A farm paying `reward_mint` tokens to stakers of `stake_mint`, at the PDA of `FARM_SEED`, its
+/// authority and both mints, so that every authority can open its own farm for a pair of mints.
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct Farm {
+    pub authority: Pubkey,
+    pub stake_mint: Pubkey,
+    pub reward_mint: Pubkey,
+    pub stake_vault: Pubkey,
+    pub reward_vault: Pubkey,
+    pub farm_bump: u8,
+    // Reward tokens paid per second, shared by all stakers, until `end_timestamp`.
+    pub reward_rate: [u8; 8],
+    pub end_timestamp: [u8; 8],
+    // Rewards earned by one staked token since the farm opened, as a Q64.64 fixed-point number.
+    pub reward_per_share: [u8; 16],
+    pub last_update_timestamp: [u8; 8],
+    pub total_staked: [u8; 8],
+}
+
+impl Farm {
+    pub const LEN: usize = core::mem::size_of::<Farm>();
+
+    pub fn load(farm_account: &AccountInfo) -> Result<&mut Self, ProgramError> {
+        if !farm_account.is_owned_by(&crate::ID) {
+            return Err(ProgramError::InvalidAccountOwner);
+        }
+        let data = unsafe { farm_account.borrow_mut_data_unchecked() };
+        bytemuck::try_from_bytes_mut::<Farm>(data).map_err(|_| ProgramError::InvalidAccountData)
+    }
+
+    /// Returns the seeds of the farm PDA, bump included.
+    pub fn signer_seeds(&self) -> [&[u8]; 5] {
+        [
+            FARM_SEED.as_bytes(),
+            &self.authority,
+            &self.stake_mint,
+            &self.reward_mint,
+            core::slice::from_ref(&self.farm_bump),
+        ]
+    }
+
+    /// Accrues the rewards paid since the last update into `reward_per_share`, up to `now` or the
+    /// end of the reward period, whichever comes first. Rewards paid while nothing is staked stay
+    /// in the reward vault.
+    pub fn update(&mut self, now: i64) -> Result<(), ProgramError> {
+        let last_update_timestamp = i64::from_le_bytes(self.last_update_timestamp);
+        let until = now.min(i64::from_le_bytes(self.end_timestamp));
+        let total_staked = u64::from_le_bytes(self.total_staked);
+
+        if until > last_update_timestamp && total_staked > 0 {
+            let rewards = (until - last_update_timestamp) as u128
+                * u64::from_le_bytes(self.reward_rate) as u128;
+            let reward_per_share = rewards
+                .checked_mul(1 << 64)
+                .map(|rewards| rewards / total_staked as u128)
+                .and_then(|increase| {
+                    u128::from_le_bytes(self.reward_per_share).checked_add(increase)
+                })
+                .ok_or(ProgramError::ArithmeticOverflow)?;
+            self.reward_per_share = reward_per_share.to_le_bytes();
+        }
+        if now > last_update_timestamp {
+            self.last_update_timestamp = now.to_le_bytes();
+        }
+
+        Ok(())
+    }
+
+    /// Returns the rewards earned by `amount` staked tokens since the farm opened, in whole reward
+    /// tokens, rounded down or up. The integer and fractional halves of `reward_per_share` are
+    /// multiplied separately, so that no product exceeds 128 bits.
+    pub fn rewards_of(&self, amount: u64, round_up: bool) -> u128 {
+        let reward_per_share = u128::from_le_bytes(self.reward_per_share);
+        let integer = amount as u128 * (reward_per_share >> 64);
+        let fraction = amount as u128 * (reward_per_share as u64 as u128);
+        let fraction = match round_up {
+            true => fraction.div_ceil(1 << 64),
+            false => fraction >> 64,
+        };
+        // At most (2^64 - 1)^2 + 2^64, which fits in 128 bits.
+        integer + fraction
+    }
+}
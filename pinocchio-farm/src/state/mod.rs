@@ -0,0 +1,4 @@
+pub mod farm;
+pub mod position;
+pub use farm::*;
+pub use position::*;
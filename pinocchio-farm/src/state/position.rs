@@ -0,0 +1,57 @@
+use bytemuck::{Pod, Zeroable};
+use pinocchio::{account_info::AccountInfo, program_error::ProgramError, pubkey::Pubkey};
+
+use super::Farm;
+
+/// The stake of `owner` in `farm`, at the PDA of `POSITION_SEED`, the farm and the owner.
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct Position {
+    pub owner: Pubkey,
+    pub farm: Pubkey,
+    pub bump: u8,
+    pub amount: [u8; 8],
+    // Rewards earned by `amount` up to the last settlement, in whole reward tokens, so that only
+    // what was earned since is paid. Rounded up when the amount is set, so that rounding never
+    // pays out more than the farm accrued.
+    pub reward_debt: [u8; 16],
+    // Rewards settled but not claimed yet.
+    pub pending_rewards: [u8; 8],
+}
+
+impl Position {
+    pub const LEN: usize = core::mem::size_of::<Position>();
+
+    pub fn load(position_account: &AccountInfo) -> Result<&mut Self, ProgramError> {
+        if !position_account.is_owned_by(&crate::ID) {
+            return Err(ProgramError::InvalidAccountOwner);
+        }
+        let data = unsafe { position_account.borrow_mut_data_unchecked() };
+        bytemuck::try_from_bytes_mut::<Position>(data).map_err(|_| ProgramError::InvalidAccountData)
+    }
+
+    /// Moves the rewards earned since the last settlement into `pending_rewards`. `farm` must be
+    /// updated first.
+    pub fn settle(&mut self, farm: &Farm) -> Result<(), ProgramError> {
+        let rewards = farm.rewards_of(u64::from_le_bytes(self.amount), false);
+        let reward_debt = u128::from_le_bytes(self.reward_debt);
+        // The debt is rounded up, so the rewards can fall short of it until the next token is
+        // earned.
+        let earned = rewards.saturating_sub(reward_debt);
+        let pending_rewards = u64::try_from(earned)
+            .ok()
+            .and_then(|earned| u64::from_le_bytes(self.pending_rewards).checked_add(earned))
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+        self.pending_rewards = pending_rewards.to_le_bytes();
+        self.reward_debt = reward_debt.max(rewards).to_le_bytes();
+        Ok(())
+    }
+
+    /// Sets the staked amount, after settling the rewards of the previous one.
+    pub fn set_amount(&mut self, farm: &Farm, amount: u64) -> Result<(), ProgramError> {
+        self.settle(farm)?;
+        self.amount = amount.to_le_bytes();
+        self.reward_debt = farm.rewards_of(amount, true).to_le_bytes();
+        Ok(())
+    }
+}
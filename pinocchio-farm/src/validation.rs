@@ -0,0 +1,46 @@
+use pinocchio::{
+    account_info::AccountInfo,
+    program_error::ProgramError,
+    pubkey::{self, Pubkey},
+    ProgramResult,
+};
+
+use crate::{
+    error::CustomError,
+    state::{Farm, Position},
+};
+
+pub fn validate_farm(farm: &AccountInfo, farm_state: &Farm) -> ProgramResult {
+    let farm_pda = pubkey::create_program_address(&farm_state.signer_seeds(), &crate::ID)?;
+    if farm.key() != &farm_pda {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(())
+}
+
+/// Validates the mint and vault of either side of the farm against the ones it was opened with.
+pub fn validate_mint_vault(
+    mint: &AccountInfo,
+    vault: &AccountInfo,
+    farm_mint: &Pubkey,
+    farm_vault: &Pubkey,
+) -> ProgramResult {
+    if mint.key() != farm_mint {
+        return Err(CustomError::MintMismatch.into());
+    }
+    if vault.key() != farm_vault {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(())
+}
+
+pub fn validate_position(
+    farm: &AccountInfo,
+    owner: &AccountInfo,
+    position_state: &Position,
+) -> ProgramResult {
+    if &position_state.farm != farm.key() || &position_state.owner != owner.key() {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(())
+}
@@ -0,0 +1,673 @@
+use mollusk_svm::result::Check;
+use mollusk_svm::{program, Mollusk};
+use pinocchio_farm::constants::{FARM_SEED, POSITION_SEED, VAULT_SEED};
+use pinocchio_farm::error::CustomError;
+use pinocchio_farm::instruction::{
+    FundRewardsInstructionData, InitializeFarmInstructionData, StakeInstructionData,
+    UnstakeInstructionData,
+};
+use pinocchio_farm::state::{Farm, Position};
+use solana_sdk::account::Account;
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::program_error::ProgramError;
+use solana_sdk::pubkey::Pubkey;
+extern crate alloc;
+use alloc::vec;
+use bytemuck::Zeroable;
+
+use solana_sdk::{account::WritableAccount, program_option::COption, program_pack::Pack};
+use spl_token::state::AccountState;
+
+use pinocchio_farm::ID;
+
+pub const PROGRAM: Pubkey = Pubkey::new_from_array(ID);
+
+pub const NOW: i64 = 1_000;
+
+pub fn mollusk() -> Mollusk {
+    let mut mollusk = Mollusk::new(&PROGRAM, "target/deploy/pinocchio_farm");
+    mollusk.add_program(
+        &spl_token::ID,
+        "../pinocchio-amm/tests/elf_files/spl_token",
+        &mollusk_svm::program::loader_keys::LOADER_V3,
+    );
+    mollusk.sysvars.clock.unix_timestamp = NOW;
+    mollusk
+}
+
+pub fn pack_mint(mollusk: &Mollusk, supply: u64) -> Account {
+    let mut account = Account::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Mint::LEN),
+        spl_token::state::Mint::LEN,
+        &spl_token::ID,
+    );
+    solana_sdk::program_pack::Pack::pack(
+        spl_token::state::Mint {
+            mint_authority: COption::None,
+            supply,
+            decimals: 6,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        },
+        account.data_as_mut_slice(),
+    )
+    .unwrap();
+    account
+}
+
+pub fn pack_token_account(mollusk: &Mollusk, mint: Pubkey, owner: Pubkey, amount: u64) -> Account {
+    let mut account = Account::new(
+        mollusk
+            .sysvars
+            .rent
+            .minimum_balance(spl_token::state::Account::LEN),
+        spl_token::state::Account::LEN,
+        &spl_token::ID,
+    );
+    solana_sdk::program_pack::Pack::pack(
+        spl_token::state::Account {
+            mint,
+            owner,
+            amount,
+            delegate: COption::None,
+            state: AccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        },
+        account.data_as_mut_slice(),
+    )
+    .unwrap();
+    account
+}
+
+pub fn token_amount(account: &Account) -> u64 {
+    spl_token::state::Account::unpack(&account.data)
+        .unwrap()
+        .amount
+}
+
+/// Accounts of a funded farm and of one of its stakers.
+pub struct FarmFixture {
+    pub authority: Pubkey,
+    pub user: Pubkey,
+    pub stake_mint: Pubkey,
+    pub reward_mint: Pubkey,
+    pub farm: Pubkey,
+    pub stake_vault: Pubkey,
+    pub reward_vault: Pubkey,
+    pub user_stake: Pubkey,
+    pub user_reward: Pubkey,
+    pub authority_reward: Pubkey,
+    pub position: Pubkey,
+    pub position_bump: u8,
+    pub accounts: Vec<(Pubkey, Account)>,
+}
+
+/// Sets up a farm paying `reward_rate` per second until `NOW + 1_000`, with `total_staked`
+/// tokens staked of which the user holds `staked`. The user has no position when `staked` is
+/// zero. `configure` can adjust the farm state before it is written.
+pub fn farm_fixture(
+    mollusk: &Mollusk,
+    total_staked: u64,
+    staked: u64,
+    reward_rate: u64,
+    configure: impl FnOnce(&mut Farm),
+) -> FarmFixture {
+    let (system_program, system_account) = mollusk_svm::program::keyed_account_for_system_program();
+    let (token_program, token_account) = (
+        spl_token::ID,
+        program::create_program_account_loader_v3(&spl_token::ID),
+    );
+
+    let authority = Pubkey::new_from_array([0x00; 32]);
+    let user = Pubkey::new_from_array([0x01; 32]);
+    let stake_mint = Pubkey::new_from_array([0x02; 32]);
+    let reward_mint = Pubkey::new_from_array([0x03; 32]);
+    let stake_vault = Pubkey::new_from_array([0x04; 32]);
+    let reward_vault = Pubkey::new_from_array([0x05; 32]);
+    let user_stake = Pubkey::new_from_array([0x06; 32]);
+    let user_reward = Pubkey::new_from_array([0x07; 32]);
+    let authority_reward = Pubkey::new_from_array([0x08; 32]);
+
+    let (farm, farm_bump) = solana_sdk::pubkey::Pubkey::find_program_address(
+        &[
+            FARM_SEED.as_bytes(),
+            authority.as_ref(),
+            stake_mint.as_ref(),
+            reward_mint.as_ref(),
+        ],
+        &PROGRAM,
+    );
+    let mut farm_state = Farm {
+        authority: *authority.as_array(),
+        stake_mint: *stake_mint.as_array(),
+        reward_mint: *reward_mint.as_array(),
+        stake_vault: *stake_vault.as_array(),
+        reward_vault: *reward_vault.as_array(),
+        farm_bump,
+        reward_rate: reward_rate.to_le_bytes(),
+        end_timestamp: (NOW + 1_000).to_le_bytes(),
+        last_update_timestamp: NOW.to_le_bytes(),
+        total_staked: total_staked.to_le_bytes(),
+        ..Farm::zeroed()
+    };
+    configure(&mut farm_state);
+    let mut farm_account = Account::new(
+        mollusk.sysvars.rent.minimum_balance(Farm::LEN),
+        Farm::LEN,
+        &PROGRAM,
+    );
+    farm_account.data = bytemuck::bytes_of(&farm_state).to_vec();
+
+    let (position, position_bump) = solana_sdk::pubkey::Pubkey::find_program_address(
+        &[POSITION_SEED.as_bytes(), farm.as_ref(), user.as_ref()],
+        &PROGRAM,
+    );
+    let position_account = match staked {
+        0 => Account::new(0, 0, &system_program),
+        _ => {
+            let position_state = Position {
+                owner: *user.as_array(),
+                farm: *farm.as_array(),
+                bump: position_bump,
+                amount: staked.to_le_bytes(),
+                ..Position::zeroed()
+            };
+            let mut position_account = Account::new(
+                mollusk.sysvars.rent.minimum_balance(Position::LEN),
+                Position::LEN,
+                &PROGRAM,
+            );
+            position_account.data = bytemuck::bytes_of(&position_state).to_vec();
+            position_account
+        }
+    };
+
+    let accounts = vec![
+        (
+            authority,
+            Account::new(LAMPORTS_PER_SOL, 0, &system_program),
+        ),
+        (user, Account::new(LAMPORTS_PER_SOL, 0, &system_program)),
+        (stake_mint, pack_mint(mollusk, 100_000_000)),
+        (reward_mint, pack_mint(mollusk, 100_000_000)),
+        (farm, farm_account),
+        (
+            stake_vault,
+            pack_token_account(mollusk, stake_mint, farm, total_staked),
+        ),
+        (
+            reward_vault,
+            pack_token_account(mollusk, reward_mint, farm, 1_000 * reward_rate),
+        ),
+        (
+            user_stake,
+            pack_token_account(mollusk, stake_mint, user, 1_000_000),
+        ),
+        (
+            user_reward,
+            pack_token_account(mollusk, reward_mint, user, 0),
+        ),
+        (
+            authority_reward,
+            pack_token_account(mollusk, reward_mint, authority, 1_000_000),
+        ),
+        (position, position_account),
+        (system_program, system_account),
+        (token_program, token_account),
+    ];
+
+    FarmFixture {
+        authority,
+        user,
+        stake_mint,
+        reward_mint,
+        farm,
+        stake_vault,
+        reward_vault,
+        user_stake,
+        user_reward,
+        authority_reward,
+        position,
+        position_bump,
+        accounts,
+    }
+}
+
+impl FarmFixture {
+    pub fn stake_instruction(&self, amount: u64) -> Instruction {
+        let instruction_data = StakeInstructionData {
+            amount: amount.to_le_bytes(),
+            position_bump: self.position_bump,
+        };
+        let mut ser_instruction_data = vec![1];
+        ser_instruction_data.extend_from_slice(bytemuck::bytes_of(&instruction_data));
+        Instruction::new_with_bytes(
+            PROGRAM,
+            &ser_instruction_data,
+            vec![
+                AccountMeta::new(self.user, true),
+                AccountMeta::new(self.farm, false),
+                AccountMeta::new_readonly(self.stake_mint, false),
+                AccountMeta::new(self.user_stake, false),
+                AccountMeta::new(self.stake_vault, false),
+                AccountMeta::new(self.position, false),
+                AccountMeta::new_readonly(self.accounts[11].0, false),
+                AccountMeta::new_readonly(self.accounts[12].0, false),
+            ],
+        )
+    }
+
+    pub fn unstake_instruction(&self, amount: u64) -> Instruction {
+        let instruction_data = UnstakeInstructionData {
+            amount: amount.to_le_bytes(),
+        };
+        let mut ser_instruction_data = vec![2];
+        ser_instruction_data.extend_from_slice(bytemuck::bytes_of(&instruction_data));
+        Instruction::new_with_bytes(
+            PROGRAM,
+            &ser_instruction_data,
+            vec![
+                AccountMeta::new(self.user, true),
+                AccountMeta::new(self.farm, false),
+                AccountMeta::new_readonly(self.stake_mint, false),
+                AccountMeta::new(self.user_stake, false),
+                AccountMeta::new(self.stake_vault, false),
+                AccountMeta::new(self.position, false),
+                AccountMeta::new_readonly(self.accounts[12].0, false),
+            ],
+        )
+    }
+
+    pub fn claim_instruction(&self) -> Instruction {
+        Instruction::new_with_bytes(
+            PROGRAM,
+            &[3],
+            vec![
+                AccountMeta::new(self.user, true),
+                AccountMeta::new(self.farm, false),
+                AccountMeta::new_readonly(self.reward_mint, false),
+                AccountMeta::new(self.user_reward, false),
+                AccountMeta::new(self.reward_vault, false),
+                AccountMeta::new(self.position, false),
+                AccountMeta::new_readonly(self.accounts[12].0, false),
+            ],
+        )
+    }
+
+    pub fn fund_rewards_instruction(&self, amount: u64, duration: i64) -> Instruction {
+        let instruction_data = FundRewardsInstructionData {
+            amount: amount.to_le_bytes(),
+            duration: duration.to_le_bytes(),
+        };
+        let mut ser_instruction_data = vec![4];
+        ser_instruction_data.extend_from_slice(bytemuck::bytes_of(&instruction_data));
+        Instruction::new_with_bytes(
+            PROGRAM,
+            &ser_instruction_data,
+            vec![
+                AccountMeta::new(self.authority, true),
+                AccountMeta::new(self.farm, false),
+                AccountMeta::new_readonly(self.reward_mint, false),
+                AccountMeta::new(self.authority_reward, false),
+                AccountMeta::new(self.reward_vault, false),
+                AccountMeta::new_readonly(self.accounts[12].0, false),
+            ],
+        )
+    }
+}
+
+#[test]
+fn test_initialize_farm() {
+    let mollusk = mollusk();
+
+    let (system_program, system_account) = mollusk_svm::program::keyed_account_for_system_program();
+    let (token_program, token_account) = (
+        spl_token::ID,
+        program::create_program_account_loader_v3(&spl_token::ID),
+    );
+
+    let authority = Pubkey::new_from_array([0x01; 32]);
+    let stake_mint = Pubkey::new_from_array([0x02; 32]);
+    let reward_mint = Pubkey::new_from_array([0x03; 32]);
+
+    // The farm and its vaults are PDAs created by the program.
+    let (farm, farm_bump) = solana_sdk::pubkey::Pubkey::find_program_address(
+        &[
+            FARM_SEED.as_bytes(),
+            authority.as_ref(),
+            stake_mint.as_ref(),
+            reward_mint.as_ref(),
+        ],
+        &PROGRAM,
+    );
+    let (stake_vault, stake_vault_bump) = solana_sdk::pubkey::Pubkey::find_program_address(
+        &[VAULT_SEED.as_bytes(), farm.as_ref(), stake_mint.as_ref()],
+        &PROGRAM,
+    );
+    let (reward_vault, reward_vault_bump) = solana_sdk::pubkey::Pubkey::find_program_address(
+        &[VAULT_SEED.as_bytes(), farm.as_ref(), reward_mint.as_ref()],
+        &PROGRAM,
+    );
+
+    let instruction_data = InitializeFarmInstructionData {
+        farm_bump,
+        stake_vault_bump,
+        reward_vault_bump,
+    };
+    let mut ser_instruction_data = vec![0];
+    ser_instruction_data.extend_from_slice(bytemuck::bytes_of(&instruction_data));
+
+    let instruction = Instruction::new_with_bytes(
+        PROGRAM,
+        &ser_instruction_data,
+        vec![
+            AccountMeta::new(authority, true),
+            AccountMeta::new_readonly(stake_mint, false),
+            AccountMeta::new_readonly(reward_mint, false),
+            AccountMeta::new(farm, false),
+            AccountMeta::new(stake_vault, false),
+            AccountMeta::new(reward_vault, false),
+            AccountMeta::new_readonly(system_program, false),
+            AccountMeta::new_readonly(token_program, false),
+        ],
+    );
+
+    let accounts = vec![
+        (
+            authority,
+            Account::new(1 * LAMPORTS_PER_SOL, 0, &system_program),
+        ),
+        (stake_mint, pack_mint(&mollusk, 100_000_000)),
+        (reward_mint, pack_mint(&mollusk, 100_000_000)),
+        (farm, Account::new(0, 0, &system_program)),
+        (stake_vault, Account::new(0, 0, &system_program)),
+        (reward_vault, Account::new(0, 0, &system_program)),
+        (system_program, system_account),
+        (token_program, token_account),
+    ];
+    let result = mollusk.process_and_validate_instruction(
+        &instruction,
+        &accounts,
+        &[
+            Check::success(),
+            Check::account(&farm).owner(&PROGRAM).build(),
+        ],
+    );
+
+    let farm_state = *bytemuck::from_bytes::<Farm>(&result.get_account(&farm).unwrap().data);
+    assert_eq!(farm_state.authority, *authority.as_array());
+    assert_eq!(farm_state.stake_vault, *stake_vault.as_array());
+    assert_eq!(farm_state.reward_vault, *reward_vault.as_array());
+    assert_eq!(farm_state.farm_bump, farm_bump);
+    assert_eq!(i64::from_le_bytes(farm_state.last_update_timestamp), NOW);
+    assert_eq!(u64::from_le_bytes(farm_state.reward_rate), 0);
+
+    for (vault, mint) in [(stake_vault, stake_mint), (reward_vault, reward_mint)] {
+        let vault_state =
+            spl_token::state::Account::unpack(&result.get_account(&vault).unwrap().data).unwrap();
+        assert_eq!(vault_state.mint, mint);
+        assert_eq!(vault_state.owner, farm);
+    }
+
+    // The farm of the same mints derives from another address for another authority.
+    let mut instruction = instruction;
+    let mut accounts = accounts;
+    let other_authority = Pubkey::new_from_array([0x04; 32]);
+    instruction.accounts[0].pubkey = other_authority;
+    accounts[0].0 = other_authority;
+    mollusk.process_and_validate_instruction(
+        &instruction,
+        &accounts,
+        &[Check::err(ProgramError::InvalidAccountData)],
+    );
+}
+
+#[test]
+fn test_stake() {
+    let mut mollusk = mollusk();
+
+    // The user opens a position in a farm where 1_000 tokens are already staked.
+    let farm = farm_fixture(&mollusk, 1_000, 0, 10, |_| {});
+    mollusk.sysvars.clock.unix_timestamp = NOW + 100;
+
+    let result = mollusk.process_and_validate_instruction(
+        &farm.stake_instruction(3_000),
+        &farm.accounts,
+        &[
+            Check::success(),
+            Check::account(&farm.position).owner(&PROGRAM).build(),
+        ],
+    );
+
+    assert_eq!(
+        token_amount(result.get_account(&farm.stake_vault).unwrap()),
+        4_000
+    );
+    let farm_state = *bytemuck::from_bytes::<Farm>(&result.get_account(&farm.farm).unwrap().data);
+    assert_eq!(u64::from_le_bytes(farm_state.total_staked), 4_000);
+    // The first 100 seconds of rewards went to the 1_000 tokens staked before.
+    assert_eq!(u128::from_le_bytes(farm_state.reward_per_share), 1 << 64);
+    assert_eq!(
+        i64::from_le_bytes(farm_state.last_update_timestamp),
+        NOW + 100
+    );
+
+    // The new position only earns from now on.
+    let position_state =
+        *bytemuck::from_bytes::<Position>(&result.get_account(&farm.position).unwrap().data);
+    assert_eq!(position_state.owner, *farm.user.as_array());
+    assert_eq!(position_state.farm, *farm.farm.as_array());
+    assert_eq!(u64::from_le_bytes(position_state.amount), 3_000);
+    assert_eq!(u128::from_le_bytes(position_state.reward_debt), 3_000);
+    assert_eq!(u64::from_le_bytes(position_state.pending_rewards), 0);
+
+    // Lamports sent to the position address ahead of time don't keep the user from opening it.
+    let mut accounts = farm.accounts.clone();
+    accounts[10].1 = Account::new(1, 0, &accounts[11].0);
+    let result = mollusk.process_and_validate_instruction(
+        &farm.stake_instruction(3_000),
+        &accounts,
+        &[
+            Check::success(),
+            Check::account(&farm.position)
+                .owner(&PROGRAM)
+                .lamports(mollusk.sysvars.rent.minimum_balance(Position::LEN))
+                .space(Position::LEN)
+                .build(),
+        ],
+    );
+    let position_state =
+        *bytemuck::from_bytes::<Position>(&result.get_account(&farm.position).unwrap().data);
+    assert_eq!(u64::from_le_bytes(position_state.amount), 3_000);
+
+    // Nothing to stake.
+    mollusk.process_and_validate_instruction(
+        &farm.stake_instruction(0),
+        &farm.accounts,
+        &[Check::err(ProgramError::Custom(
+            CustomError::InvalidAmount as u32,
+        ))],
+    );
+}
+
+#[test]
+fn test_claim() {
+    let mut mollusk = mollusk();
+
+    // The user holds a quarter of the stake.
+    let farm = farm_fixture(&mollusk, 4_000, 1_000, 10, |_| {});
+    mollusk.sysvars.clock.unix_timestamp = NOW + 100;
+
+    let result = mollusk.process_and_validate_instruction(
+        &farm.claim_instruction(),
+        &farm.accounts,
+        &[Check::success()],
+    );
+
+    assert_eq!(
+        token_amount(result.get_account(&farm.user_reward).unwrap()),
+        250
+    );
+    let position_state =
+        *bytemuck::from_bytes::<Position>(&result.get_account(&farm.position).unwrap().data);
+    assert_eq!(u64::from_le_bytes(position_state.pending_rewards), 0);
+
+    // Claiming again in the same second pays nothing more.
+    let accounts = farm
+        .accounts
+        .iter()
+        .map(|(key, account)| (*key, result.get_account(key).unwrap_or(account).clone()))
+        .collect::<Vec<_>>();
+    let result = mollusk.process_and_validate_instruction(
+        &farm.claim_instruction(),
+        &accounts,
+        &[Check::success()],
+    );
+    assert_eq!(
+        token_amount(result.get_account(&farm.user_reward).unwrap()),
+        250
+    );
+
+    // Rewards stop at the end of the reward period.
+    mollusk.sysvars.clock.unix_timestamp = NOW + 5_000;
+    let result = mollusk.process_and_validate_instruction(
+        &farm.claim_instruction(),
+        &accounts,
+        &[Check::success()],
+    );
+    assert_eq!(
+        token_amount(result.get_account(&farm.user_reward).unwrap()),
+        2_500
+    );
+
+    // Another user's position is rejected.
+    let mut accounts = farm.accounts.clone();
+    let mut position_state = *bytemuck::from_bytes::<Position>(&accounts[10].1.data);
+    position_state.owner = *farm.authority.as_array();
+    accounts[10].1.data = bytemuck::bytes_of(&position_state).to_vec();
+    mollusk.process_and_validate_instruction(
+        &farm.claim_instruction(),
+        &accounts,
+        &[Check::err(ProgramError::InvalidAccountData)],
+    );
+}
+
+#[test]
+fn test_claim_large_reward_per_share() {
+    let mut mollusk = mollusk();
+
+    // After a long run the index is at 2^33 reward tokens per share, so that the position's share
+    // of it is far beyond 128 bits in Q64.64.
+    let staked = 4_000_000_000;
+    let reward_per_share: u128 = 1 << 97;
+    let mut farm = farm_fixture(&mollusk, staked, staked, 10, |farm| {
+        farm.reward_per_share = reward_per_share.to_le_bytes();
+    });
+    let mut position_state = *bytemuck::from_bytes::<Position>(&farm.accounts[10].1.data);
+    position_state.reward_debt = (staked as u128 * (reward_per_share >> 64)).to_le_bytes();
+    farm.accounts[10].1.data = bytemuck::bytes_of(&position_state).to_vec();
+    mollusk.sysvars.clock.unix_timestamp = NOW + 100;
+
+    let result = mollusk.process_and_validate_instruction(
+        &farm.claim_instruction(),
+        &farm.accounts,
+        &[Check::success()],
+    );
+
+    // The 1_000 rewards paid since are rounded down.
+    assert_eq!(
+        token_amount(result.get_account(&farm.user_reward).unwrap()),
+        999
+    );
+}
+
+#[test]
+fn test_unstake() {
+    let mut mollusk = mollusk();
+
+    let farm = farm_fixture(&mollusk, 1_000, 1_000, 10, |_| {});
+    mollusk.sysvars.clock.unix_timestamp = NOW + 100;
+
+    let result = mollusk.process_and_validate_instruction(
+        &farm.unstake_instruction(400),
+        &farm.accounts,
+        &[Check::success()],
+    );
+
+    assert_eq!(
+        token_amount(result.get_account(&farm.user_stake).unwrap()),
+        1_000_400
+    );
+    assert_eq!(
+        token_amount(result.get_account(&farm.stake_vault).unwrap()),
+        600
+    );
+    let farm_state = *bytemuck::from_bytes::<Farm>(&result.get_account(&farm.farm).unwrap().data);
+    assert_eq!(u64::from_le_bytes(farm_state.total_staked), 600);
+
+    // The rewards earned so far stay pending in the position.
+    let position_state =
+        *bytemuck::from_bytes::<Position>(&result.get_account(&farm.position).unwrap().data);
+    assert_eq!(u64::from_le_bytes(position_state.amount), 600);
+    assert_eq!(u64::from_le_bytes(position_state.pending_rewards), 1_000);
+
+    // More than the position holds.
+    mollusk.process_and_validate_instruction(
+        &farm.unstake_instruction(1_001),
+        &farm.accounts,
+        &[Check::err(ProgramError::Custom(
+            CustomError::InsufficientStake as u32,
+        ))],
+    );
+}
+
+#[test]
+fn test_fund_rewards() {
+    let mut mollusk = mollusk();
+
+    let farm = farm_fixture(&mollusk, 1_000, 1_000, 10, |_| {});
+    mollusk.sysvars.clock.unix_timestamp = NOW + 900;
+
+    // 1_000 rewards are left from the current period, paid along with the new ones.
+    let result = mollusk.process_and_validate_instruction(
+        &farm.fund_rewards_instruction(9_000, 500),
+        &farm.accounts,
+        &[Check::success()],
+    );
+
+    assert_eq!(
+        token_amount(result.get_account(&farm.reward_vault).unwrap()),
+        19_000
+    );
+    let farm_state = *bytemuck::from_bytes::<Farm>(&result.get_account(&farm.farm).unwrap().data);
+    assert_eq!(u64::from_le_bytes(farm_state.reward_rate), 20);
+    assert_eq!(i64::from_le_bytes(farm_state.end_timestamp), NOW + 1_400);
+    // The current period was accrued at its own rate.
+    assert_eq!(u128::from_le_bytes(farm_state.reward_per_share), 9 << 64);
+
+    // Only the authority can fund the farm.
+    let mut accounts = farm.accounts.clone();
+    let mut farm_state = *bytemuck::from_bytes::<Farm>(&accounts[4].1.data);
+    farm_state.authority = *farm.user.as_array();
+    accounts[4].1.data = bytemuck::bytes_of(&farm_state).to_vec();
+    mollusk.process_and_validate_instruction(
+        &farm.fund_rewards_instruction(9_000, 500),
+        &accounts,
+        &[Check::err(ProgramError::IncorrectAuthority)],
+    );
+
+    // Rewards need a period to be paid over.
+    mollusk.process_and_validate_instruction(
+        &farm.fund_rewards_instruction(9_000, 0),
+        &farm.accounts,
+        &[Check::err(ProgramError::Custom(
+            CustomError::InvalidRewardDuration as u32,
+        ))],
+    );
+}